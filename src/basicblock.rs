@@ -15,6 +15,23 @@ pub enum EdgeType {
     ConditionalFallthrough,
     /// This is edge is always taken.
     Unconditional,
+    /// This edge is when a call returns to the next instruction.
+    CallFallthrough,
+    /// This edge is to a known target of an indirect jump.
+    IndirectJump,
+    /// This edge is taken by a multi-way branch, such as a switch or
+    /// jump table, when the value being tested is the case value.
+    SwitchCase(i64),
+    /// This edge is taken by a multi-way branch when the value being
+    /// tested does not match any of the cases.
+    SwitchDefault,
+    /// This edge is to an exception handler or landing pad.
+    Exception,
+    /// This edge leads from a block ending in a tail call to the
+    /// virtual exit block.
+    TailCall,
+    /// This edge leads from an exit block to the virtual exit block.
+    Exit,
 }
 
 /// A [basic block] is a sequence of instructions with no inward-bound
@@ -4,26 +4,145 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+extern crate object;
 extern crate rbpf;
 
+use self::object::{
+    Architecture, Object, ObjectSection, ObjectSymbol, RelocationTarget, SectionKind, SymbolKind,
+};
 use self::rbpf::{disassembler, ebpf};
 use super::address::Address;
 use super::function::Function;
 use super::instruction::Instruction;
+use super::module::Module;
+use super::operand::{MemoryOperand, Operand, Register};
 use super::symbol::Symbol;
+use std::collections::HashMap;
 use std::fmt;
+use std::fs;
+use std::path::Path;
 
 /// A representation of an eBPF instruction.
 #[derive(Debug)]
 pub struct BpfInstruction {
     idx: u64,
     insn: disassembler::HLInsn,
+    relocation: Option<String>,
+    /// The function called by a BPF-to-BPF call that is relocated
+    /// against a symbol in a code section.
+    callee: Option<Address>,
 }
 
+/// The source register of a call to another BPF function, where the
+/// immediate is the offset of the function.
+const BPF_PSEUDO_CALL: u8 = 1;
+
+/// The source register of a call to a kernel function, where the
+/// immediate is the BTF id of the function.
+const BPF_PSEUDO_KFUNC_CALL: u8 = 2;
+
+/// The id of the `bpf_tail_call` helper.
+const BPF_FUNC_TAIL_CALL: i64 = 12;
+
 impl BpfInstruction {
     /// Wrap an `HLInsn` as a `BPFInstruction`.
+    ///
+    /// The `idx` is the slot of the instruction within the program.
+    /// A 64-bit immediate load occupies two slots, so the instruction
+    /// after it is at `idx + 2`.
     pub fn new(idx: u64, insn: disassembler::HLInsn) -> Self {
-        BpfInstruction { idx, insn }
+        BpfInstruction {
+            idx,
+            insn,
+            relocation: None,
+            callee: None,
+        }
+    }
+
+    /// The name of the symbol that this instruction is relocated against
+    /// when it was loaded from an ELF object.
+    ///
+    /// For a 64-bit immediate load, this is usually a map. For a call,
+    /// it is the function that is called.
+    pub fn relocation(&self) -> Option<&str> {
+        self.relocation.as_deref()
+    }
+
+    /// Is this a call to a BPF helper function, where the immediate is
+    /// the id of the helper?
+    pub fn is_helper_call(&self) -> bool {
+        self.insn.opc == ebpf::CALL && self.insn.src == 0
+    }
+
+    /// Is this a BPF-to-BPF call to another function within the program?
+    pub fn is_bpf_call(&self) -> bool {
+        self.insn.opc == ebpf::CALL && self.insn.src == BPF_PSEUDO_CALL
+    }
+
+    /// Is this a call to a kernel function (a kfunc), where the
+    /// immediate is the BTF id of the function?
+    pub fn is_kfunc_call(&self) -> bool {
+        self.insn.opc == ebpf::CALL && self.insn.src == BPF_PSEUDO_KFUNC_CALL
+    }
+
+    /// Is this a tail call to another program, either through the
+    /// `bpf_tail_call` helper or the dedicated opcode?
+    fn is_bpf_tail_call(&self) -> bool {
+        self.insn.opc == ebpf::TAIL_CALL
+            || (self.is_helper_call() && self.insn.imm == BPF_FUNC_TAIL_CALL)
+    }
+
+    fn class(&self) -> u8 {
+        self.insn.opc & 0x07
+    }
+
+    fn dst(&self) -> Register {
+        Register::Machine(u32::from(self.insn.dst))
+    }
+
+    fn src(&self) -> Register {
+        Register::Machine(u32::from(self.insn.src))
+    }
+
+    /// Does this instruction use the source register rather than
+    /// the immediate value?
+    fn uses_src(&self) -> bool {
+        (self.insn.opc & ebpf::BPF_X) == ebpf::BPF_X
+    }
+
+    /// The number of bytes accessed by a load or store.
+    fn access_size(&self) -> u32 {
+        match self.insn.opc & 0x18 {
+            ebpf::BPF_W => 4,
+            ebpf::BPF_H => 2,
+            ebpf::BPF_B => 1,
+            _ => 8,
+        }
+    }
+
+    fn memory_operand(&self, base: Register) -> Operand {
+        Operand::Memory(MemoryOperand {
+            base: Some(base),
+            index: None,
+            scale: 1,
+            displacement: i64::from(self.insn.off),
+            size: Some(self.access_size()),
+        })
+    }
+
+    /// The source operand for ALU and jump instructions.
+    fn src_operand(&self) -> Operand {
+        if self.uses_src() {
+            Operand::Register(self.src())
+        } else {
+            Operand::Immediate(self.insn.imm)
+        }
+    }
+
+    /// Is this one of the legacy packet access instructions?
+    fn is_packet_load(&self) -> bool {
+        self.class() == ebpf::BPF_LD
+            && matches!(self.insn.opc & 0xe0, ebpf::BPF_ABS | ebpf::BPF_IND)
     }
 }
 
@@ -33,26 +152,174 @@ impl Instruction for BpfInstruction {
     }
 
     fn comment(&self) -> Option<String> {
-        None
+        if self.is_helper_call() {
+            helper(self.insn.imm).map(|signature| signature.to_owned())
+        } else if self.is_kfunc_call() {
+            Some(match self.relocation {
+                Some(ref name) => format!("kfunc {}", name),
+                None => format!("kfunc btf_id={}", self.insn.imm),
+            })
+        } else {
+            self.relocation.clone()
+        }
+    }
+
+    fn operands(&self) -> Vec<Operand> {
+        match self.class() {
+            ebpf::BPF_LD if self.is_packet_load() => {
+                let mut operands = vec![];
+                if (self.insn.opc & 0xe0) == ebpf::BPF_IND {
+                    operands.push(Operand::Register(self.src()));
+                }
+                operands.push(Operand::Immediate(self.insn.imm));
+                operands
+            }
+            ebpf::BPF_LD => vec![
+                Operand::Register(self.dst()),
+                Operand::Immediate(self.insn.imm),
+            ],
+            ebpf::BPF_LDX => vec![
+                Operand::Register(self.dst()),
+                self.memory_operand(self.src()),
+            ],
+            ebpf::BPF_ST => vec![
+                self.memory_operand(self.dst()),
+                Operand::Immediate(self.insn.imm),
+            ],
+            ebpf::BPF_STX => vec![
+                self.memory_operand(self.dst()),
+                Operand::Register(self.src()),
+            ],
+            ebpf::BPF_ALU | ebpf::BPF_ALU64 => match self.insn.opc & 0xf0 {
+                ebpf::BPF_NEG => vec![Operand::Register(self.dst())],
+                ebpf::BPF_END => vec![
+                    Operand::Register(self.dst()),
+                    Operand::Immediate(self.insn.imm),
+                ],
+                _ => vec![Operand::Register(self.dst()), self.src_operand()],
+            },
+            _ => {
+                if self.is_bpf_call() {
+                    self.target_address()
+                        .map(Operand::Address)
+                        .into_iter()
+                        .collect()
+                } else if self.insn.opc == ebpf::CALL {
+                    vec![Operand::Immediate(self.insn.imm)]
+                } else if let Some(target) = self.target_address() {
+                    if self.is_local_conditional_jump() {
+                        vec![
+                            Operand::Register(self.dst()),
+                            self.src_operand(),
+                            Operand::Address(target),
+                        ]
+                    } else {
+                        vec![Operand::Address(target)]
+                    }
+                } else {
+                    vec![]
+                }
+            }
+        }
+    }
+
+    fn registers_read(&self) -> Vec<Register> {
+        match self.class() {
+            ebpf::BPF_LD if self.is_packet_load() => {
+                // These implicitly read the packet context from r6.
+                let mut regs = vec![Register::Machine(6)];
+                if (self.insn.opc & 0xe0) == ebpf::BPF_IND {
+                    regs.push(self.src());
+                }
+                regs
+            }
+            ebpf::BPF_LD => vec![],
+            ebpf::BPF_LDX => vec![self.src()],
+            ebpf::BPF_ST => vec![self.dst()],
+            ebpf::BPF_STX => vec![self.dst(), self.src()],
+            ebpf::BPF_ALU | ebpf::BPF_ALU64 => match self.insn.opc & 0xf0 {
+                ebpf::BPF_MOV if self.uses_src() => vec![self.src()],
+                ebpf::BPF_MOV => vec![],
+                ebpf::BPF_NEG | ebpf::BPF_END => vec![self.dst()],
+                _ if self.uses_src() => vec![self.dst(), self.src()],
+                _ => vec![self.dst()],
+            },
+            _ => {
+                if self.insn.opc == ebpf::CALL {
+                    // Functions take their arguments in r1 through r5.
+                    (1..=5).map(Register::Machine).collect()
+                } else if self.insn.opc == ebpf::TAIL_CALL {
+                    (1..=3).map(Register::Machine).collect()
+                } else if self.insn.opc == ebpf::EXIT {
+                    vec![Register::Machine(0)]
+                } else if self.is_local_conditional_jump() {
+                    if self.uses_src() {
+                        vec![self.dst(), self.src()]
+                    } else {
+                        vec![self.dst()]
+                    }
+                } else {
+                    vec![]
+                }
+            }
+        }
+    }
+
+    fn registers_written(&self) -> Vec<Register> {
+        match self.class() {
+            ebpf::BPF_LD if self.is_packet_load() => {
+                // r1 through r5 are also clobbered.
+                (0..=5).map(Register::Machine).collect()
+            }
+            ebpf::BPF_LD | ebpf::BPF_LDX | ebpf::BPF_ALU | ebpf::BPF_ALU64 => vec![self.dst()],
+            _ => {
+                if self.insn.opc == ebpf::CALL || self.insn.opc == ebpf::TAIL_CALL {
+                    // The result is in r0 and r1 through r5 are clobbered.
+                    // Execution only continues past a tail call when it
+                    // fails, which clobbers the same registers.
+                    (0..=5).map(Register::Machine).collect()
+                } else {
+                    vec![]
+                }
+            }
+        }
+    }
+
+    fn reads_memory(&self) -> bool {
+        match self.class() {
+            ebpf::BPF_LDX => true,
+            ebpf::BPF_LD => self.is_packet_load(),
+            // Atomic adds read the old value before writing the new one.
+            ebpf::BPF_STX => (self.insn.opc & 0xe0) == ebpf::BPF_XADD,
+            _ => false,
+        }
+    }
+
+    fn writes_memory(&self) -> bool {
+        matches!(self.class(), ebpf::BPF_ST | ebpf::BPF_STX)
     }
 
     fn mnemonic(&self) -> &str {
-        &*self.insn.name
+        &self.insn.name
     }
 
     fn is_call(&self) -> bool {
-        self.insn.opc == ebpf::CALL
+        self.insn.opc == ebpf::CALL || self.insn.opc == ebpf::TAIL_CALL
+    }
+
+    fn is_conditional_tail_call(&self) -> bool {
+        // The tail call fails if there is no program at the index, and
+        // then execution continues with the next instruction.
+        self.is_bpf_tail_call()
     }
 
     fn is_local_conditional_jump(&self) -> bool {
-        self.is_local_jump() && (self.insn.opc != ebpf::JA)
+        self.is_local_jump() && (self.insn.opc & 0xf0) != ebpf::BPF_JA
     }
 
     fn is_local_jump(&self) -> bool {
-        (self.insn.opc & 0x07) == ebpf::BPF_JMP
-            && (self.insn.opc != ebpf::CALL)
-            && (self.insn.opc != ebpf::TAIL_CALL)
-            && (self.insn.opc != ebpf::EXIT)
+        matches!(self.class(), ebpf::BPF_JMP | ebpf::BPF_JMP32)
+            && !matches!(self.insn.opc & 0xf0, ebpf::BPF_CALL | ebpf::BPF_EXIT)
     }
 
     fn is_return(&self) -> bool {
@@ -60,10 +327,25 @@ impl Instruction for BpfInstruction {
     }
 
     fn target_address(&self) -> Option<Address> {
-        if self.is_local_jump() {
-            Some(Address::new(
-                (self.idx as i64 + i64::from(self.insn.off)) as u64,
-            ))
+        if self.is_bpf_call() {
+            // A relocated call is relative to its symbol rather than to this
+            // instruction, so its target is only known when the symbol is
+            // in a code section. Otherwise, the offset is from the next
+            // instruction.
+            if self.relocation.is_some() {
+                self.callee
+            } else {
+                Some(Address::new((self.idx as i64 + self.insn.imm + 1) as u64))
+            }
+        } else if self.is_local_jump() {
+            // The offset is from the next instruction. An unconditional
+            // jump in the 32-bit class has a wider offset in the immediate.
+            let offset = if self.insn.opc == ebpf::BPF_JMP32 | ebpf::BPF_JA {
+                self.insn.imm
+            } else {
+                i64::from(self.insn.off)
+            };
+            Some(Address::new((self.idx as i64 + offset + 1) as u64))
         } else {
             None
         }
@@ -72,14 +354,167 @@ impl Instruction for BpfInstruction {
 
 impl Function<BpfInstruction> {
     /// Create a function from eBPF bytecode.
+    ///
+    /// Instructions are addressed by their slot, so that the targets of
+    /// jumps and calls are the addresses of instructions.
     pub fn from_bpf(symbol: Symbol, data: &[u8]) -> Function<BpfInstruction> {
-        let v = rbpf::disassembler::to_insn_vec(data);
-        let is = v
-            .into_iter()
-            .enumerate()
-            .map(|(idx, insn)| BpfInstruction::new(idx as u64, insn))
+        Function::new(symbol, instructions(data, 0))
+    }
+}
+
+/// Decode eBPF bytecode, addressing the instructions by their slot
+/// from `base`.
+fn instructions(data: &[u8], base: u64) -> Vec<BpfInstruction> {
+    let mut slot = base;
+    disassembler::to_insn_vec(data)
+        .into_iter()
+        .map(|insn| {
+            let idx = slot;
+            slot += if insn.opc == ebpf::LD_DW_IMM { 2 } else { 1 };
+            BpfInstruction::new(idx, insn)
+        })
+        .collect()
+}
+
+/// The size of an instruction slot, in bytes. A 64-bit immediate load
+/// occupies two slots.
+const SLOT_SIZE: u64 = ebpf::INSN_SIZE as u64;
+
+impl Module<BpfInstruction> {
+    /// Load a module from an eBPF ELF object file, as produced by
+    /// `clang -target bpf`.
+    pub fn from_bpf_file<P: AsRef<Path>>(path: P) -> Option<Self> {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| Module::from_bpf_bytes(&bytes))
+    }
+
+    /// Load a module from the contents of an eBPF ELF object file.
+    ///
+    /// There is a function for each function symbol in an executable
+    /// section, covering both programs and the BPF-to-BPF subprograms
+    /// that they call. An executable section without any function
+    /// symbols becomes a single function named after the section.
+    ///
+    /// The sections are laid out one after another in the order of
+    /// the ELF file and instructions are addressed by their slot within
+    /// that layout, so that each has a unique address.
+    ///
+    /// Instructions with a relocation, such as a 64-bit immediate load
+    /// of a map, record the name of the symbol that they refer to. It
+    /// is given as their [`comment`]. A BPF-to-BPF call that is
+    /// relocated against a function calls that function.
+    ///
+    /// This returns `None` if the file is not a little-endian eBPF
+    /// object, as only those can be decoded.
+    ///
+    /// [`comment`]: Instruction::comment
+    pub fn from_bpf_bytes(bytes: &[u8]) -> Option<Self> {
+        let file = object::File::parse(bytes).ok()?;
+        if file.architecture() != Architecture::Bpf || !file.is_little_endian() {
+            return None;
+        }
+        let sections = file
+            .sections()
+            .filter(|section| section.kind() == SectionKind::Text)
             .collect::<Vec<_>>();
-        Function::new(symbol, is)
+        let mut bases = HashMap::new();
+        let mut base = 0;
+        for section in &sections {
+            bases.insert(section.index(), base);
+            base += section.size() / SLOT_SIZE;
+        }
+
+        // The function symbols, by address, to name the targets of calls.
+        let names = file
+            .symbols()
+            .filter(|symbol| symbol.kind() == SymbolKind::Text)
+            .filter_map(|symbol| {
+                let base = bases.get(&symbol.section_index()?)?;
+                let address = Address::new(base + symbol.address() / SLOT_SIZE);
+                Some((address, symbol.name().ok()?))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut functions = vec![];
+        for section in sections {
+            let base = bases[&section.index()];
+            let data = section.data().ok()?;
+            let slots = data.len() as u64 / SLOT_SIZE;
+
+            let mut relocations = HashMap::new();
+            for (offset, relocation) in section.relocations() {
+                let symbol = match relocation.target() {
+                    RelocationTarget::Symbol(index) => file.symbol_by_index(index).ok()?,
+                    _ => continue,
+                };
+                let name = match (symbol.kind(), symbol.section_index()) {
+                    (SymbolKind::Section, Some(index)) => {
+                        file.section_by_index(index).ok()?.name().ok()?
+                    }
+                    _ => symbol.name().ok()?,
+                };
+                // The slot of the symbol, when it is in a code section.
+                let slot = symbol
+                    .section_index()
+                    .and_then(|index| bases.get(&index))
+                    .map(|base| base + symbol.address() / SLOT_SIZE);
+                relocations.insert(offset / SLOT_SIZE, (name, slot));
+            }
+
+            let mut starts = file
+                .symbols()
+                .filter(|symbol| {
+                    symbol.kind() == SymbolKind::Text
+                        && symbol.section_index() == Some(section.index())
+                        && symbol.address() < slots * SLOT_SIZE
+                })
+                .map(|symbol| (symbol.address() / SLOT_SIZE, symbol))
+                .collect::<Vec<_>>();
+            starts.sort_by_key(|&(start, _)| start);
+            starts.dedup_by_key(|&mut (start, _)| start);
+
+            let mut ranges = vec![];
+            for (i, (start, symbol)) in starts.iter().enumerate() {
+                let next = starts.get(i + 1).map_or(slots, |&(next, _)| next);
+                let end = match symbol.size() / SLOT_SIZE {
+                    0 => next,
+                    size => (start + size).min(slots),
+                };
+                ranges.push((*start, end, symbol.name().ok()?));
+            }
+            if ranges.is_empty() && slots > 0 {
+                ranges.push((0, slots, section.name().ok()?));
+            }
+
+            for (start, end, name) in ranges {
+                let code = &data[(start * SLOT_SIZE) as usize..(end * SLOT_SIZE) as usize];
+                let mut is = instructions(code, base + start);
+                for inst in &mut is {
+                    if let Some(&(name, slot)) = relocations.get(&(inst.idx - base)) {
+                        inst.relocation = Some(name.to_owned());
+                        if let Some(slot) = slot.filter(|_| inst.is_bpf_call()) {
+                            // As in libbpf, the immediate is relative to the
+                            // symbol, which is often the section of a static
+                            // function rather than the function itself.
+                            let callee = Address::new((slot as i64 + inst.insn.imm + 1) as u64);
+                            inst.callee = Some(callee);
+                            if let Some(name) = names.get(&callee) {
+                                inst.relocation = Some((*name).to_owned());
+                            }
+                        }
+                    }
+                }
+                let symbol = Symbol::new(Address::new(base + start), Some(name));
+                functions.push(Function::new(symbol, is));
+            }
+        }
+        Some(Module {
+            functions,
+            imported_functions: vec![],
+            #[cfg(feature = "webassembly")]
+            declarations: Default::default(),
+        })
     }
 }
 
@@ -89,9 +524,243 @@ impl fmt::Display for BpfInstruction {
     }
 }
 
+/// The signature of a BPF helper function, given its id.
+fn helper(id: i64) -> Option<&'static str> {
+    usize::try_from(id)
+        .ok()
+        .and_then(|id| id.checked_sub(1))
+        .and_then(|index| HELPERS.get(index))
+        .copied()
+}
+
+/// The signatures of the BPF helper functions, in the order of their
+/// ids from `__BPF_FUNC_MAPPER` in `linux/bpf.h`, starting from 1.
+#[rustfmt::skip]
+static HELPERS: &[&str] = &[
+    "void *bpf_map_lookup_elem(struct bpf_map *map, const void *key)",
+    "long bpf_map_update_elem(struct bpf_map *map, const void *key, const void *value, u64 flags)",
+    "long bpf_map_delete_elem(struct bpf_map *map, const void *key)",
+    "long bpf_probe_read(void *dst, u32 size, const void *unsafe_ptr)",
+    "u64 bpf_ktime_get_ns(void)",
+    "long bpf_trace_printk(const char *fmt, u32 fmt_size, ...)",
+    "u32 bpf_get_prandom_u32(void)",
+    "u32 bpf_get_smp_processor_id(void)",
+    "long bpf_skb_store_bytes(struct sk_buff *skb, u32 offset, const void *from, u32 len, u64 flags)",
+    "long bpf_l3_csum_replace(struct sk_buff *skb, u32 offset, u64 from, u64 to, u64 size)",
+    "long bpf_l4_csum_replace(struct sk_buff *skb, u32 offset, u64 from, u64 to, u64 flags)",
+    "long bpf_tail_call(void *ctx, struct bpf_map *prog_array_map, u32 index)",
+    "long bpf_clone_redirect(struct sk_buff *skb, u32 ifindex, u64 flags)",
+    "u64 bpf_get_current_pid_tgid(void)",
+    "u64 bpf_get_current_uid_gid(void)",
+    "long bpf_get_current_comm(void *buf, u32 size_of_buf)",
+    "u32 bpf_get_cgroup_classid(struct sk_buff *skb)",
+    "long bpf_skb_vlan_push(struct sk_buff *skb, __be16 vlan_proto, u16 vlan_tci)",
+    "long bpf_skb_vlan_pop(struct sk_buff *skb)",
+    "long bpf_skb_get_tunnel_key(struct sk_buff *skb, struct bpf_tunnel_key *key, u32 size, u64 flags)",
+    "long bpf_skb_set_tunnel_key(struct sk_buff *skb, struct bpf_tunnel_key *key, u32 size, u64 flags)",
+    "u64 bpf_perf_event_read(struct bpf_map *map, u64 flags)",
+    "long bpf_redirect(u32 ifindex, u64 flags)",
+    "u32 bpf_get_route_realm(struct sk_buff *skb)",
+    "long bpf_perf_event_output(void *ctx, struct bpf_map *map, u64 flags, void *data, u64 size)",
+    "long bpf_skb_load_bytes(const void *skb, u32 offset, void *to, u32 len)",
+    "long bpf_get_stackid(void *ctx, struct bpf_map *map, u64 flags)",
+    "s64 bpf_csum_diff(__be32 *from, u32 from_size, __be32 *to, u32 to_size, __wsum seed)",
+    "long bpf_skb_get_tunnel_opt(struct sk_buff *skb, void *opt, u32 size)",
+    "long bpf_skb_set_tunnel_opt(struct sk_buff *skb, void *opt, u32 size)",
+    "long bpf_skb_change_proto(struct sk_buff *skb, __be16 proto, u64 flags)",
+    "long bpf_skb_change_type(struct sk_buff *skb, u32 type)",
+    "long bpf_skb_under_cgroup(struct sk_buff *skb, struct bpf_map *map, u32 index)",
+    "u32 bpf_get_hash_recalc(struct sk_buff *skb)",
+    "u64 bpf_get_current_task(void)",
+    "long bpf_probe_write_user(void *dst, const void *src, u32 len)",
+    "long bpf_current_task_under_cgroup(struct bpf_map *map, u32 index)",
+    "long bpf_skb_change_tail(struct sk_buff *skb, u32 len, u64 flags)",
+    "long bpf_skb_pull_data(struct sk_buff *skb, u32 len)",
+    "s64 bpf_csum_update(struct sk_buff *skb, __wsum csum)",
+    "void bpf_set_hash_invalid(struct sk_buff *skb)",
+    "long bpf_get_numa_node_id(void)",
+    "long bpf_skb_change_head(struct sk_buff *skb, u32 len, u64 flags)",
+    "long bpf_xdp_adjust_head(struct xdp_buff *xdp_md, int delta)",
+    "long bpf_probe_read_str(void *dst, u32 size, const void *unsafe_ptr)",
+    "u64 bpf_get_socket_cookie(struct sk_buff *skb)",
+    "u32 bpf_get_socket_uid(struct sk_buff *skb)",
+    "long bpf_set_hash(struct sk_buff *skb, u32 hash)",
+    "long bpf_setsockopt(void *bpf_socket, int level, int optname, void *optval, int optlen)",
+    "long bpf_skb_adjust_room(struct sk_buff *skb, s32 len_diff, u32 mode, u64 flags)",
+    "long bpf_redirect_map(struct bpf_map *map, u32 key, u64 flags)",
+    "long bpf_sk_redirect_map(struct sk_buff *skb, struct bpf_map *map, u32 key, u64 flags)",
+    "long bpf_sock_map_update(struct bpf_sock_ops *skops, struct bpf_map *map, void *key, u64 flags)",
+    "long bpf_xdp_adjust_meta(struct xdp_buff *xdp_md, int delta)",
+    "long bpf_perf_event_read_value(struct bpf_map *map, u64 flags, struct bpf_perf_event_value *buf, u32 buf_size)",
+    "long bpf_perf_prog_read_value(struct bpf_perf_event_data *ctx, struct bpf_perf_event_value *buf, u32 buf_size)",
+    "long bpf_getsockopt(void *bpf_socket, int level, int optname, void *optval, int optlen)",
+    "long bpf_override_return(struct pt_regs *regs, u64 rc)",
+    "long bpf_sock_ops_cb_flags_set(struct bpf_sock_ops *bpf_sock, int argval)",
+    "long bpf_msg_redirect_map(struct sk_msg_buff *msg, struct bpf_map *map, u32 key, u64 flags)",
+    "long bpf_msg_apply_bytes(struct sk_msg_buff *msg, u32 bytes)",
+    "long bpf_msg_cork_bytes(struct sk_msg_buff *msg, u32 bytes)",
+    "long bpf_msg_pull_data(struct sk_msg_buff *msg, u32 start, u32 end, u64 flags)",
+    "long bpf_bind(struct bpf_sock_addr *ctx, struct sockaddr *addr, int addr_len)",
+    "long bpf_xdp_adjust_tail(struct xdp_buff *xdp_md, int delta)",
+    "long bpf_skb_get_xfrm_state(struct sk_buff *skb, u32 index, struct bpf_xfrm_state *xfrm_state, u32 size, u64 flags)",
+    "long bpf_get_stack(void *ctx, void *buf, u32 size, u64 flags)",
+    "long bpf_skb_load_bytes_relative(const void *skb, u32 offset, void *to, u32 len, u32 start_header)",
+    "long bpf_fib_lookup(void *ctx, struct bpf_fib_lookup *params, int plen, u32 flags)",
+    "long bpf_sock_hash_update(struct bpf_sock_ops *skops, struct bpf_map *map, void *key, u64 flags)",
+    "long bpf_msg_redirect_hash(struct sk_msg_buff *msg, struct bpf_map *map, void *key, u64 flags)",
+    "long bpf_sk_redirect_hash(struct sk_buff *skb, struct bpf_map *map, void *key, u64 flags)",
+    "long bpf_lwt_push_encap(struct sk_buff *skb, u32 type, void *hdr, u32 len)",
+    "long bpf_lwt_seg6_store_bytes(struct sk_buff *skb, u32 offset, const void *from, u32 len)",
+    "long bpf_lwt_seg6_adjust_srh(struct sk_buff *skb, u32 offset, s32 delta)",
+    "long bpf_lwt_seg6_action(struct sk_buff *skb, u32 action, void *param, u32 param_len)",
+    "long bpf_rc_repeat(void *ctx)",
+    "long bpf_rc_keydown(void *ctx, u32 protocol, u64 scancode, u32 toggle)",
+    "u64 bpf_skb_cgroup_id(struct sk_buff *skb)",
+    "u64 bpf_get_current_cgroup_id(void)",
+    "void *bpf_get_local_storage(void *map, u64 flags)",
+    "long bpf_sk_select_reuseport(struct sk_reuseport_md *reuse, struct bpf_map *map, void *key, u64 flags)",
+    "u64 bpf_skb_ancestor_cgroup_id(struct sk_buff *skb, int ancestor_level)",
+    "struct bpf_sock *bpf_sk_lookup_tcp(void *ctx, struct bpf_sock_tuple *tuple, u32 tuple_size, u64 netns, u64 flags)",
+    "struct bpf_sock *bpf_sk_lookup_udp(void *ctx, struct bpf_sock_tuple *tuple, u32 tuple_size, u64 netns, u64 flags)",
+    "long bpf_sk_release(void *sock)",
+    "long bpf_map_push_elem(struct bpf_map *map, const void *value, u64 flags)",
+    "long bpf_map_pop_elem(struct bpf_map *map, void *value)",
+    "long bpf_map_peek_elem(struct bpf_map *map, void *value)",
+    "long bpf_msg_push_data(struct sk_msg_buff *msg, u32 start, u32 len, u64 flags)",
+    "long bpf_msg_pop_data(struct sk_msg_buff *msg, u32 start, u32 len, u64 flags)",
+    "long bpf_rc_pointer_rel(void *ctx, s32 rel_x, s32 rel_y)",
+    "long bpf_spin_lock(struct bpf_spin_lock *lock)",
+    "long bpf_spin_unlock(struct bpf_spin_lock *lock)",
+    "struct bpf_sock *bpf_sk_fullsock(struct bpf_sock *sk)",
+    "struct bpf_tcp_sock *bpf_tcp_sock(struct bpf_sock *sk)",
+    "long bpf_skb_ecn_set_ce(struct sk_buff *skb)",
+    "struct bpf_sock *bpf_get_listener_sock(struct bpf_sock *sk)",
+    "struct bpf_sock *bpf_skc_lookup_tcp(void *ctx, struct bpf_sock_tuple *tuple, u32 tuple_size, u64 netns, u64 flags)",
+    "long bpf_tcp_check_syncookie(void *sk, void *iph, u32 iph_len, struct tcphdr *th, u32 th_len)",
+    "long bpf_sysctl_get_name(struct bpf_sysctl *ctx, char *buf, size_t buf_len, u64 flags)",
+    "long bpf_sysctl_get_current_value(struct bpf_sysctl *ctx, char *buf, size_t buf_len)",
+    "long bpf_sysctl_get_new_value(struct bpf_sysctl *ctx, char *buf, size_t buf_len)",
+    "long bpf_sysctl_set_new_value(struct bpf_sysctl *ctx, const char *buf, size_t buf_len)",
+    "long bpf_strtol(const char *buf, size_t buf_len, u64 flags, long *res)",
+    "long bpf_strtoul(const char *buf, size_t buf_len, u64 flags, unsigned long *res)",
+    "void *bpf_sk_storage_get(struct bpf_map *map, void *sk, void *value, u64 flags)",
+    "long bpf_sk_storage_delete(struct bpf_map *map, void *sk)",
+    "long bpf_send_signal(u32 sig)",
+    "s64 bpf_tcp_gen_syncookie(void *sk, void *iph, u32 iph_len, struct tcphdr *th, u32 th_len)",
+    "long bpf_skb_output(void *ctx, struct bpf_map *map, u64 flags, void *data, u64 size)",
+    "long bpf_probe_read_user(void *dst, u32 size, const void *unsafe_ptr)",
+    "long bpf_probe_read_kernel(void *dst, u32 size, const void *unsafe_ptr)",
+    "long bpf_probe_read_user_str(void *dst, u32 size, const void *unsafe_ptr)",
+    "long bpf_probe_read_kernel_str(void *dst, u32 size, const void *unsafe_ptr)",
+    "long bpf_tcp_send_ack(void *tp, u32 rcv_nxt)",
+    "long bpf_send_signal_thread(u32 sig)",
+    "u64 bpf_jiffies64(void)",
+    "long bpf_read_branch_records(struct bpf_perf_event_data *ctx, void *buf, u32 size, u64 flags)",
+    "long bpf_get_ns_current_pid_tgid(u64 dev, u64 ino, struct bpf_pidns_info *nsdata, u32 size)",
+    "long bpf_xdp_output(void *ctx, struct bpf_map *map, u64 flags, void *data, u64 size)",
+    "u64 bpf_get_netns_cookie(void *ctx)",
+    "u64 bpf_get_current_ancestor_cgroup_id(int ancestor_level)",
+    "long bpf_sk_assign(struct sk_buff *skb, void *sk, u64 flags)",
+    "u64 bpf_ktime_get_boot_ns(void)",
+    "long bpf_seq_printf(struct seq_file *m, const char *fmt, u32 fmt_size, const void *data, u32 data_len)",
+    "long bpf_seq_write(struct seq_file *m, const void *data, u32 len)",
+    "u64 bpf_sk_cgroup_id(void *sk)",
+    "u64 bpf_sk_ancestor_cgroup_id(void *sk, int ancestor_level)",
+    "long bpf_ringbuf_output(void *ringbuf, void *data, u64 size, u64 flags)",
+    "void *bpf_ringbuf_reserve(void *ringbuf, u64 size, u64 flags)",
+    "void bpf_ringbuf_submit(void *data, u64 flags)",
+    "void bpf_ringbuf_discard(void *data, u64 flags)",
+    "u64 bpf_ringbuf_query(void *ringbuf, u64 flags)",
+    "long bpf_csum_level(struct sk_buff *skb, u64 level)",
+    "struct tcp6_sock *bpf_skc_to_tcp6_sock(void *sk)",
+    "struct tcp_sock *bpf_skc_to_tcp_sock(void *sk)",
+    "struct tcp_timewait_sock *bpf_skc_to_tcp_timewait_sock(void *sk)",
+    "struct tcp_request_sock *bpf_skc_to_tcp_request_sock(void *sk)",
+    "struct udp6_sock *bpf_skc_to_udp6_sock(void *sk)",
+    "long bpf_get_task_stack(struct task_struct *task, void *buf, u32 size, u64 flags)",
+    "long bpf_load_hdr_opt(struct bpf_sock_ops *skops, void *searchby_res, u32 len, u64 flags)",
+    "long bpf_store_hdr_opt(struct bpf_sock_ops *skops, const void *from, u32 len, u64 flags)",
+    "long bpf_reserve_hdr_opt(struct bpf_sock_ops *skops, u32 len, u64 flags)",
+    "void *bpf_inode_storage_get(struct bpf_map *map, void *inode, void *value, u64 flags)",
+    "int bpf_inode_storage_delete(struct bpf_map *map, void *inode)",
+    "long bpf_d_path(struct path *path, char *buf, u32 sz)",
+    "long bpf_copy_from_user(void *dst, u32 size, const void *user_ptr)",
+    "long bpf_snprintf_btf(char *str, u32 str_size, struct btf_ptr *ptr, u32 btf_ptr_size, u64 flags)",
+    "long bpf_seq_printf_btf(struct seq_file *m, struct btf_ptr *ptr, u32 ptr_size, u64 flags)",
+    "u64 bpf_skb_cgroup_classid(struct sk_buff *skb)",
+    "long bpf_redirect_neigh(u32 ifindex, struct bpf_redir_neigh *params, int plen, u64 flags)",
+    "void *bpf_per_cpu_ptr(const void *percpu_ptr, u32 cpu)",
+    "void *bpf_this_cpu_ptr(const void *percpu_ptr)",
+    "long bpf_redirect_peer(u32 ifindex, u64 flags)",
+    "void *bpf_task_storage_get(struct bpf_map *map, struct task_struct *task, void *value, u64 flags)",
+    "long bpf_task_storage_delete(struct bpf_map *map, struct task_struct *task)",
+    "struct task_struct *bpf_get_current_task_btf(void)",
+    "long bpf_bprm_opts_set(struct linux_binprm *bprm, u64 flags)",
+    "u64 bpf_ktime_get_coarse_ns(void)",
+    "long bpf_ima_inode_hash(struct inode *inode, void *dst, u32 size)",
+    "struct socket *bpf_sock_from_file(struct file *file)",
+    "long bpf_check_mtu(void *ctx, u32 ifindex, u32 *mtu_len, s32 len_diff, u64 flags)",
+    "long bpf_for_each_map_elem(struct bpf_map *map, void *callback_fn, void *callback_ctx, u64 flags)",
+    "long bpf_snprintf(char *str, u32 str_size, const char *fmt, u64 *data, u32 data_len)",
+    "long bpf_sys_bpf(u32 cmd, void *attr, u32 attr_size)",
+    "long bpf_btf_find_by_name_kind(char *name, int name_sz, u32 kind, int flags)",
+    "long bpf_sys_close(u32 fd)",
+    "long bpf_timer_init(struct bpf_timer *timer, struct bpf_map *map, u64 flags)",
+    "long bpf_timer_set_callback(struct bpf_timer *timer, void *callback_fn)",
+    "long bpf_timer_start(struct bpf_timer *timer, u64 nsecs, u64 flags)",
+    "long bpf_timer_cancel(struct bpf_timer *timer)",
+    "u64 bpf_get_func_ip(void *ctx)",
+    "u64 bpf_get_attach_cookie(void *ctx)",
+    "long bpf_task_pt_regs(struct task_struct *task)",
+    "long bpf_get_branch_snapshot(void *entries, u32 size, u64 flags)",
+    "long bpf_trace_vprintk(const char *fmt, u32 fmt_size, const void *data, u32 data_len)",
+    "struct unix_sock *bpf_skc_to_unix_sock(void *sk)",
+    "long bpf_kallsyms_lookup_name(const char *name, int name_sz, int flags, u64 *res)",
+    "long bpf_find_vma(struct task_struct *task, u64 addr, void *callback_fn, void *callback_ctx, u64 flags)",
+    "long bpf_loop(u32 nr_loops, void *callback_fn, void *callback_ctx, u64 flags)",
+    "long bpf_strncmp(const char *s1, u32 s1_sz, const char *s2)",
+    "long bpf_get_func_arg(void *ctx, u32 n, u64 *value)",
+    "long bpf_get_func_ret(void *ctx, u64 *value)",
+    "long bpf_get_func_arg_cnt(void *ctx)",
+    "int bpf_get_retval(void)",
+    "int bpf_set_retval(int retval)",
+    "u64 bpf_xdp_get_buff_len(struct xdp_buff *xdp_md)",
+    "long bpf_xdp_load_bytes(struct xdp_buff *xdp_md, u32 offset, void *buf, u32 len)",
+    "long bpf_xdp_store_bytes(struct xdp_buff *xdp_md, u32 offset, void *buf, u32 len)",
+    "long bpf_copy_from_user_task(void *dst, u32 size, const void *user_ptr, struct task_struct *tsk, u64 flags)",
+    "long bpf_skb_set_tstamp(struct sk_buff *skb, u64 tstamp, u32 tstamp_type)",
+    "long bpf_ima_file_hash(struct file *file, void *dst, u32 size)",
+    "void *bpf_kptr_xchg(void *map_value, void *ptr)",
+    "void *bpf_map_lookup_percpu_elem(struct bpf_map *map, const void *key, u32 cpu)",
+    "struct mptcp_sock *bpf_skc_to_mptcp_sock(void *sk)",
+    "long bpf_dynptr_from_mem(void *data, u32 size, u64 flags, struct bpf_dynptr *ptr)",
+    "long bpf_ringbuf_reserve_dynptr(void *ringbuf, u32 size, u64 flags, struct bpf_dynptr *ptr)",
+    "void bpf_ringbuf_submit_dynptr(struct bpf_dynptr *ptr, u64 flags)",
+    "void bpf_ringbuf_discard_dynptr(struct bpf_dynptr *ptr, u64 flags)",
+    "long bpf_dynptr_read(void *dst, u32 len, struct bpf_dynptr *src, u32 offset, u64 flags)",
+    "long bpf_dynptr_write(struct bpf_dynptr *dst, u32 offset, void *src, u32 len, u64 flags)",
+    "void *bpf_dynptr_data(struct bpf_dynptr *ptr, u32 offset, u32 len)",
+    "s64 bpf_tcp_raw_gen_syncookie_ipv4(struct iphdr *iph, struct tcphdr *th, u32 th_len)",
+    "s64 bpf_tcp_raw_gen_syncookie_ipv6(struct ipv6hdr *iph, struct tcphdr *th, u32 th_len)",
+    "long bpf_tcp_raw_check_syncookie_ipv4(struct iphdr *iph, struct tcphdr *th)",
+    "long bpf_tcp_raw_check_syncookie_ipv6(struct ipv6hdr *iph, struct tcphdr *th)",
+    "u64 bpf_ktime_get_tai_ns(void)",
+    "long bpf_user_ringbuf_drain(struct bpf_map *map, void *callback_fn, void *ctx, u64 flags)",
+    "void *bpf_cgrp_storage_get(struct bpf_map *map, struct cgroup *cgroup, void *value, u64 flags)",
+    "long bpf_cgrp_storage_delete(struct bpf_map *map, struct cgroup *cgroup)",
+];
+
 #[cfg(test)]
 mod tests {
-    use super::super::{Address, Function, Symbol};
+    use super::super::{
+        Address, CallGraphAnalysis, CallSite, CallSiteTarget, ExitType, Function, Instruction,
+        MemoryOperand, Module, Operand, Register, Symbol,
+    };
+    use super::object::write::{self, SymbolSection};
+    use super::object::{
+        elf, Architecture, BinaryFormat, Endianness, RelocationFlags, SectionKind, SymbolFlags,
+        SymbolKind, SymbolScope,
+    };
 
     #[test]
     fn test() {
@@ -112,4 +781,327 @@ mod tests {
         assert!(f.control_flow_graph.entry_block.is_some());
         assert_eq!(f.control_flow_graph.graph.node_count(), 3);
     }
+
+    #[test]
+    fn registers() {
+        let prog = &[
+            0xb7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // mov r0, 0
+            0x79, 0x12, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // load ptr from r1[0x40] to r2
+            0x07, 0x02, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, // add r2, 5
+            0x2d, 0x12, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, // if r2 > r1 skip 1 instruction
+            0x63, 0x0a, 0xfc, 0xff, 0x00, 0x00, 0x00, 0x00, // store r0 to r10[-4]
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+
+        let f = Function::from_bpf(Symbol::new(Address::new(100000), Some("test")), prog);
+        let r = Register::Machine;
+        let is = &f.instructions;
+
+        assert!(is[0].registers_read().is_empty());
+        assert_eq!(is[0].registers_written(), vec![r(0)]);
+
+        assert_eq!(is[1].registers_read(), vec![r(1)]);
+        assert_eq!(is[1].registers_written(), vec![r(2)]);
+        assert_eq!(
+            is[1].operands(),
+            vec![
+                Operand::Register(r(2)),
+                Operand::Memory(MemoryOperand {
+                    base: Some(r(1)),
+                    index: None,
+                    scale: 1,
+                    displacement: 0x40,
+                    size: Some(8),
+                }),
+            ]
+        );
+
+        assert_eq!(is[2].registers_read(), vec![r(2)]);
+        assert_eq!(is[2].registers_written(), vec![r(2)]);
+        assert_eq!(
+            is[2].operands(),
+            vec![Operand::Register(r(2)), Operand::Immediate(5)]
+        );
+
+        assert_eq!(is[3].registers_read(), vec![r(2), r(1)]);
+        assert!(is[3].registers_written().is_empty());
+        assert_eq!(
+            is[3].operands(),
+            vec![
+                Operand::Register(r(2)),
+                Operand::Register(r(1)),
+                Operand::Address(Address::new(5)),
+            ]
+        );
+
+        assert_eq!(is[4].registers_read(), vec![r(10), r(0)]);
+        assert!(is[4].registers_written().is_empty());
+
+        assert_eq!(is[5].registers_read(), vec![r(0)]);
+    }
+
+    #[test]
+    fn memory_access() {
+        let prog = &[
+            0x79, 0x12, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // load ptr from r1[0x40] to r2
+            0x63, 0x0a, 0xfc, 0xff, 0x00, 0x00, 0x00, 0x00, // store r0 to r10[-4]
+            0xdb, 0x0a, 0xf8, 0xff, 0x00, 0x00, 0x00, 0x00, // atomic add r0 to r10[-8]
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+
+        let f = Function::from_bpf(Symbol::new(Address::new(100000), Some("test")), prog);
+        let is = &f.instructions;
+
+        assert!(is[0].reads_memory() && !is[0].writes_memory());
+        assert!(!is[1].reads_memory() && is[1].writes_memory());
+        assert!(is[2].reads_memory() && is[2].writes_memory());
+        assert!(!is[3].reads_memory() && !is[3].writes_memory());
+    }
+
+    #[test]
+    fn elf_object() {
+        let prog = &[
+            0x18, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r1 = map
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x02, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // r2 = .rodata
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x85, 0x10, 0x00, 0x00, 0xff, 0xff,
+            0xff, 0xff, // call add
+            0x85, 0x10, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // call .text + 1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+        let text = &[
+            0xbf, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // mov r0, r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+            0xb7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // mov r0, 0
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+
+        let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::Bpf, Endianness::Little);
+        let symbol = |obj: &mut write::Object, name: &str, section, value, size, kind| {
+            obj.add_symbol(write::Symbol {
+                name: name.as_bytes().to_vec(),
+                value,
+                size,
+                kind,
+                scope: SymbolScope::Linkage,
+                weak: false,
+                section: SymbolSection::Section(section),
+                flags: SymbolFlags::None,
+            })
+        };
+        let xdp = obj.add_section(vec![], b"xdp".to_vec(), SectionKind::Text);
+        obj.append_section_data(xdp, prog, 8);
+        symbol(&mut obj, "prog", xdp, 0, 56, SymbolKind::Text);
+        let code = obj.add_section(vec![], b".text".to_vec(), SectionKind::Text);
+        obj.append_section_data(code, text, 8);
+        let add = symbol(&mut obj, "add", code, 0, 16, SymbolKind::Text);
+        symbol(&mut obj, "sub", code, 16, 16, SymbolKind::Text);
+        let maps = obj.add_section(vec![], b".maps".to_vec(), SectionKind::Data);
+        obj.append_section_data(maps, &[0; 32], 8);
+        let map = symbol(&mut obj, "map", maps, 0, 32, SymbolKind::Data);
+        let rodata = obj.add_section(vec![], b".rodata".to_vec(), SectionKind::ReadOnlyData);
+        obj.append_section_data(rodata, b"hello", 1);
+        let rodata = obj.section_symbol(rodata);
+        // Calls to static functions are relocated against the section.
+        let code = obj.section_symbol(code);
+        for (offset, symbol, r_type) in [
+            (0, map, elf::R_BPF_64_64),
+            (16, rodata, elf::R_BPF_64_64),
+            (32, add, elf::R_BPF_64_32),
+            (40, code, elf::R_BPF_64_32),
+        ] {
+            let flags = RelocationFlags::Elf { r_type };
+            let relocation = write::Relocation {
+                offset,
+                symbol,
+                addend: 0,
+                flags,
+            };
+            obj.add_relocation(xdp, relocation).unwrap();
+        }
+
+        let m = Module::from_bpf_bytes(&obj.write().unwrap()).unwrap();
+        let symbols = m
+            .functions
+            .iter()
+            .map(|f| (f.symbol.address, f.symbol.name.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            vec![
+                (Address::new(0), Some("prog")),
+                (Address::new(7), Some("add")),
+                (Address::new(9), Some("sub")),
+            ]
+        );
+        let comments = m.functions[0]
+            .instructions
+            .iter()
+            .map(|i| i.comment())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            comments,
+            vec![
+                Some("map".to_owned()),
+                Some(".rodata".to_owned()),
+                Some("add".to_owned()),
+                Some("sub".to_owned()),
+                None,
+            ]
+        );
+        let addresses = m.functions[0]
+            .instructions
+            .iter()
+            .map(|i| i.address())
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, [0, 2, 4, 5, 6].map(Address::new));
+        let calls = &m.functions[0].instructions[2..4];
+        assert_eq!(calls[0].target_address(), Some(Address::new(7)));
+        assert_eq!(calls[1].target_address(), Some(Address::new(9)));
+        assert_eq!(m.functions[1].instructions.len(), 2);
+        assert_eq!(m.functions[2].instructions[0].address(), Address::new(9));
+
+        // Anything other than an eBPF object is rejected.
+        assert!(Module::from_bpf_bytes(prog).is_none());
+    }
+
+    #[test]
+    fn calls() {
+        let prog = &[
+            0x85, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // call map_lookup_elem
+            0x85, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // call +2
+            0x85, 0x20, 0x00, 0x00, 0xd2, 0x04, 0x00, 0x00, // call kfunc 1234
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+            0xb7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // mov r0, 0
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+
+        let f = Function::from_bpf(Symbol::new(Address::new(0), Some("test")), prog);
+        let is = &f.instructions;
+
+        assert!(is[0].is_helper_call());
+        assert_eq!(
+            is[0].comment().as_deref(),
+            Some("void *bpf_map_lookup_elem(struct bpf_map *map, const void *key)")
+        );
+        assert_eq!(is[0].target_address(), None);
+
+        assert!(is[1].is_bpf_call());
+        assert_eq!(is[1].target_address(), Some(Address::new(4)));
+        assert_eq!(is[1].operands(), vec![Operand::Address(Address::new(4))]);
+        assert_eq!(is[1].comment(), None);
+
+        // A call relocated against a symbol from outside of the object
+        // has no known target.
+        let mut external = super::instructions(&prog[8..16], 1).remove(0);
+        external.relocation = Some("external".to_owned());
+        assert_eq!(external.target_address(), None);
+
+        assert!(is[2].is_kfunc_call() && !is[2].is_helper_call());
+        assert_eq!(is[2].comment().as_deref(), Some("kfunc btf_id=1234"));
+
+        assert_eq!(
+            f.identify_call_sites(),
+            vec![
+                CallSite {
+                    call_site_address: Address::new(0),
+                    target: CallSiteTarget::Indirect,
+                },
+                CallSite {
+                    call_site_address: Address::new(1),
+                    target: CallSiteTarget::Direct(Address::new(4)),
+                },
+                CallSite {
+                    call_site_address: Address::new(2),
+                    target: CallSiteTarget::Indirect,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn wide_loads_and_branches() {
+        let prog = &[
+            0x18, 0x01, 0x00, 0x00, 0x88, 0x77, 0x66, 0x55, // r1 = 0x1122334455667788
+            0x00, 0x00, 0x00, 0x00, 0x44, 0x33, 0x22, 0x11, 0x15, 0x01, 0x03, 0x00, 0x00, 0x00,
+            0x00, 0x00, // if r1 == 0 skip 3 slots
+            0x18, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // r0 = 1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0xfa, 0xff, 0x00, 0x00,
+            0x00, 0x00, // jump back 6 slots
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+
+        let f = Function::from_bpf(Symbol::new(Address::new(0), Some("test")), prog);
+        let is = &f.instructions;
+        let addresses = is.iter().map(|i| i.address()).collect::<Vec<_>>();
+        assert_eq!(addresses, [0, 2, 3, 5, 6].map(Address::new));
+
+        assert_eq!(is[1].target_address(), Some(Address::new(6)));
+        assert_eq!(is[3].target_address(), Some(Address::new(0)));
+
+        let cfg = &f.control_flow_graph;
+        assert_eq!(cfg.graph.node_count(), 3);
+        let successors = |address| {
+            let node = cfg.block_finder[&Address::new(address)];
+            let mut successors = cfg
+                .graph
+                .neighbors(node)
+                .map(|n| cfg.graph[n].address)
+                .collect::<Vec<_>>();
+            successors.sort();
+            successors
+        };
+        assert_eq!(successors(0), [3, 6].map(Address::new));
+        assert_eq!(successors(3), [Address::new(0)]);
+    }
+
+    #[test]
+    fn jmp32_and_tail_calls() {
+        let prog = &[
+            0x16, 0x01, 0x03, 0x00, 0x2a, 0x00, 0x00, 0x00, // if w1 == 42 skip 3
+            0x85, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, // call bpf_tail_call
+            0x8d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // tail call
+            0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // gotol +1
+            0xb7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // mov r0, 0
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+
+        let f = Function::from_bpf(Symbol::new(Address::new(0), Some("test")), prog);
+        let is = &f.instructions;
+
+        assert!(is[0].is_local_conditional_jump());
+        assert_eq!(is[0].target_address(), Some(Address::new(4)));
+        assert_eq!(
+            is[0].operands(),
+            vec![
+                Operand::Register(Register::Machine(1)),
+                Operand::Immediate(42),
+                Operand::Address(Address::new(4)),
+            ]
+        );
+        assert!(is[1].is_call() && is[1].is_conditional_tail_call());
+        assert!(is[2].is_call() && is[2].is_conditional_tail_call());
+        assert_eq!(is[2].registers_written(), is[1].registers_written());
+        assert_eq!(
+            is[2].registers_written(),
+            (0..=5).map(Register::Machine).collect::<Vec<_>>()
+        );
+        assert!(is[3].is_local_jump() && !is[3].is_local_conditional_jump());
+        assert_eq!(is[3].target_address(), Some(Address::new(5)));
+
+        let call_sites = f.identify_call_sites();
+        assert_eq!(call_sites.len(), 2);
+        assert!(call_sites
+            .iter()
+            .all(|c| c.target == CallSiteTarget::Indirect));
+
+        let cfg = &f.control_flow_graph;
+        let block = |address| cfg.block_finder[&Address::new(address)];
+        let (tail_call, helper_tail_call) = (block(2), block(1));
+        assert_eq!(cfg.exit_blocks[&helper_tail_call], ExitType::TailCall);
+        assert_eq!(cfg.exit_blocks[&tail_call], ExitType::TailCall);
+        assert!(cfg.graph.find_edge(block(0), block(4)).is_some());
+        assert!(cfg.graph.find_edge(block(3), block(5)).is_some());
+        assert!(cfg.graph.find_edge(tail_call, block(3)).is_some());
+    }
 }
@@ -0,0 +1,331 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::address::Address;
+use crate::callgraphanalysis::{CallGraphAnalysis, CallSite, CallSiteTarget};
+use crate::instruction::Instruction;
+use crate::module::Module;
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{Graph, NodeIndex};
+use petgraph::EdgeDirection;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A [call graph] for the functions within a [`Module`].
+///
+/// Each node is the [address] of a function's [`Symbol`] and each
+/// edge is a [`CallSite`] that directly calls another function within
+/// the module. An indirect call site with a known set of candidate
+/// targets has an edge to each candidate within the module. Call sites
+/// that can not be resolved to a function in the module are kept
+/// separately: indirect calls in [`indirect_calls`] and direct calls
+/// to other addresses in [`external_calls`].
+///
+/// [address]: crate::Address
+/// [call graph]: https://en.wikipedia.org/wiki/Call_graph
+/// [`external_calls`]: CallGraph::external_calls
+/// [`indirect_calls`]: CallGraph::indirect_calls
+/// [`Symbol`]: crate::Symbol
+#[derive(Debug)]
+pub struct CallGraph {
+    /// The graph of functions and the calls between them.
+    pub graph: Graph<Address, CallSite>,
+    /// Find the node for the function at an address.
+    pub function_finder: BTreeMap<Address, NodeIndex>,
+    /// Indirect call sites, keyed by the address of the calling function.
+    pub indirect_calls: BTreeMap<Address, Vec<CallSite>>,
+    /// Direct call sites whose target is not a function within the
+    /// module, keyed by the address of the calling function.
+    pub external_calls: BTreeMap<Address, Vec<CallSite>>,
+}
+
+impl CallGraph {
+    /// Build the call graph for a [`Module`].
+    pub fn new<I: Instruction>(module: &Module<I>) -> Self {
+        let mut cg = CallGraph {
+            graph: Graph::new(),
+            function_finder: BTreeMap::new(),
+            indirect_calls: BTreeMap::new(),
+            external_calls: BTreeMap::new(),
+        };
+        for function in &module.functions {
+            let address = function.symbol.address;
+            let node = cg.graph.add_node(address);
+            cg.function_finder.insert(address, node);
+        }
+        for function in &module.functions {
+            let caller = function.symbol.address;
+            let caller_node = cg.function_finder[&caller];
+            for call_site in function.identify_call_sites() {
+                match call_site.target {
+                    CallSiteTarget::Direct(target) => match cg.function_finder.get(&target) {
+                        Some(&callee_node) => {
+                            cg.graph.add_edge(caller_node, callee_node, call_site);
+                        }
+                        None => cg.external_calls.entry(caller).or_default().push(call_site),
+                    },
+                    CallSiteTarget::Candidates(ref candidates) => {
+                        let callee_nodes = candidates
+                            .iter()
+                            .filter_map(|target| cg.function_finder.get(target).cloned())
+                            .collect::<Vec<_>>();
+                        if callee_nodes.is_empty() {
+                            cg.indirect_calls.entry(caller).or_default().push(call_site);
+                        } else {
+                            for callee_node in callee_nodes {
+                                cg.graph
+                                    .add_edge(caller_node, callee_node, call_site.clone());
+                            }
+                        }
+                    }
+                    CallSiteTarget::Indirect => {
+                        cg.indirect_calls.entry(caller).or_default().push(call_site)
+                    }
+                }
+            }
+        }
+        cg
+    }
+
+    fn neighbors(&self, function: Address, direction: EdgeDirection) -> Vec<Address> {
+        match self.function_finder.get(&function) {
+            Some(&node) => self
+                .graph
+                .neighbors_directed(node, direction)
+                .map(|n| self.graph[n])
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// The functions that directly call the function at an address.
+    pub fn callers(&self, function: Address) -> Vec<Address> {
+        self.neighbors(function, EdgeDirection::Incoming)
+    }
+
+    /// The functions within the module that are directly called by the
+    /// function at an address.
+    pub fn callees(&self, function: Address) -> Vec<Address> {
+        self.neighbors(function, EdgeDirection::Outgoing)
+    }
+
+    /// The [strongly connected components] of the call graph.
+    ///
+    /// The components are returned in reverse topological order, so
+    /// callees come before their callers.
+    ///
+    /// [strongly connected components]: https://en.wikipedia.org/wiki/Strongly_connected_component
+    pub fn strongly_connected_components(&self) -> Vec<Vec<Address>> {
+        tarjan_scc(&self.graph)
+            .into_iter()
+            .map(|scc| {
+                let mut functions = scc.into_iter().map(|n| self.graph[n]).collect::<Vec<_>>();
+                functions.sort();
+                functions
+            })
+            .collect()
+    }
+
+    /// The groups of functions that are recursive, either by calling
+    /// themselves or by calling each other.
+    pub fn recursive_components(&self) -> Vec<Vec<Address>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || self.is_self_recursive(scc[0]))
+            .collect()
+    }
+
+    /// Is the function at an address part of a recursive cycle?
+    pub fn is_recursive(&self, function: Address) -> bool {
+        self.recursive_components()
+            .iter()
+            .any(|scc| scc.contains(&function))
+    }
+
+    fn is_self_recursive(&self, function: Address) -> bool {
+        self.function_finder
+            .get(&function)
+            .is_some_and(|&node| self.graph.contains_edge(node, node))
+    }
+
+    /// The functions that are not called by any other function within
+    /// the module, such as entry points and exported functions.
+    pub fn roots(&self) -> Vec<Address> {
+        self.graph
+            .node_indices()
+            .filter(|&node| {
+                self.graph
+                    .neighbors_directed(node, EdgeDirection::Incoming)
+                    .all(|caller| caller == node)
+            })
+            .map(|node| self.graph[node])
+            .collect()
+    }
+
+    /// The functions that do not make any calls, whether direct,
+    /// indirect or external.
+    pub fn leaves(&self) -> Vec<Address> {
+        self.graph
+            .node_indices()
+            .filter(|&node| {
+                let address = self.graph[node];
+                self.graph
+                    .neighbors_directed(node, EdgeDirection::Outgoing)
+                    .next()
+                    .is_none()
+                    && !self.indirect_calls.contains_key(&address)
+                    && !self.external_calls.contains_key(&address)
+            })
+            .map(|node| self.graph[node])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CallGraph;
+    use crate::address::Address;
+    use crate::callgraphanalysis::{CallSite, CallSiteTarget};
+    use crate::function::Function;
+    use crate::module::Module;
+    use crate::symbol::Symbol;
+    use crate::tests::*;
+
+    fn function(address: u64, opcodes: Vec<Opcode>) -> Function<TestInstruction> {
+        let insts = opcodes
+            .into_iter()
+            .enumerate()
+            .map(|(idx, op)| TestInstruction::new(address + idx as u64, op))
+            .collect();
+        Function::new(Symbol::new(Address::new(address), None), insts)
+    }
+
+    fn module() -> Module<TestInstruction> {
+        Module {
+            functions: vec![
+                // main calls a and b.
+                function(
+                    100,
+                    vec![
+                        Opcode::Call(Address::new(200)),
+                        Opcode::Call(Address::new(300)),
+                        Opcode::Ret,
+                    ],
+                ),
+                // a and b call each other.
+                function(200, vec![Opcode::Call(Address::new(300)), Opcode::Ret]),
+                function(
+                    300,
+                    vec![
+                        Opcode::CJmp(Address::new(302)),
+                        Opcode::Call(Address::new(200)),
+                        Opcode::Call(Address::new(400)),
+                        Opcode::Ret,
+                    ],
+                ),
+                // c is a leaf.
+                function(400, vec![Opcode::Add, Opcode::Ret]),
+                // d calls itself and something outside of the module.
+                function(
+                    500,
+                    vec![
+                        Opcode::Call(Address::new(500)),
+                        Opcode::Call(Address::new(900)),
+                        Opcode::TailCall(Address::new(500)),
+                    ],
+                ),
+                function(600, vec![Opcode::ICall(vec![]), Opcode::Ret]),
+            ],
+            imported_functions: vec![],
+            #[cfg(feature = "webassembly")]
+            declarations: Default::default(),
+        }
+    }
+
+    #[test]
+    fn callers_and_callees() {
+        let cg = CallGraph::new(&module());
+        let a = Address::new;
+
+        assert_eq!(cg.callees(a(100)), vec![a(200), a(300)]);
+        assert_eq!(cg.callees(a(300)), vec![a(200), a(400)]);
+        assert_eq!(cg.callers(a(200)), vec![a(100), a(300)]);
+        assert_eq!(cg.callers(a(400)), vec![a(300)]);
+        assert_eq!(cg.callees(a(500)), vec![a(500)]);
+        assert!(cg.callees(a(1000)).is_empty());
+    }
+
+    #[test]
+    fn recursion() {
+        let cg = CallGraph::new(&module());
+        let a = Address::new;
+
+        assert_eq!(
+            cg.recursive_components(),
+            vec![vec![a(200), a(300)], vec![a(500)]]
+        );
+        assert!(cg.is_recursive(a(300)));
+        assert!(!cg.is_recursive(a(100)));
+        assert!(!cg.is_recursive(a(400)));
+    }
+
+    #[test]
+    fn roots_and_leaves() {
+        let cg = CallGraph::new(&module());
+        let a = Address::new;
+
+        assert_eq!(cg.roots(), vec![a(100), a(500), a(600)]);
+        assert_eq!(cg.leaves(), vec![a(400)]);
+    }
+
+    #[test]
+    fn unresolved_calls() {
+        let cg = CallGraph::new(&module());
+
+        assert_eq!(
+            cg.external_calls[&Address::new(500)],
+            vec![CallSite {
+                call_site_address: Address::new(501),
+                target: CallSiteTarget::Direct(Address::new(900)),
+            }]
+        );
+        assert_eq!(
+            cg.indirect_calls[&Address::new(600)],
+            vec![CallSite {
+                call_site_address: Address::new(600),
+                target: CallSiteTarget::Indirect,
+            }]
+        );
+        assert_eq!(cg.external_calls.len(), 1);
+        assert_eq!(cg.indirect_calls.len(), 1);
+        assert_eq!(cg.graph.edge_count(), 7);
+    }
+
+    #[test]
+    fn candidate_calls() {
+        let mut m = module();
+        m.functions.push(function(
+            700,
+            vec![
+                Opcode::ICall(vec![Address::new(400), Address::new(600)]),
+                Opcode::ICall(vec![Address::new(900)]),
+                Opcode::Ret,
+            ],
+        ));
+        let cg = CallGraph::new(&m);
+        let a = Address::new;
+
+        assert_eq!(cg.callees(a(700)), vec![a(400), a(600)]);
+        assert_eq!(
+            cg.indirect_calls[&a(700)],
+            vec![CallSite {
+                call_site_address: a(701),
+                target: CallSiteTarget::Candidates(vec![a(900)]),
+            }]
+        );
+    }
+}
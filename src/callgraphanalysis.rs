@@ -8,17 +8,20 @@ use crate::address::Address;
 use crate::instruction::Instruction;
 
 /// Information about the target of a `CallSite`.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum CallSiteTarget {
     /// The call site directly invokes the function at the `Address`.
     Direct(Address),
+    /// The call site is indirect, but is known to invoke one of the
+    /// functions at these addresses.
+    Candidates(Vec<Address>),
     /// The call site is indirect, and we haven't yet done further
     /// analysis.
     Indirect,
 }
 
 /// Information about a call site.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CallSite {
     /// The address of the call site.
     pub call_site_address: Address,
@@ -33,16 +36,25 @@ pub trait CallGraphAnalysis<I: Instruction> {
 
     /// Get information about the function calls made within a set of instructions.
     ///
+    /// Tail calls are included as call sites.
+    ///
     /// This is meant to be called by implementations of this trait.
     fn identify_call_sites_in_instructions(&self, instructions: &[I]) -> Vec<CallSite> {
         instructions
             .iter()
-            .filter(|i| i.is_call())
+            .filter(|i| i.is_call() || i.is_tail_call())
             .map(|i| CallSite {
                 call_site_address: i.address(),
                 target: match i.target_address() {
                     Some(a) => CallSiteTarget::Direct(a),
-                    None => CallSiteTarget::Indirect,
+                    None => {
+                        let candidates = i.possible_call_targets();
+                        if candidates.is_empty() {
+                            CallSiteTarget::Indirect
+                        } else {
+                            CallSiteTarget::Candidates(candidates)
+                        }
+                    }
                 },
             })
             .collect()
@@ -99,4 +111,27 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn calls_tail_call() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Call(Address::new(500))),
+            TestInstruction::new(1, Opcode::TailCall(Address::new(600))),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let calls = f.identify_call_sites();
+        assert_eq!(
+            calls,
+            vec![
+                CallSite {
+                    call_site_address: Address::new(0),
+                    target: CallSiteTarget::Direct(Address::new(500)),
+                },
+                CallSite {
+                    call_site_address: Address::new(1),
+                    target: CallSiteTarget::Direct(Address::new(600)),
+                },
+            ]
+        );
+    }
 }
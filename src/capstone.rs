@@ -6,18 +6,26 @@
 
 extern crate capstone;
 
-use self::capstone::arch::x86::X86Insn;
+use self::capstone::arch::x86::{X86Insn, X86Operand, X86OperandType, X86Reg};
+use self::capstone::arch::{ArchDetail, ArchOperand};
 use self::capstone::prelude::*;
-use self::capstone::{Insn, InsnGroupType};
+use self::capstone::{Insn, InsnGroupType, RegAccessType};
 use super::address::Address;
 use super::instruction::Instruction;
+use super::operand::{MemoryOperand, Operand, Register};
 use std::fmt;
 
 /// A representation of a Capstone instruction.
+///
+/// Control flow works for every architecture Capstone supports, but the
+/// operands, memory accesses and registers are only known for x86. The
+/// Rust bindings do not expose how the operands of other architectures
+/// are accessed, so for those the instruction has no operands, does not
+/// access memory and reads and writes no registers.
 #[derive(Debug)]
 pub struct CapstoneInstruction<'i> {
     insn: Insn<'i>,
-    cs: &'i Capstone<'i>,
+    cs: &'i Capstone,
 }
 
 impl<'i> CapstoneInstruction<'i> {
@@ -28,6 +36,118 @@ impl<'i> CapstoneInstruction<'i> {
             false
         }
     }
+
+    /// The x86 operands, along with how each of them is accessed.
+    fn x86_operands(&self) -> Vec<X86Operand> {
+        let detail = match self.cs.insn_detail(&self.insn) {
+            Ok(detail) => detail,
+            Err(_) => return vec![],
+        };
+        detail
+            .arch_detail()
+            .operands()
+            .into_iter()
+            .filter_map(|op| match op {
+                ArchOperand::X86Operand(op) => Some(op),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The ids of the registers that are implicitly read or written, such
+    /// as the flags register or the stack pointer. This is empty except
+    /// on x86.
+    fn implicit_registers(&self, written: bool) -> Vec<RegId> {
+        match self.cs.insn_detail(&self.insn) {
+            Ok(detail) if matches!(detail.arch_detail(), ArchDetail::X86Detail(_)) => {
+                if written {
+                    detail.regs_write().collect()
+                } else {
+                    detail.regs_read().collect()
+                }
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Convert an x86 Capstone register id, where `0` means no register.
+///
+/// A register is identified by the full-width register that contains
+/// it, so `al`, `ax`, `eax` and `rax` are all the same.
+fn register(reg: RegId) -> Option<Register> {
+    match u32::from(reg.0) {
+        0 => None,
+        reg => Some(Register::Machine(x86_full_register(reg).0)),
+    }
+}
+
+/// Does writing to this register keep the rest of the full-width
+/// register that contains it? This is the case for the 8-bit and 16-bit
+/// registers, so writing them also reads the register. A 32-bit write
+/// on x86-64 clears the upper half instead.
+fn is_partial_write(reg: RegId) -> bool {
+    x86_full_register(u32::from(reg.0)).1
+}
+
+/// The full-width x86 register that contains `reg`, and whether `reg`
+/// is an 8-bit or 16-bit part of it.
+fn x86_full_register(reg: u32) -> (u32, bool) {
+    use self::X86Reg::*;
+    match reg {
+        X86_REG_AL | X86_REG_AH | X86_REG_AX => (X86_REG_RAX, true),
+        X86_REG_BL | X86_REG_BH | X86_REG_BX => (X86_REG_RBX, true),
+        X86_REG_CL | X86_REG_CH | X86_REG_CX => (X86_REG_RCX, true),
+        X86_REG_DL | X86_REG_DH | X86_REG_DX => (X86_REG_RDX, true),
+        X86_REG_SIL | X86_REG_SI => (X86_REG_RSI, true),
+        X86_REG_DIL | X86_REG_DI => (X86_REG_RDI, true),
+        X86_REG_BPL | X86_REG_BP => (X86_REG_RBP, true),
+        X86_REG_SPL | X86_REG_SP => (X86_REG_RSP, true),
+        X86_REG_IP => (X86_REG_RIP, true),
+        X86_REG_R8B | X86_REG_R8W => (X86_REG_R8, true),
+        X86_REG_R9B | X86_REG_R9W => (X86_REG_R9, true),
+        X86_REG_R10B | X86_REG_R10W => (X86_REG_R10, true),
+        X86_REG_R11B | X86_REG_R11W => (X86_REG_R11, true),
+        X86_REG_R12B | X86_REG_R12W => (X86_REG_R12, true),
+        X86_REG_R13B | X86_REG_R13W => (X86_REG_R13, true),
+        X86_REG_R14B | X86_REG_R14W => (X86_REG_R14, true),
+        X86_REG_R15B | X86_REG_R15W => (X86_REG_R15, true),
+        X86_REG_EAX => (X86_REG_RAX, false),
+        X86_REG_EBX => (X86_REG_RBX, false),
+        X86_REG_ECX => (X86_REG_RCX, false),
+        X86_REG_EDX => (X86_REG_RDX, false),
+        X86_REG_ESI => (X86_REG_RSI, false),
+        X86_REG_EDI => (X86_REG_RDI, false),
+        X86_REG_EBP => (X86_REG_RBP, false),
+        X86_REG_ESP => (X86_REG_RSP, false),
+        X86_REG_EIP => (X86_REG_RIP, false),
+        X86_REG_R8D => (X86_REG_R8, false),
+        X86_REG_R9D => (X86_REG_R9, false),
+        X86_REG_R10D => (X86_REG_R10, false),
+        X86_REG_R11D => (X86_REG_R11, false),
+        X86_REG_R12D => (X86_REG_R12, false),
+        X86_REG_R13D => (X86_REG_R13, false),
+        X86_REG_R14D => (X86_REG_R14, false),
+        X86_REG_R15D => (X86_REG_R15, false),
+        _ => (reg, false),
+    }
+}
+
+/// Is the operand read? Capstone does not know how every operand is
+/// accessed, so operands without this information are assumed to be read.
+fn is_read(op: &X86Operand) -> bool {
+    op.access.is_none_or(RegAccessType::is_readable)
+}
+
+/// Is the operand written?
+fn is_written(op: &X86Operand) -> bool {
+    op.access.is_some_and(RegAccessType::is_writable)
+}
+
+fn push_unique(registers: &mut Vec<Register>, reg: Register) {
+    if !registers.contains(&reg) {
+        registers.push(reg);
+    }
 }
 
 impl<'i> Instruction for CapstoneInstruction<'i> {
@@ -51,7 +171,7 @@ impl<'i> Instruction for CapstoneInstruction<'i> {
     fn is_local_conditional_jump(&self) -> bool {
         self.is_local_jump()
             && matches!(
-                unsafe { ::std::mem::transmute(self.insn.id().0) },
+                unsafe { ::std::mem::transmute::<u32, X86Insn>(self.insn.id().0) },
                 X86Insn::X86_INS_JMP
                     | X86Insn::X86_INS_LOOP
                     | X86Insn::X86_INS_LOOPE
@@ -64,7 +184,7 @@ impl<'i> Instruction for CapstoneInstruction<'i> {
     fn is_local_jump(&self) -> bool {
         self.is_group_match(InsnGroupType::CS_GRP_JUMP)
             && !matches!(
-                unsafe { ::std::mem::transmute(self.insn.id().0) },
+                unsafe { ::std::mem::transmute::<u32, X86Insn>(self.insn.id().0) },
                 X86Insn::X86_INS_LJMP
             )
     }
@@ -76,6 +196,95 @@ impl<'i> Instruction for CapstoneInstruction<'i> {
     fn target_address(&self) -> Option<Address> {
         None
     }
+
+    fn operands(&self) -> Vec<Operand> {
+        self.x86_operands()
+            .into_iter()
+            .filter_map(|op| match op.op_type {
+                X86OperandType::Reg(reg) => register(reg).map(Operand::Register),
+                X86OperandType::Imm(value) => Some(Operand::Immediate(value)),
+                X86OperandType::Mem(mem) => Some(Operand::Memory(MemoryOperand {
+                    base: register(mem.base()),
+                    index: register(mem.index()),
+                    scale: mem.scale() as u8,
+                    displacement: mem.disp(),
+                    size: Some(u32::from(op.size)),
+                })),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A memory operand is read when Capstone reports it as read. The
+    /// address computed by `lea` and the operand of a multi-byte `nop`
+    /// are not read.
+    ///
+    /// Only memory operands are considered, so the implicit stack
+    /// accesses of instructions like `push` and `pop` are not included.
+    fn reads_memory(&self) -> bool {
+        !matches!(self.mnemonic(), "lea" | "nop")
+            && self
+                .x86_operands()
+                .iter()
+                .any(|op| matches!(op.op_type, X86OperandType::Mem(_)) && is_read(op))
+    }
+
+    /// A memory operand is written when Capstone reports it as written.
+    ///
+    /// As with [`reads_memory`], implicit stack accesses are not included.
+    ///
+    /// [`reads_memory`]: Instruction::reads_memory
+    fn writes_memory(&self) -> bool {
+        self.x86_operands()
+            .iter()
+            .any(|op| matches!(op.op_type, X86OperandType::Mem(_)) && is_written(op))
+    }
+
+    /// The implicitly read registers, the register operands that are
+    /// read and the registers used to address memory. Writing part of a
+    /// register also reads it, as the rest of the register is kept.
+    fn registers_read(&self) -> Vec<Register> {
+        let mut ids = self.implicit_registers(false);
+        ids.extend(
+            self.implicit_registers(true)
+                .into_iter()
+                .filter(|&reg| is_partial_write(reg)),
+        );
+        for op in self.x86_operands() {
+            match op.op_type {
+                X86OperandType::Reg(reg)
+                    if is_read(&op) || (is_written(&op) && is_partial_write(reg)) =>
+                {
+                    ids.push(reg)
+                }
+                X86OperandType::Mem(mem) => ids.extend([mem.base(), mem.index()]),
+                _ => {}
+            }
+        }
+        let mut registers = vec![];
+        for reg in ids.into_iter().filter_map(register) {
+            push_unique(&mut registers, reg);
+        }
+        registers
+    }
+
+    /// The implicitly written registers and the register operands that
+    /// are written.
+    fn registers_written(&self) -> Vec<Register> {
+        let mut ids = self.implicit_registers(true);
+        for op in self.x86_operands() {
+            if let X86OperandType::Reg(reg) = op.op_type {
+                if is_written(&op) {
+                    ids.push(reg);
+                }
+            }
+        }
+        let mut registers = vec![];
+        for reg in ids.into_iter().filter_map(register) {
+            push_unique(&mut registers, reg);
+        }
+        registers
+    }
 }
 
 impl<'i> fmt::Display for CapstoneInstruction<'i> {
@@ -91,15 +300,24 @@ impl<'i> fmt::Display for CapstoneInstruction<'i> {
 
 #[cfg(test)]
 mod tests {
-    use super::super::{Address, Function, Symbol};
+    use super::super::{Address, Function, Instruction, Symbol};
     use super::capstone::prelude::*;
     use super::CapstoneInstruction;
 
+    fn x86_64() -> Capstone {
+        Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode64)
+            .detail(true)
+            .build()
+            .unwrap()
+    }
+
     #[test]
     fn test() {
         let code = &[0x55, 0x48, 0x8b, 0x05, 0xb8, 0x13, 0x00, 0x00];
 
-        let mut cs = Capstone::new()
+        let cs = Capstone::new()
             .x86()
             .mode(arch::x86::ArchMode::Mode32)
             .syntax(arch::x86::ArchSyntax::Att)
@@ -116,4 +334,157 @@ mod tests {
 
         assert!(f.control_flow_graph.entry_block.is_some());
     }
+
+    #[test]
+    fn memory_accesses() {
+        let code = &[
+            0x48, 0x89, 0x18, // mov qword ptr [rax], rbx
+            0x48, 0x8b, 0x18, // mov rbx, qword ptr [rax]
+            0x48, 0x01, 0x18, // add qword ptr [rax], rbx
+            0x48, 0xa5, // movsq qword ptr [rdi], qword ptr [rsi]
+            0x0f, 0xb6, 0x00, // movzx eax, byte ptr [rax]
+            0xff, 0x30, // push qword ptr [rax]
+            0x8f, 0x00, // pop qword ptr [rax]
+            0x0f, 0xa3, 0x18, // bt dword ptr [rax], ebx
+            0x0f, 0x2e, 0x00, // ucomiss xmm0, dword ptr [rax]
+            0x48, 0x85, 0x00, // test qword ptr [rax], rax
+            0x48, 0x8d, 0x04, 0x18, // lea rax, [rax + rbx]
+            0x0f, 0x1f, 0x00, // nop dword ptr [rax]
+            0x50, // push rax
+        ];
+        let cs = x86_64();
+        let buf = cs.disasm_all(code, 0).unwrap();
+        let accesses = buf
+            .iter()
+            .map(|insn| CapstoneInstruction { insn, cs: &cs })
+            .map(|i| (i.to_string(), i.reads_memory(), i.writes_memory()))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("mov qword ptr [rax], rbx", false, true),
+            ("mov rbx, qword ptr [rax]", true, false),
+            ("add qword ptr [rax], rbx", true, true),
+            ("movsq qword ptr [rdi], qword ptr [rsi]", true, true),
+            ("movzx eax, byte ptr [rax]", true, false),
+            ("push qword ptr [rax]", true, false),
+            ("pop qword ptr [rax]", false, true),
+            ("bt dword ptr [rax], ebx", true, false),
+            ("ucomiss xmm0, dword ptr [rax]", true, false),
+            ("test qword ptr [rax], rax", true, false),
+            ("lea rax, [rax + rbx]", false, false),
+            ("nop dword ptr [rax]", false, false),
+            ("push rax", false, false),
+        ]
+        .iter()
+        .map(|&(text, reads, writes)| (text.to_string(), reads, writes))
+        .collect::<Vec<_>>();
+        assert_eq!(accesses, expected);
+    }
+
+    #[test]
+    fn registers() {
+        use super::super::Register;
+        use super::capstone::arch::x86::X86Reg;
+
+        let code = &[
+            0x48, 0x89, 0xd8, // mov rax, rbx
+            0x48, 0x01, 0xd8, // add rax, rbx
+            0x48, 0x39, 0xd8, // cmp rax, rbx
+            0x50, // push rax
+            0x48, 0x8b, 0x04, 0x19, // mov rax, qword ptr [rcx + rbx]
+            0xff, 0xd0, // call rax
+        ];
+        let cs = x86_64();
+        let buf = cs.disasm_all(code, 0).unwrap();
+        let is = buf
+            .iter()
+            .map(|insn| CapstoneInstruction { insn, cs: &cs })
+            .collect::<Vec<_>>();
+        let reg = |r: u32| Register::Machine(r);
+        let rax = reg(X86Reg::X86_REG_RAX);
+        let rbx = reg(X86Reg::X86_REG_RBX);
+        let rcx = reg(X86Reg::X86_REG_RCX);
+        let rsp = reg(X86Reg::X86_REG_RSP);
+        let rflags = reg(X86Reg::X86_REG_EFLAGS);
+
+        assert_eq!(is[0].registers_read(), vec![rbx]);
+        assert_eq!(is[0].registers_written(), vec![rax]);
+        assert_eq!(is[1].registers_read(), vec![rax, rbx]);
+        assert_eq!(is[1].registers_written(), vec![rflags, rax]);
+        assert_eq!(is[2].registers_read(), vec![rax, rbx]);
+        assert_eq!(is[2].registers_written(), vec![rflags]);
+        assert_eq!(is[3].registers_read(), vec![rsp, rax]);
+        assert_eq!(is[3].registers_written(), vec![rsp]);
+        assert_eq!(is[4].registers_read(), vec![rcx, rbx]);
+        assert_eq!(is[4].registers_written(), vec![rax]);
+        assert_eq!(is[5].registers_read(), vec![rsp, rax]);
+        assert_eq!(is[5].registers_written(), vec![rsp]);
+    }
+
+    #[test]
+    fn sub_registers() {
+        use super::super::{Address, Function, ReachingDefinitions, Register, Symbol};
+        use super::capstone::arch::x86::X86Reg;
+
+        let code = &[
+            0x8b, 0x01, // mov eax, dword ptr [rcx]
+            0x48, 0x83, 0xc0, 0x01, // add rax, 1
+            0xb0, 0x02, // mov al, 2
+            0x66, 0x89, 0xd8, // mov ax, bx
+            0xc3, // ret
+        ];
+        let cs = x86_64();
+        let buf = cs.disasm_all(code, 0).unwrap();
+        let is = buf
+            .iter()
+            .map(|insn| CapstoneInstruction { insn, cs: &cs })
+            .collect::<Vec<_>>();
+        let reg = |r: u32| Register::Machine(r);
+        let rax = reg(X86Reg::X86_REG_RAX);
+        let rbx = reg(X86Reg::X86_REG_RBX);
+        let rcx = reg(X86Reg::X86_REG_RCX);
+
+        // A 32-bit write replaces the whole register.
+        assert_eq!(is[0].registers_read(), vec![rcx]);
+        assert_eq!(is[0].registers_written(), vec![rax]);
+        assert!(is[1].registers_read().contains(&rax));
+        // 8-bit and 16-bit writes keep the rest of the register.
+        assert_eq!(is[2].registers_read(), vec![rax]);
+        assert_eq!(is[2].registers_written(), vec![rax]);
+        assert_eq!(is[3].registers_read(), vec![rax, rbx]);
+        assert_eq!(is[3].registers_written(), vec![rax]);
+
+        let f = Function::new(Symbol::new(Address::new(0), None), is);
+        let rd = ReachingDefinitions::new(&f);
+        assert_eq!(rd.definitions(1, rax), Some(&[0].into()));
+        assert_eq!(rd.definitions(2, rax), Some(&[1].into()));
+    }
+
+    #[test]
+    fn other_architectures() {
+        let code = &[
+            0x20, 0x00, 0x40, 0xf9, // ldr x0, [x1]
+            0xc0, 0x03, 0x5f, 0xd6, // ret
+        ];
+        let cs = Capstone::new()
+            .arm64()
+            .mode(arch::arm64::ArchMode::Arm)
+            .detail(true)
+            .build()
+            .unwrap();
+        let buf = cs.disasm_all(code, 0).unwrap();
+        let is = buf
+            .iter()
+            .map(|insn| CapstoneInstruction { insn, cs: &cs })
+            .collect::<Vec<_>>();
+
+        // Only control flow is known outside of x86.
+        assert!(is[1].is_return());
+        for i in &is {
+            assert!(i.operands().is_empty());
+            assert!(!i.reads_memory());
+            assert!(!i.writes_memory());
+            assert!(i.registers_read().is_empty());
+            assert!(i.registers_written().is_empty());
+        }
+    }
 }
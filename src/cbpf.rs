@@ -0,0 +1,522 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::address::Address;
+use super::function::Function;
+use super::instruction::Instruction;
+use super::operand::{MemoryOperand, Operand, Register};
+use super::symbol::Symbol;
+use std::fmt;
+
+// Instruction classes.
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+
+// Sizes of loads.
+const BPF_H: u16 = 0x08;
+const BPF_B: u16 = 0x10;
+
+// Modes of loads.
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_IND: u16 = 0x40;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+const BPF_MSH: u16 = 0xa0;
+
+// ALU operations.
+const BPF_NEG: u16 = 0x80;
+
+// Jumps.
+const BPF_JA: u16 = 0x00;
+
+// Sources of operands.
+const BPF_X: u16 = 0x08;
+
+// Return values.
+const BPF_A: u16 = 0x10;
+
+// Miscellaneous operations.
+const BPF_TXA: u16 = 0x80;
+
+/// The accumulator, `A`.
+const A: Register = Register::Machine(0);
+
+/// The index register, `X`.
+const X: Register = Register::Machine(1);
+
+/// A representation of a classic BPF instruction, as used by seccomp
+/// filters and packet filters like those of `tcpdump`.
+///
+/// The accumulator `A` is register 0 and the index register `X` is
+/// register 1. The scratch memory slots `M[0]` to `M[15]` are treated
+/// as [local] registers, while packet loads are memory accesses.
+///
+/// [local]: Register::Local
+#[derive(Debug)]
+pub struct CbpfInstruction {
+    idx: u64,
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+impl CbpfInstruction {
+    /// Construct a `CbpfInstruction` from the fields of a `sock_filter`.
+    ///
+    /// The `idx` is the position of the instruction within the filter.
+    pub fn new(idx: u64, code: u16, jt: u8, jf: u8, k: u32) -> Self {
+        CbpfInstruction {
+            idx,
+            code,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    fn class(&self) -> u16 {
+        self.code & 0x07
+    }
+
+    fn mode(&self) -> u16 {
+        self.code & 0xe0
+    }
+
+    fn op(&self) -> u16 {
+        self.code & 0xf0
+    }
+
+    /// Does this instruction use `X` rather than the constant `k`?
+    fn uses_x(&self) -> bool {
+        (self.code & BPF_X) == BPF_X
+    }
+
+    /// The register that is loaded, stored or returned.
+    fn register(&self) -> Option<Register> {
+        match self.class() {
+            BPF_LD | BPF_ST => Some(A),
+            BPF_LDX | BPF_STX => Some(X),
+            BPF_RET => match self.code & 0x18 {
+                BPF_A => Some(A),
+                BPF_X => Some(X),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The scratch memory slot that is accessed, as a register.
+    fn scratch(&self) -> Register {
+        Register::Local(self.k)
+    }
+
+    /// The number of bytes read by a load from the packet.
+    fn access_size(&self) -> u32 {
+        match self.code & 0x18 {
+            BPF_H => 2,
+            BPF_B => 1,
+            _ => 4,
+        }
+    }
+
+    /// Is this a load from the packet?
+    fn is_packet_load(&self) -> bool {
+        match self.class() {
+            BPF_LD => matches!(self.mode(), BPF_ABS | BPF_IND),
+            BPF_LDX => self.mode() == BPF_MSH,
+            _ => false,
+        }
+    }
+
+    /// The source operand for ALU and jump instructions.
+    fn src_operand(&self) -> Operand {
+        if self.uses_x() {
+            Operand::Register(X)
+        } else {
+            Operand::Immediate(i64::from(self.k))
+        }
+    }
+
+    /// The address of the instruction that is `offset` after the next one.
+    fn relative(&self, offset: u32) -> Address {
+        Address::new(self.idx + 1 + u64::from(offset))
+    }
+}
+
+impl Instruction for CbpfInstruction {
+    fn address(&self) -> Address {
+        Address::new(self.idx)
+    }
+
+    fn comment(&self) -> Option<String> {
+        None
+    }
+
+    fn mnemonic(&self) -> &str {
+        match self.class() {
+            BPF_LD => match self.code & 0x18 {
+                BPF_H => "ldh",
+                BPF_B => "ldb",
+                _ => "ld",
+            },
+            BPF_LDX if self.mode() == BPF_MSH => "ldxb",
+            BPF_LDX => "ldx",
+            BPF_ST => "st",
+            BPF_STX => "stx",
+            BPF_ALU => match self.op() {
+                0x00 => "add",
+                0x10 => "sub",
+                0x20 => "mul",
+                0x30 => "div",
+                0x40 => "or",
+                0x50 => "and",
+                0x60 => "lsh",
+                0x70 => "rsh",
+                0x80 => "neg",
+                0x90 => "mod",
+                0xa0 => "xor",
+                _ => "unknown",
+            },
+            BPF_JMP => match self.op() {
+                0x00 => "ja",
+                0x10 => "jeq",
+                0x20 => "jgt",
+                0x30 => "jge",
+                0x40 => "jset",
+                _ => "unknown",
+            },
+            BPF_RET => "ret",
+            _ if self.code & 0xf8 == BPF_TXA => "txa",
+            _ => "tax",
+        }
+    }
+
+    fn operands(&self) -> Vec<Operand> {
+        match self.class() {
+            BPF_LD | BPF_LDX => {
+                let register = Operand::Register(self.register().unwrap_or(A));
+                let memory = |base| {
+                    Operand::Memory(MemoryOperand {
+                        base,
+                        index: None,
+                        scale: 1,
+                        displacement: i64::from(self.k),
+                        size: Some(self.access_size()),
+                    })
+                };
+                match self.mode() {
+                    BPF_IMM => vec![register, Operand::Immediate(i64::from(self.k))],
+                    BPF_ABS | BPF_MSH => vec![register, memory(None)],
+                    BPF_IND => vec![register, memory(Some(X))],
+                    BPF_MEM => vec![register, Operand::Register(self.scratch())],
+                    _ => vec![register],
+                }
+            }
+            BPF_ST | BPF_STX => vec![
+                Operand::Register(self.scratch()),
+                Operand::Register(self.register().unwrap_or(A)),
+            ],
+            BPF_ALU if self.op() == BPF_NEG => vec![Operand::Register(A)],
+            BPF_ALU => vec![Operand::Register(A), self.src_operand()],
+            BPF_JMP if self.op() == BPF_JA => vec![Operand::Address(self.relative(self.k))],
+            BPF_JMP => vec![
+                Operand::Register(A),
+                self.src_operand(),
+                Operand::Address(self.relative(u32::from(self.jt))),
+                Operand::Address(self.relative(u32::from(self.jf))),
+            ],
+            BPF_RET => match self.register() {
+                Some(register) => vec![Operand::Register(register)],
+                None => vec![Operand::Immediate(i64::from(self.k))],
+            },
+            _ if self.code & 0xf8 == BPF_TXA => vec![Operand::Register(A), Operand::Register(X)],
+            _ => vec![Operand::Register(X), Operand::Register(A)],
+        }
+    }
+
+    fn registers_read(&self) -> Vec<Register> {
+        match self.class() {
+            BPF_LD | BPF_LDX => match self.mode() {
+                BPF_IND => vec![X],
+                BPF_MEM => vec![self.scratch()],
+                _ => vec![],
+            },
+            BPF_ST | BPF_STX | BPF_RET => self.register().into_iter().collect(),
+            BPF_JMP if self.op() == BPF_JA => vec![],
+            BPF_ALU | BPF_JMP if self.op() != BPF_NEG && self.uses_x() => vec![A, X],
+            BPF_ALU | BPF_JMP => vec![A],
+            _ if self.code & 0xf8 == BPF_TXA => vec![X],
+            _ => vec![A],
+        }
+    }
+
+    fn registers_written(&self) -> Vec<Register> {
+        match self.class() {
+            BPF_LD | BPF_LDX => self.register().into_iter().collect(),
+            BPF_ST | BPF_STX => vec![self.scratch()],
+            BPF_ALU => vec![A],
+            BPF_JMP | BPF_RET => vec![],
+            _ if self.code & 0xf8 == BPF_TXA => vec![A],
+            _ => vec![X],
+        }
+    }
+
+    fn reads_memory(&self) -> bool {
+        self.is_packet_load()
+    }
+
+    fn is_call(&self) -> bool {
+        false
+    }
+
+    fn is_local_conditional_jump(&self) -> bool {
+        self.is_local_jump() && self.op() != BPF_JA
+    }
+
+    fn is_local_jump(&self) -> bool {
+        self.class() == BPF_JMP
+    }
+
+    fn is_return(&self) -> bool {
+        self.class() == BPF_RET
+    }
+
+    fn target_address(&self) -> Option<Address> {
+        if self.is_local_conditional_jump() {
+            Some(self.relative(u32::from(self.jt)))
+        } else if self.is_local_jump() {
+            Some(self.relative(self.k))
+        } else {
+            None
+        }
+    }
+
+    fn fallthrough_address(&self) -> Option<Address> {
+        if self.is_local_conditional_jump() {
+            Some(self.relative(u32::from(self.jf)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Function<CbpfInstruction> {
+    /// Create a function from a classic BPF filter.
+    ///
+    /// The filter is an array of `struct sock_filter` in little-endian
+    /// byte order, as it is on most hosts. Instructions are addressed by
+    /// their position within the filter.
+    ///
+    /// This returns `None` if the length of the filter is not a multiple
+    /// of the size of an instruction.
+    pub fn from_cbpf(symbol: Symbol, data: &[u8]) -> Option<Function<CbpfInstruction>> {
+        if !data.len().is_multiple_of(8) {
+            return None;
+        }
+        let is = data
+            .chunks_exact(8)
+            .enumerate()
+            .map(|(idx, b)| {
+                CbpfInstruction::new(
+                    idx as u64,
+                    u16::from_le_bytes([b[0], b[1]]),
+                    b[2],
+                    b[3],
+                    u32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+                )
+            })
+            .collect();
+        Some(Function::new(symbol, is))
+    }
+}
+
+impl fmt::Display for CbpfInstruction {
+    /// Format the instruction as `tcpdump -d` does.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonic = self.mnemonic();
+        let k = self.k;
+        match self.class() {
+            BPF_LD | BPF_LDX => match self.mode() {
+                BPF_IMM => write!(f, "{} #{:#x}", mnemonic, k),
+                BPF_ABS => write!(f, "{} [{}]", mnemonic, k),
+                BPF_IND => write!(f, "{} [x + {}]", mnemonic, k),
+                BPF_MEM => write!(f, "{} M[{}]", mnemonic, k),
+                BPF_LEN => write!(f, "{} #pktlen", mnemonic),
+                BPF_MSH => write!(f, "{} 4*([{}]&0xf)", mnemonic, k),
+                _ => f.write_str(mnemonic),
+            },
+            BPF_ST | BPF_STX => write!(f, "{} M[{}]", mnemonic, k),
+            BPF_ALU if self.op() == BPF_NEG => f.write_str(mnemonic),
+            BPF_ALU if self.uses_x() => write!(f, "{} x", mnemonic),
+            BPF_ALU => write!(f, "{} #{}", mnemonic, k),
+            BPF_JMP if self.op() == BPF_JA => {
+                write!(f, "{} {}", mnemonic, self.idx + 1 + u64::from(k))
+            }
+            BPF_JMP => {
+                let jt = self.idx + 1 + u64::from(self.jt);
+                let jf = self.idx + 1 + u64::from(self.jf);
+                if self.uses_x() {
+                    write!(f, "{} x jt {} jf {}", mnemonic, jt, jf)
+                } else {
+                    write!(f, "{} #{:#x} jt {} jf {}", mnemonic, k, jt, jf)
+                }
+            }
+            BPF_RET => match self.code & 0x18 {
+                BPF_A => write!(f, "{} a", mnemonic),
+                BPF_X => write!(f, "{} x", mnemonic),
+                _ => write!(f, "{} #{}", mnemonic, k),
+            },
+            _ => f.write_str(mnemonic),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::address::Address;
+    use crate::basicblock::EdgeType;
+    use crate::function::Function;
+    use crate::instruction::Instruction;
+    use crate::operand::{MemoryOperand, Operand, Register};
+    use crate::symbol::Symbol;
+
+    /// Encode a `sock_filter`.
+    fn insn(code: u16, jt: u8, jf: u8, k: u32) -> Vec<u8> {
+        let mut bytes = code.to_le_bytes().to_vec();
+        bytes.extend([jt, jf]);
+        bytes.extend(k.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn seccomp_filter() {
+        let prog = [
+            insn(0x20, 0, 0, 4),          // ld [4]
+            insn(0x15, 1, 0, 0xc000003e), // jeq #0xc000003e jt 3 jf 2
+            insn(0x06, 0, 0, 0),          // ret #0
+            insn(0x20, 0, 0, 0),          // ld [0]
+            insn(0x15, 0, 1, 60),         // jeq #0x3c jt 5 jf 6
+            insn(0x06, 0, 0, 0x7fff0000), // ret #2147418112
+            insn(0x06, 0, 0, 0x00050001), // ret #327681
+        ]
+        .concat();
+
+        let f = Function::from_cbpf(Symbol::new(Address::new(0), Some("filter")), &prog).unwrap();
+        let is = &f.instructions;
+        let text = is.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            text,
+            vec![
+                "ld [4]",
+                "jeq #0xc000003e jt 3 jf 2",
+                "ret #0",
+                "ld [0]",
+                "jeq #0x3c jt 5 jf 6",
+                "ret #2147418112",
+                "ret #327681",
+            ]
+        );
+
+        assert!(is[0].reads_memory());
+        assert_eq!(is[0].registers_written(), vec![Register::Machine(0)]);
+        assert_eq!(
+            is[0].operands(),
+            vec![
+                Operand::Register(Register::Machine(0)),
+                Operand::Memory(MemoryOperand {
+                    base: None,
+                    index: None,
+                    scale: 1,
+                    displacement: 4,
+                    size: Some(4),
+                }),
+            ]
+        );
+        assert_eq!(is[1].target_address(), Some(Address::new(3)));
+        assert_eq!(is[1].fallthrough_address(), Some(Address::new(2)));
+        assert!(is[2].is_return());
+
+        let cfg = &f.control_flow_graph;
+        let block = |address| cfg.block_finder[&Address::new(address)];
+        let edge_type = |a, b| {
+            let edge = cfg.graph.find_edge(block(a), block(b)).unwrap();
+            cfg.graph[edge].edge_type
+        };
+        assert_eq!(cfg.graph.node_count(), 5);
+        assert_eq!(edge_type(0, 3), EdgeType::ConditionalTaken);
+        assert_eq!(edge_type(0, 2), EdgeType::ConditionalFallthrough);
+        assert_eq!(edge_type(3, 5), EdgeType::ConditionalTaken);
+        assert_eq!(edge_type(3, 6), EdgeType::ConditionalFallthrough);
+        assert_eq!(cfg.exit_blocks.len(), 3);
+    }
+
+    #[test]
+    fn registers() {
+        let prog = [
+            insn(0x00, 0, 0, 5),  // ld #0x5
+            insn(0x02, 0, 0, 3),  // st M[3]
+            insn(0xb1, 0, 0, 14), // ldxb 4*([14]&0xf)
+            insn(0x48, 0, 0, 2),  // ldh [x + 2]
+            insn(0x0c, 0, 0, 0),  // add x
+            insn(0x61, 0, 0, 3),  // ldx M[3]
+            insn(0x87, 0, 0, 0),  // txa
+            insn(0x05, 0, 0, 0),  // ja 8
+            insn(0x16, 0, 0, 0),  // ret a
+        ]
+        .concat();
+
+        let f = Function::from_cbpf(Symbol::new(Address::new(0), None), &prog).unwrap();
+        let is = &f.instructions;
+        let (a, x, m) = (Register::Machine(0), Register::Machine(1), Register::Local);
+
+        assert_eq!(is[0].to_string(), "ld #0x5");
+        assert_eq!(is[1].to_string(), "st M[3]");
+        assert_eq!(
+            (is[1].registers_read(), is[1].registers_written()),
+            (vec![a], vec![m(3)])
+        );
+        assert_eq!(is[2].to_string(), "ldxb 4*([14]&0xf)");
+        assert!(is[2].reads_memory());
+        assert_eq!(is[2].registers_written(), vec![x]);
+        assert_eq!(is[3].to_string(), "ldh [x + 2]");
+        assert_eq!(is[3].registers_read(), vec![x]);
+        assert_eq!(is[4].to_string(), "add x");
+        assert_eq!(
+            (is[4].registers_read(), is[4].registers_written()),
+            (vec![a, x], vec![a])
+        );
+        assert_eq!(is[5].to_string(), "ldx M[3]");
+        assert_eq!(
+            (is[5].registers_read(), is[5].registers_written()),
+            (vec![m(3)], vec![x])
+        );
+        assert_eq!(is[6].to_string(), "txa");
+        assert_eq!(
+            (is[6].registers_read(), is[6].registers_written()),
+            (vec![x], vec![a])
+        );
+        assert_eq!(is[7].to_string(), "ja 8");
+        assert!(is[7].is_local_jump() && !is[7].is_local_conditional_jump());
+        assert_eq!(is[7].target_address(), Some(Address::new(8)));
+        assert_eq!(is[8].to_string(), "ret a");
+        assert_eq!(is[8].registers_read(), vec![a]);
+    }
+
+    #[test]
+    fn partial_instruction() {
+        let mut prog = insn(0x06, 0, 0, 0); // ret #0
+        prog.extend([0x06, 0x00, 0x00]);
+        assert!(Function::from_cbpf(Symbol::new(Address::new(0), None), &prog).is_none());
+        assert!(Function::from_cbpf(Symbol::new(Address::new(0), None), &prog[..8]).is_some());
+    }
+}
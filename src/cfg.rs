@@ -10,6 +10,29 @@ use crate::instruction::Instruction;
 use petgraph::graph::{Graph, NodeIndex};
 use std::collections::BTreeMap;
 
+/// How control leaves a function from an exit [basic block].
+///
+/// [basic block]: BasicBlock
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExitType {
+    /// The block ends with a return from the function.
+    Return,
+    /// The block ends with a tail call to another function. If the tail
+    /// call may fail, control can also continue to the next block.
+    TailCall,
+    /// The block ends with an instruction that always traps.
+    Trap,
+    /// The block ends by throwing an exception that is not handled
+    /// within the function.
+    Throw,
+    /// The block ends with a jump whose target could not be resolved
+    /// to a block within the function, such as an indirect jump.
+    Unresolved,
+    /// The block falls off the end of the instructions without
+    /// branching or returning.
+    FallOff,
+}
+
 /// A [control flow graph].
 ///
 /// [control flow graph]: https://en.wikipedia.org/wiki/Control_flow_graph
@@ -18,6 +41,14 @@ pub struct ControlFlowGraph {
     pub graph: Graph<BasicBlock, BasicBlockEdge>,
     /// The [`NodeIndex`] for the entry [`BasicBlock`] for this function.
     pub entry_block: Option<NodeIndex>,
+    /// The [`NodeIndex`] for the virtual exit [`BasicBlock`], if one has
+    /// been added with [`ControlFlowGraph::add_virtual_exit`].
+    pub exit_block: Option<NodeIndex>,
+    /// The blocks through which control leaves the function, along with
+    /// [how control leaves] each of them.
+    ///
+    /// [how control leaves]: ExitType
+    pub exit_blocks: BTreeMap<NodeIndex, ExitType>,
     /// Map an [address] to the corresponding [basic block].
     ///
     /// [address]: Address
@@ -42,6 +73,8 @@ impl ControlFlowGraph {
         let mut cfg = ControlFlowGraph {
             graph: Graph::new(),
             entry_block: None,
+            exit_block: None,
+            exit_blocks: BTreeMap::new(),
             block_finder: BTreeMap::new(),
         };
         if !instructions.is_empty() {
@@ -51,6 +84,59 @@ impl ControlFlowGraph {
         cfg
     }
 
+    /// The blocks that end by returning from the function.
+    pub fn return_blocks(&self) -> Vec<NodeIndex> {
+        self.exit_blocks_of_type(|t| t == ExitType::Return)
+    }
+
+    /// The blocks that end in an unresolved jump or that fall off the
+    /// end of the instructions.
+    ///
+    /// Control flow leaving these blocks is not known.
+    pub fn unresolved_blocks(&self) -> Vec<NodeIndex> {
+        self.exit_blocks_of_type(|t| t == ExitType::Unresolved || t == ExitType::FallOff)
+    }
+
+    fn exit_blocks_of_type<F: Fn(ExitType) -> bool>(&self, f: F) -> Vec<NodeIndex> {
+        self.exit_blocks
+            .iter()
+            .filter(|&(_, &t)| f(t))
+            .map(|(&idx, _)| idx)
+            .collect()
+    }
+
+    /// Add a virtual exit block that every exit block flows into.
+    ///
+    /// This gives the graph a single exit, which is convenient for some
+    /// analyses, such as finding post-dominators. The virtual exit block
+    /// is named `exit`, contains no instructions and is not present in
+    /// the `block_finder`. Its address is `u64::MAX`.
+    ///
+    /// Edges to the virtual exit block have the type [`EdgeType::Exit`],
+    /// except for blocks that end in a tail call, which have the type
+    /// [`EdgeType::TailCall`].
+    ///
+    /// This returns the virtual exit block. If the graph is empty, no
+    /// block is added and `None` is returned.
+    pub fn add_virtual_exit(&mut self) -> Option<NodeIndex> {
+        if self.exit_block.is_some() || self.entry_block.is_none() {
+            return self.exit_block;
+        }
+        let mut block = BasicBlock::new(Address::new(u64::MAX));
+        block.name = Some("exit".to_owned());
+        let exit_idx = self.graph.add_node(block);
+        for (&idx, &exit_type) in &self.exit_blocks {
+            let edge_type = match exit_type {
+                ExitType::TailCall => EdgeType::TailCall,
+                _ => EdgeType::Exit,
+            };
+            self.graph
+                .add_edge(idx, exit_idx, BasicBlockEdge { edge_type });
+        }
+        self.exit_block = Some(exit_idx);
+        self.exit_block
+    }
+
     /// Identify basic blocks by their boundaries.
     ///
     /// Inspect each instruction to see if it is a 'leader' or the start of
@@ -60,7 +146,10 @@ impl ControlFlowGraph {
     /// * It comes after a branch of any sort. We identify these as things
     ///   for which `Instruction::is_block_terminator` returns `true`.
     /// * It is the target of a jump (conditional or unconditional) within
-    ///   the function.
+    ///   the function. This includes each of the targets of a multi-way
+    ///   branch. The targets of calls are other functions, so they are
+    ///   not leaders, even when their addresses are within this function.
+    /// * It is an exception handler for an instruction within the function.
     fn identify_blocks<I: Instruction>(&mut self, instructions: &[I]) {
         let start_addr = instructions[0].address();
         let end_addr = instructions.last().map(|i| i.address()).unwrap();
@@ -71,7 +160,19 @@ impl ControlFlowGraph {
                 next_is_leader = false;
             }
             if inst.is_block_terminator() {
-                if let Some(target_addr) = inst.target_address() {
+                let branch_targets = inst.branch_targets();
+                let targets = if !branch_targets.is_empty() {
+                    branch_targets.iter().map(|t| t.address).collect::<Vec<_>>()
+                } else if inst.is_local_jump() || inst.is_local_conditional_jump() {
+                    inst.target_address().into_iter().collect()
+                } else {
+                    vec![]
+                };
+                let targets = targets
+                    .into_iter()
+                    .chain(inst.fallthrough_address())
+                    .chain(inst.exception_handlers());
+                for target_addr in targets {
                     if target_addr >= start_addr && target_addr <= end_addr {
                         self.add_node_to_graph(target_addr);
                     }
@@ -93,55 +194,142 @@ impl ControlFlowGraph {
         self.block_finder.insert(address, idx);
     }
 
+    /// Find the block for the target of a jump, if it is within this function.
+    fn target_block<I: Instruction>(&self, inst: &I) -> Option<NodeIndex> {
+        inst.target_address()
+            .and_then(|addr| self.block_finder.get(&addr))
+            .cloned()
+    }
+
+    /// Record that control leaves the function from a block.
+    ///
+    /// If a block leaves the function in more than one way, the first
+    /// one recorded is kept.
+    fn add_exit(&mut self, block_idx: NodeIndex, exit_type: ExitType) {
+        self.exit_blocks.entry(block_idx).or_insert(exit_type);
+    }
+
     /// Build an edge between 2 basic blocks.
+    ///
+    /// If there is no next block, then this is the last instruction and
+    /// anything that would continue on to the next instruction falls off
+    /// the end instead.
     fn build_edge<I: Instruction>(
         &mut self,
         current_block_idx: NodeIndex,
         next_block_idx: Option<NodeIndex>,
         current_inst: &I,
     ) {
-        if current_inst.is_local_conditional_jump() {
-            // We have one edge for the jump target and one for the fallthrough.
-            // We jump through some hoops here to keep the borrow checker happy.
-            if let Some(target_addr) = current_inst.target_address() {
-                if let Some(target_block_idx) = self.block_finder.get(&target_addr) {
-                    let edge = BasicBlockEdge {
-                        edge_type: EdgeType::ConditionalTaken,
+        let handlers = current_inst.exception_handlers();
+        for handler_addr in &handlers {
+            // This is in addition to the normal control flow.
+            if let Some(&handler_block_idx) = self.block_finder.get(handler_addr) {
+                let edge = BasicBlockEdge {
+                    edge_type: EdgeType::Exception,
+                };
+                self.graph
+                    .add_edge(current_block_idx, handler_block_idx, edge);
+            }
+        }
+
+        let branch_targets = current_inst.branch_targets();
+        if current_inst.is_tail_call() {
+            self.add_exit(current_block_idx, ExitType::TailCall);
+        } else if !branch_targets.is_empty() {
+            // We have one edge for each target of a multi-way branch.
+            for target in branch_targets {
+                if let Some(&target_block_idx) = self.block_finder.get(&target.address) {
+                    let edge_type = match target.case {
+                        Some(value) => EdgeType::SwitchCase(value),
+                        None if current_inst.is_indirect_jump() => EdgeType::IndirectJump,
+                        None => EdgeType::SwitchDefault,
                     };
-                    self.graph
-                        .add_edge(current_block_idx, *target_block_idx, edge);
+                    self.graph.add_edge(
+                        current_block_idx,
+                        target_block_idx,
+                        BasicBlockEdge { edge_type },
+                    );
+                } else {
+                    self.add_exit(current_block_idx, ExitType::Unresolved);
                 }
-
+            }
+            if current_inst.is_local_conditional_jump() {
                 if let Some(index) = next_block_idx {
                     let edge = BasicBlockEdge {
                         edge_type: EdgeType::ConditionalFallthrough,
                     };
                     self.graph.add_edge(current_block_idx, index, edge);
+                } else {
+                    self.add_exit(current_block_idx, ExitType::FallOff);
+                }
+            }
+        } else if current_inst.is_local_conditional_jump() {
+            // We have one edge for the jump target and one for the fallthrough.
+            if let Some(target_block_idx) = self.target_block(current_inst) {
+                let edge = BasicBlockEdge {
+                    edge_type: EdgeType::ConditionalTaken,
+                };
+                self.graph
+                    .add_edge(current_block_idx, target_block_idx, edge);
+            } else {
+                self.add_exit(current_block_idx, ExitType::Unresolved);
+            }
+
+            let edge = BasicBlockEdge {
+                edge_type: EdgeType::ConditionalFallthrough,
+            };
+            if let Some(address) = current_inst.fallthrough_address() {
+                // This jump goes elsewhere when it is not taken.
+                if let Some(&index) = self.block_finder.get(&address) {
+                    self.graph.add_edge(current_block_idx, index, edge);
+                } else {
+                    self.add_exit(current_block_idx, ExitType::Unresolved);
                 }
+            } else if let Some(index) = next_block_idx {
+                self.graph.add_edge(current_block_idx, index, edge);
+            } else {
+                self.add_exit(current_block_idx, ExitType::FallOff);
             }
         } else if current_inst.is_call() {
+            if current_inst.is_conditional_tail_call() {
+                // Control leaves the function when the tail call succeeds.
+                self.add_exit(current_block_idx, ExitType::TailCall);
+            }
             // We are calling a function, which will jump to target address and will return
             // to the instruction just after the current one.
             if let Some(index) = next_block_idx {
                 let edge = BasicBlockEdge {
-                    edge_type: EdgeType::Unconditional,
+                    edge_type: EdgeType::CallFallthrough,
                 };
                 self.graph.add_edge(current_block_idx, index, edge);
+            } else {
+                self.add_exit(current_block_idx, ExitType::FallOff);
             }
         } else if current_inst.is_local_jump() {
             // We are on an unconditional jump and we need to add an edge to the target
             // block (if it exists).
-            if let Some(target_addr) = current_inst.target_address() {
-                if let Some(target_block_idx) = self.block_finder.get(&target_addr) {
-                    let edge = BasicBlockEdge {
-                        edge_type: EdgeType::Unconditional,
-                    };
-                    self.graph
-                        .add_edge(current_block_idx, *target_block_idx, edge);
-                }
+            if let Some(target_block_idx) = self.target_block(current_inst) {
+                let edge = BasicBlockEdge {
+                    edge_type: if current_inst.is_indirect_jump() {
+                        EdgeType::IndirectJump
+                    } else {
+                        EdgeType::Unconditional
+                    },
+                };
+                self.graph
+                    .add_edge(current_block_idx, target_block_idx, edge);
+            } else {
+                self.add_exit(current_block_idx, ExitType::Unresolved);
             }
         } else if current_inst.is_return() {
-            // Do we want to record this exit anywhere?
+            self.add_exit(current_block_idx, ExitType::Return);
+        } else if current_inst.is_trap() {
+            self.add_exit(current_block_idx, ExitType::Trap);
+        } else if current_inst.is_throw() {
+            // Control only continues at the exception handlers.
+            if handlers.is_empty() {
+                self.add_exit(current_block_idx, ExitType::Throw);
+            }
         } else if let Some(index) = next_block_idx {
             // We are here because someone has a reference to the current instruction, but
             // it is non branching instruction, so we have to add an edge to the
@@ -150,6 +338,8 @@ impl ControlFlowGraph {
                 edge_type: EdgeType::Unconditional,
             };
             self.graph.add_edge(current_block_idx, index, edge);
+        } else {
+            self.add_exit(current_block_idx, ExitType::FallOff);
         }
     }
 
@@ -190,9 +380,8 @@ impl ControlFlowGraph {
                     current_block_idx = next_block_idx;
                 }
             } else {
-                self.build_edge(current_block_idx, None, current_inst);
                 // No next instruction, so we're at the end.
-                // Do we want to record this exit anywhere?
+                self.build_edge(current_block_idx, None, current_inst);
             }
         }
     }
@@ -200,19 +389,24 @@ impl ControlFlowGraph {
 
 #[cfg(test)]
 mod tests {
-    use super::ControlFlowGraph;
+    use super::{ControlFlowGraph, ExitType};
     use crate::address::Address;
+    use crate::basicblock::EdgeType;
+    use crate::instruction::BranchTarget;
     use crate::tests::*;
     use petgraph::graph::NodeIndex;
+    use petgraph::visit::EdgeRef;
     use petgraph::EdgeDirection;
     use std::collections::HashSet;
 
     #[test]
     fn construct() {
         let insts: Vec<TestInstruction> = vec![];
-        let cfg = ControlFlowGraph::new(&insts);
+        let mut cfg = ControlFlowGraph::new(&insts);
         assert!(cfg.entry_block.is_none());
         assert_eq!(cfg.graph.node_count(), 0);
+        assert!(cfg.exit_blocks.is_empty());
+        assert!(cfg.add_virtual_exit().is_none());
     }
 
     #[test]
@@ -366,6 +560,243 @@ mod tests {
         assert_neighbours(neighbours, vec![add_idx]);
     }
 
+    #[test]
+    fn record_exits() {
+        let insts = [
+            TestInstruction::new(0, Opcode::CJmp(Address::new(4))),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(100))),
+            TestInstruction::new(2, Opcode::Ret),
+            TestInstruction::new(3, Opcode::Jmp(Address::new(200))),
+            TestInstruction::new(4, Opcode::Add),
+        ];
+
+        let cfg = ControlFlowGraph::new(&insts);
+
+        let ret_idx = *cfg.block_finder.get(&Address::new(2)).unwrap();
+        let unresolved_cond_idx = *cfg.block_finder.get(&Address::new(1)).unwrap();
+        let unresolved_jump_idx = *cfg.block_finder.get(&Address::new(3)).unwrap();
+        let fall_off_idx = *cfg.block_finder.get(&Address::new(4)).unwrap();
+
+        assert_eq!(cfg.exit_blocks.len(), 4);
+        assert_eq!(cfg.exit_blocks[&ret_idx], ExitType::Return);
+        assert_eq!(cfg.exit_blocks[&unresolved_cond_idx], ExitType::Unresolved);
+        assert_eq!(cfg.exit_blocks[&unresolved_jump_idx], ExitType::Unresolved);
+        assert_eq!(cfg.exit_blocks[&fall_off_idx], ExitType::FallOff);
+        assert_eq!(cfg.return_blocks(), vec![ret_idx]);
+        assert_eq!(cfg.unresolved_blocks().len(), 3);
+
+        // The unresolved conditional jump still falls through.
+        let neighbours = cfg.graph.neighbors(unresolved_cond_idx).collect();
+        assert_neighbours(neighbours, vec![ret_idx]);
+    }
+
+    #[test]
+    fn record_trap() {
+        let insts = [
+            TestInstruction::new(0, Opcode::CJmp(Address::new(2))),
+            TestInstruction::new(1, Opcode::Trap),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+
+        let cfg = ControlFlowGraph::new(&insts);
+        let trap_idx = *cfg.block_finder.get(&Address::new(1)).unwrap();
+
+        assert_eq!(cfg.graph.node_count(), 3);
+        assert_eq!(cfg.exit_blocks[&trap_idx], ExitType::Trap);
+        assert_eq!(cfg.graph.neighbors(trap_idx).count(), 0);
+        assert!(cfg.unresolved_blocks().is_empty());
+    }
+
+    #[test]
+    fn record_throw() {
+        let insts = [
+            TestInstruction::new(0, Opcode::CJmp(Address::new(2))),
+            TestInstruction::new(1, Opcode::Throw(vec![])),
+            TestInstruction::new(2, Opcode::Throw(vec![Address::new(4), Address::new(5)])),
+            TestInstruction::new(3, Opcode::Add),
+            TestInstruction::new(4, Opcode::Ret),
+            TestInstruction::new(5, Opcode::Ret),
+        ];
+
+        let cfg = ControlFlowGraph::new(&insts);
+        let unhandled_idx = *cfg.block_finder.get(&Address::new(1)).unwrap();
+        let handled_idx = *cfg.block_finder.get(&Address::new(2)).unwrap();
+        let handler_idxs = [4, 5].map(|a| *cfg.block_finder.get(&Address::new(a)).unwrap());
+
+        assert_eq!(cfg.exit_blocks[&unhandled_idx], ExitType::Throw);
+        assert_eq!(cfg.graph.neighbors(unhandled_idx).count(), 0);
+        assert!(!cfg.exit_blocks.contains_key(&handled_idx));
+        let neighbours = cfg.graph.neighbors(handled_idx).collect();
+        assert_neighbours(neighbours, handler_idxs.to_vec());
+        for handler_idx in handler_idxs {
+            let edge = cfg.graph.find_edge(handled_idx, handler_idx).unwrap();
+            assert_eq!(cfg.graph[edge].edge_type, EdgeType::Exception);
+        }
+    }
+
+    #[test]
+    fn record_conditional_tail_call() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::CondTailCall),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+
+        let mut cfg = ControlFlowGraph::new(&insts);
+        let tail_call_idx = *cfg.block_finder.get(&Address::new(0)).unwrap();
+        let ret_idx = *cfg.block_finder.get(&Address::new(2)).unwrap();
+
+        assert_eq!(cfg.exit_blocks[&tail_call_idx], ExitType::TailCall);
+        let edge = cfg.graph.find_edge(tail_call_idx, ret_idx).unwrap();
+        assert_eq!(cfg.graph[edge].edge_type, EdgeType::CallFallthrough);
+
+        let exit_idx = cfg.add_virtual_exit().unwrap();
+        let edge = cfg.graph.find_edge(tail_call_idx, exit_idx).unwrap();
+        assert_eq!(cfg.graph[edge].edge_type, EdgeType::TailCall);
+    }
+
+    #[test]
+    fn add_virtual_exit() {
+        let insts = [
+            TestInstruction::new(0, Opcode::CJmp(Address::new(2))),
+            TestInstruction::new(1, Opcode::Ret),
+            TestInstruction::new(2, Opcode::Add),
+        ];
+
+        let mut cfg = ControlFlowGraph::new(&insts);
+        let exit_idx = cfg.add_virtual_exit().unwrap();
+        assert_eq!(cfg.add_virtual_exit(), Some(exit_idx));
+        assert_eq!(cfg.exit_block, Some(exit_idx));
+        assert_eq!(cfg.graph.node_count(), 4);
+        assert!(cfg.graph[exit_idx].instruction_indices.is_empty());
+        assert_eq!(cfg.graph[exit_idx].name.as_deref(), Some("exit"));
+
+        let ret_idx = *cfg.block_finder.get(&Address::new(1)).unwrap();
+        let fall_off_idx = *cfg.block_finder.get(&Address::new(2)).unwrap();
+        for idx in [ret_idx, fall_off_idx] {
+            let edge = cfg.graph.find_edge(idx, exit_idx).unwrap();
+            assert_eq!(cfg.graph[edge].edge_type, EdgeType::Exit);
+        }
+        let outbound = cfg.graph.externals(EdgeDirection::Outgoing);
+        assert_eq!(outbound.collect::<Vec<_>>(), vec![exit_idx]);
+    }
+
+    #[test]
+    fn build_cfg_with_edge_types() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Invoke(Address::new(100), Address::new(5))),
+            TestInstruction::new(1, Opcode::IJmp(Some(Address::new(3)))),
+            TestInstruction::new(2, Opcode::IJmp(None)),
+            TestInstruction::new(3, Opcode::TailCall(Address::new(200))),
+            TestInstruction::new(4, Opcode::Ret),
+            TestInstruction::new(5, Opcode::Ret),
+        ];
+
+        let mut cfg = ControlFlowGraph::new(&insts);
+
+        let invoke_idx = *cfg.block_finder.get(&Address::new(0)).unwrap();
+        let ijmp_idx = *cfg.block_finder.get(&Address::new(1)).unwrap();
+        let unresolved_idx = *cfg.block_finder.get(&Address::new(2)).unwrap();
+        let tail_call_idx = *cfg.block_finder.get(&Address::new(3)).unwrap();
+        let handler_idx = *cfg.block_finder.get(&Address::new(5)).unwrap();
+
+        let edge_type = |cfg: &ControlFlowGraph, a, b| {
+            let edge = cfg.graph.find_edge(a, b).unwrap();
+            cfg.graph[edge].edge_type
+        };
+        assert_eq!(
+            edge_type(&cfg, invoke_idx, ijmp_idx),
+            EdgeType::CallFallthrough
+        );
+        assert_eq!(
+            edge_type(&cfg, invoke_idx, handler_idx),
+            EdgeType::Exception
+        );
+        assert_eq!(
+            edge_type(&cfg, ijmp_idx, tail_call_idx),
+            EdgeType::IndirectJump
+        );
+        assert_eq!(cfg.exit_blocks[&unresolved_idx], ExitType::Unresolved);
+        assert_eq!(cfg.exit_blocks[&tail_call_idx], ExitType::TailCall);
+        assert!(cfg.graph.neighbors(tail_call_idx).next().is_none());
+
+        let exit_idx = cfg.add_virtual_exit().unwrap();
+        assert_eq!(edge_type(&cfg, tail_call_idx, exit_idx), EdgeType::TailCall);
+    }
+
+    #[test]
+    fn build_cfg_with_two_target_jump() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Branch(Address::new(3), Address::new(2))),
+            TestInstruction::new(1, Opcode::Add),
+            TestInstruction::new(2, Opcode::Ret),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+
+        let cfg = ControlFlowGraph::new(&insts);
+        let block = |address| *cfg.block_finder.get(&Address::new(address)).unwrap();
+        let edge_type = |a, b| {
+            let edge = cfg.graph.find_edge(block(a), block(b)).unwrap();
+            cfg.graph[edge].edge_type
+        };
+
+        // The instruction after the jump is not reached.
+        assert_eq!(cfg.graph.node_count(), 4);
+        assert_eq!(cfg.graph.neighbors(block(0)).count(), 2);
+        assert_eq!(edge_type(0, 3), EdgeType::ConditionalTaken);
+        assert_eq!(edge_type(0, 2), EdgeType::ConditionalFallthrough);
+        assert_eq!(
+            cfg.graph
+                .neighbors_directed(block(1), EdgeDirection::Incoming)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn build_cfg_with_switch() {
+        let target = |address, case| BranchTarget {
+            address: Address::new(address),
+            case,
+        };
+        let insts = [
+            TestInstruction::new(
+                0,
+                Opcode::Switch(vec![
+                    target(1, Some(0)),
+                    target(2, Some(1)),
+                    target(2, Some(2)),
+                    target(3, None),
+                ]),
+            ),
+            TestInstruction::new(1, Opcode::Ret),
+            TestInstruction::new(2, Opcode::Ret),
+            TestInstruction::new(3, Opcode::Switch(vec![target(100, Some(0))])),
+        ];
+
+        let cfg = ControlFlowGraph::new(&insts);
+
+        assert_eq!(4, cfg.graph.node_count());
+        assert_eq!(4, cfg.graph.edge_count());
+
+        let switch_idx = *cfg.block_finder.get(&Address::new(0)).unwrap();
+        let case_0_idx = *cfg.block_finder.get(&Address::new(1)).unwrap();
+        let case_1_idx = *cfg.block_finder.get(&Address::new(2)).unwrap();
+        let default_idx = *cfg.block_finder.get(&Address::new(3)).unwrap();
+
+        let edge_types = cfg
+            .graph
+            .edges(switch_idx)
+            .map(|e| (e.target(), e.weight().edge_type))
+            .collect::<Vec<_>>();
+        assert_eq!(edge_types.len(), 4);
+        assert!(edge_types.contains(&(case_0_idx, EdgeType::SwitchCase(0))));
+        assert!(edge_types.contains(&(case_1_idx, EdgeType::SwitchCase(1))));
+        assert!(edge_types.contains(&(case_1_idx, EdgeType::SwitchCase(2))));
+        assert!(edge_types.contains(&(default_idx, EdgeType::SwitchDefault)));
+        assert_eq!(cfg.exit_blocks[&default_idx], ExitType::Unresolved);
+    }
+
     fn assert_neighbours(actual: Vec<NodeIndex>, expected: Vec<NodeIndex>) {
         let actual_set: HashSet<NodeIndex> = actual.into_iter().collect();
         let expected_set: HashSet<NodeIndex> = expected.into_iter().collect();
@@ -0,0 +1,432 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::basicblock::BasicBlock;
+use crate::cfg::ControlFlowGraph;
+use crate::instruction::Instruction;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::DfsPostOrder;
+use petgraph::EdgeDirection;
+use std::collections::{BTreeSet, VecDeque};
+
+/// A value in a [data flow analysis].
+///
+/// Values start out at the bottom of the lattice, as given by
+/// [`DataFlowAnalysis::bottom`], and move up the lattice as they
+/// are joined with the values flowing in from other blocks. For
+/// the analysis to terminate, the lattice must have a finite height.
+///
+/// [data flow analysis]: DataFlowAnalysis
+pub trait Lattice: Clone + PartialEq {
+    /// Merge `other` into this value, returning whether or not this
+    /// value changed.
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+/// Sets are joined by taking their union.
+impl<T: Clone + Ord> Lattice for BTreeSet<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        let len = self.len();
+        self.extend(other.iter().cloned());
+        self.len() != len
+    }
+}
+
+/// The direction in which values flow through the [`ControlFlowGraph`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// Values flow from the entry block towards the exits, like
+    /// reaching definitions.
+    Forward,
+    /// Values flow from the exits back towards the entry block, like
+    /// liveness.
+    Backward,
+}
+
+/// A [data flow analysis] over the [basic blocks] of a [`ControlFlowGraph`].
+///
+/// An analysis provides the [lattice] of values being computed, the
+/// [direction] in which they flow and a transfer function that gives
+/// the effect of each instruction. The [`solve`] method then iterates
+/// over the graph until a fixed point is reached.
+///
+/// [basic blocks]: BasicBlock
+/// [data flow analysis]: https://en.wikipedia.org/wiki/Data-flow_analysis
+/// [direction]: Direction
+/// [lattice]: Lattice
+/// [`solve`]: DataFlowAnalysis::solve
+pub trait DataFlowAnalysis<I: Instruction> {
+    /// The values computed by this analysis.
+    type Domain: Lattice;
+
+    /// The direction in which values flow.
+    fn direction(&self) -> Direction;
+
+    /// The initial value for each block.
+    fn bottom(&self) -> Self::Domain;
+
+    /// The value flowing into the entry block for a forward analysis
+    /// or into each exit block for a backward analysis.
+    ///
+    /// By default, this is the same as [`bottom`].
+    ///
+    /// [`bottom`]: DataFlowAnalysis::bottom
+    fn boundary(&self) -> Self::Domain {
+        self.bottom()
+    }
+
+    /// Apply the effect of a single instruction to `state`.
+    ///
+    /// `index` is the index of the instruction within the instructions
+    /// that the [`ControlFlowGraph`] was built from. For a backward
+    /// analysis, `state` is the value after the instruction and should
+    /// be updated to be the value before it.
+    fn transfer_instruction(&self, index: usize, instruction: &I, state: &mut Self::Domain);
+
+    /// Apply the effect of a whole block to `state`.
+    ///
+    /// By default, this applies [`transfer_instruction`] to each
+    /// instruction in the block, in the direction of the analysis.
+    ///
+    /// [`transfer_instruction`]: DataFlowAnalysis::transfer_instruction
+    fn transfer_block(&self, block: &BasicBlock, instructions: &[I], state: &mut Self::Domain) {
+        match self.direction() {
+            Direction::Forward => {
+                for &idx in &block.instruction_indices {
+                    self.transfer_instruction(idx, &instructions[idx], state);
+                }
+            }
+            Direction::Backward => {
+                for &idx in block.instruction_indices.iter().rev() {
+                    self.transfer_instruction(idx, &instructions[idx], state);
+                }
+            }
+        }
+    }
+
+    /// Run this analysis over a [`ControlFlowGraph`] built from
+    /// `instructions`.
+    fn solve(&self, cfg: &ControlFlowGraph, instructions: &[I]) -> DataFlowResults<Self::Domain>
+    where
+        Self: Sized,
+    {
+        let graph = &cfg.graph;
+        let direction = self.direction();
+        let (incoming, outgoing) = match direction {
+            Direction::Forward => (EdgeDirection::Incoming, EdgeDirection::Outgoing),
+            Direction::Backward => (EdgeDirection::Outgoing, EdgeDirection::Incoming),
+        };
+        let is_boundary = |node: NodeIndex| match direction {
+            Direction::Forward => cfg.entry_block == Some(node),
+            Direction::Backward => {
+                graph
+                    .neighbors_directed(node, EdgeDirection::Outgoing)
+                    .next()
+                    .is_none()
+                    || cfg.exit_blocks.contains_key(&node)
+            }
+        };
+
+        // The values where flow enters and leaves each block, in the
+        // direction of the analysis.
+        let mut flow_in = vec![self.bottom(); graph.node_count()];
+        let mut flow_out = vec![self.bottom(); graph.node_count()];
+
+        let mut worklist = iteration_order(cfg, direction)
+            .into_iter()
+            .collect::<VecDeque<_>>();
+        let mut queued = vec![true; graph.node_count()];
+        while let Some(node) = worklist.pop_front() {
+            queued[node.index()] = false;
+            let mut state = if is_boundary(node) {
+                self.boundary()
+            } else {
+                self.bottom()
+            };
+            for pred in graph.neighbors_directed(node, incoming) {
+                state.join(&flow_out[pred.index()]);
+            }
+            flow_in[node.index()] = state.clone();
+            self.transfer_block(&graph[node], instructions, &mut state);
+            if state != flow_out[node.index()] {
+                flow_out[node.index()] = state;
+                for succ in graph.neighbors_directed(node, outgoing) {
+                    if !queued[succ.index()] {
+                        queued[succ.index()] = true;
+                        worklist.push_back(succ);
+                    }
+                }
+            }
+        }
+
+        let (entry, exit) = match direction {
+            Direction::Forward => (flow_in, flow_out),
+            Direction::Backward => (flow_out, flow_in),
+        };
+        DataFlowResults { entry, exit }
+    }
+}
+
+/// Visit blocks in reverse post-order for a forward analysis and in
+/// post-order for a backward analysis so that most blocks are visited
+/// after the blocks that flow into them. Blocks that can not be reached
+/// from the entry block are visited last.
+fn iteration_order(cfg: &ControlFlowGraph, direction: Direction) -> Vec<NodeIndex> {
+    let graph = &cfg.graph;
+    let mut order = vec![];
+    let mut seen = vec![false; graph.node_count()];
+    if let Some(entry) = cfg.entry_block {
+        let mut dfs = DfsPostOrder::new(graph, entry);
+        while let Some(node) = dfs.next(graph) {
+            seen[node.index()] = true;
+            order.push(node);
+        }
+    }
+    if direction == Direction::Forward {
+        order.reverse();
+    }
+    order.extend(graph.node_indices().filter(|n| !seen[n.index()]));
+    order
+}
+
+/// The fixed point computed by a [`DataFlowAnalysis`].
+///
+/// Values are kept for the entry and exit of each block, in program
+/// order, regardless of the direction of the analysis. The values at
+/// each instruction can be recovered with [`instruction_states`].
+///
+/// [`instruction_states`]: DataFlowResults::instruction_states
+#[derive(Debug)]
+pub struct DataFlowResults<D> {
+    entry: Vec<D>,
+    exit: Vec<D>,
+}
+
+/// The values before and after an instruction, in program order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstructionStates<D> {
+    /// The index of the instruction.
+    pub index: usize,
+    /// The value before the instruction executes.
+    pub before: D,
+    /// The value after the instruction executes.
+    pub after: D,
+}
+
+impl<D: Lattice> DataFlowResults<D> {
+    /// The value at the start of a block.
+    pub fn entry(&self, block: NodeIndex) -> Option<&D> {
+        self.entry.get(block.index())
+    }
+
+    /// The value at the end of a block.
+    pub fn exit(&self, block: NodeIndex) -> Option<&D> {
+        self.exit.get(block.index())
+    }
+
+    /// Replay the transfer function over a block of the [`ControlFlowGraph`]
+    /// that was analyzed to find the values before and after each of its
+    /// instructions.
+    ///
+    /// The states are returned in program order.
+    pub fn instruction_states<I, A>(
+        &self,
+        analysis: &A,
+        cfg: &ControlFlowGraph,
+        node: NodeIndex,
+        instructions: &[I],
+    ) -> Vec<InstructionStates<D>>
+    where
+        I: Instruction,
+        A: DataFlowAnalysis<I, Domain = D>,
+    {
+        let mut states = vec![];
+        let block = match cfg.graph.node_weight(node) {
+            Some(block) => block,
+            None => return states,
+        };
+        match analysis.direction() {
+            Direction::Forward => {
+                let mut state = match self.entry(node) {
+                    Some(state) => state.clone(),
+                    None => return states,
+                };
+                for &idx in &block.instruction_indices {
+                    let before = state.clone();
+                    analysis.transfer_instruction(idx, &instructions[idx], &mut state);
+                    states.push(InstructionStates {
+                        index: idx,
+                        before,
+                        after: state.clone(),
+                    });
+                }
+            }
+            Direction::Backward => {
+                let mut state = match self.exit(node) {
+                    Some(state) => state.clone(),
+                    None => return states,
+                };
+                for &idx in block.instruction_indices.iter().rev() {
+                    let after = state.clone();
+                    analysis.transfer_instruction(idx, &instructions[idx], &mut state);
+                    states.push(InstructionStates {
+                        index: idx,
+                        before: state.clone(),
+                        after,
+                    });
+                }
+                states.reverse();
+            }
+        }
+        states
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataFlowAnalysis, Direction, Lattice};
+    use crate::address::Address;
+    use crate::function::Function;
+    use crate::symbol::Symbol;
+    use crate::tests::*;
+    use std::collections::BTreeSet;
+
+    /// Collect the instructions that may have executed before
+    /// reaching a point in the program.
+    struct Executed;
+
+    impl DataFlowAnalysis<TestInstruction> for Executed {
+        type Domain = BTreeSet<usize>;
+
+        fn direction(&self) -> Direction {
+            Direction::Forward
+        }
+
+        fn bottom(&self) -> Self::Domain {
+            BTreeSet::new()
+        }
+
+        fn transfer_instruction(
+            &self,
+            index: usize,
+            _: &TestInstruction,
+            state: &mut Self::Domain,
+        ) {
+            state.insert(index);
+        }
+    }
+
+    /// Collect the instructions that may execute after a point
+    /// in the program.
+    struct Remaining;
+
+    impl DataFlowAnalysis<TestInstruction> for Remaining {
+        type Domain = BTreeSet<usize>;
+
+        fn direction(&self) -> Direction {
+            Direction::Backward
+        }
+
+        fn bottom(&self) -> Self::Domain {
+            BTreeSet::new()
+        }
+
+        fn transfer_instruction(
+            &self,
+            index: usize,
+            _: &TestInstruction,
+            state: &mut Self::Domain,
+        ) {
+            state.insert(index);
+        }
+    }
+
+    fn set(items: &[usize]) -> BTreeSet<usize> {
+        items.iter().cloned().collect()
+    }
+
+    fn diamond() -> Function<TestInstruction> {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(1, Opcode::Add),
+            TestInstruction::new(2, Opcode::Jmp(Address::new(4))),
+            TestInstruction::new(3, Opcode::Add),
+            TestInstruction::new(4, Opcode::Ret),
+        ];
+        Function::new(Symbol::new(Address::new(100), None), insts)
+    }
+
+    #[test]
+    fn join_sets() {
+        let mut a = set(&[1, 2]);
+        assert!(!a.join(&set(&[2])));
+        assert!(a.join(&set(&[3])));
+        assert_eq!(a, set(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn forward() {
+        let f = diamond();
+        let cfg = &f.control_flow_graph;
+        let results = Executed.solve(cfg, &f.instructions);
+        let entry = cfg.entry_block.unwrap();
+        let join = cfg.block_finder[&Address::new(4)];
+
+        assert_eq!(results.entry(entry), Some(&set(&[])));
+        assert_eq!(results.exit(entry), Some(&set(&[0])));
+        assert_eq!(results.entry(join), Some(&set(&[0, 1, 2, 3])));
+        assert_eq!(results.exit(join), Some(&set(&[0, 1, 2, 3, 4])));
+    }
+
+    #[test]
+    fn backward() {
+        let f = diamond();
+        let cfg = &f.control_flow_graph;
+        let results = Remaining.solve(cfg, &f.instructions);
+        let entry = cfg.entry_block.unwrap();
+        let left = cfg.block_finder[&Address::new(1)];
+
+        assert_eq!(results.entry(entry), Some(&set(&[0, 1, 2, 3, 4])));
+        assert_eq!(results.exit(entry), Some(&set(&[1, 2, 3, 4])));
+        assert_eq!(results.entry(left), Some(&set(&[1, 2, 4])));
+
+        let states = results.instruction_states(&Remaining, cfg, left, &f.instructions);
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].index, 1);
+        assert_eq!(states[0].before, set(&[1, 2, 4]));
+        assert_eq!(states[0].after, set(&[2, 4]));
+        assert_eq!(states[1].index, 2);
+        assert_eq!(states[1].after, set(&[4]));
+    }
+
+    #[test]
+    fn loop_fixed_point() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::Add),
+            TestInstruction::new(2, Opcode::CJmp(Address::new(1))),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let cfg = &f.control_flow_graph;
+        let results = Executed.solve(cfg, &f.instructions);
+        let header = cfg.block_finder[&Address::new(1)];
+
+        assert_eq!(results.entry(header), Some(&set(&[0, 1, 2])));
+
+        let states = results.instruction_states(&Executed, cfg, header, &f.instructions);
+        assert_eq!(states[0].before, set(&[0, 1, 2]));
+        assert_eq!(states[1].after, set(&[0, 1, 2]));
+    }
+
+    #[test]
+    fn empty() {
+        let f: Function<TestInstruction> =
+            Function::new(Symbol::new(Address::new(100), None), vec![]);
+        let results = Executed.solve(&f.control_flow_graph, &f.instructions);
+        assert_eq!(results.entry(0.into()), None);
+    }
+}
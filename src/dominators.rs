@@ -0,0 +1,471 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::cfg::ControlFlowGraph;
+use petgraph::graph::NodeIndex;
+use petgraph::EdgeDirection;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A [dominator tree] for a [`ControlFlowGraph`], along with the
+/// dominance frontier of each [basic block].
+///
+/// The same structure is used for both dominators and post-dominators.
+/// Blocks are identified by the same [`NodeIndex`] values that are
+/// used within the [`ControlFlowGraph`].
+///
+/// For post-dominators, a virtual exit node is used that joins together
+/// every exit block. Unless the [`ControlFlowGraph`] has its own virtual
+/// exit block, this virtual exit does not appear in the tree. Instead,
+/// the blocks that are immediately post-dominated by it are the [roots]
+/// of the tree.
+///
+/// Blocks that can not be reached from the entry block (or, for
+/// post-dominators, that can not reach an exit) are not part of
+/// the tree.
+///
+/// [basic block]: crate::BasicBlock
+/// [dominator tree]: https://en.wikipedia.org/wiki/Dominator_(graph_theory)
+/// [roots]: DominatorTree::roots
+#[derive(Debug)]
+pub struct DominatorTree {
+    roots: Vec<NodeIndex>,
+    idom: BTreeMap<NodeIndex, NodeIndex>,
+    children: BTreeMap<NodeIndex, Vec<NodeIndex>>,
+    frontiers: BTreeMap<NodeIndex, BTreeSet<NodeIndex>>,
+}
+
+impl DominatorTree {
+    /// Compute the dominator tree for a [`ControlFlowGraph`].
+    ///
+    /// The root of the tree is the entry block.
+    pub fn dominators(cfg: &ControlFlowGraph) -> Self {
+        let graph = &cfg.graph;
+        let node_count = graph.node_count();
+        let successors = (0..node_count)
+            .map(|n| {
+                graph
+                    .neighbors_directed(NodeIndex::new(n), EdgeDirection::Outgoing)
+                    .map(|s| s.index())
+                    .collect()
+            })
+            .collect::<Vec<Vec<usize>>>();
+        let predecessors = (0..node_count)
+            .map(|n| {
+                graph
+                    .neighbors_directed(NodeIndex::new(n), EdgeDirection::Incoming)
+                    .map(|p| p.index())
+                    .collect()
+            })
+            .collect::<Vec<Vec<usize>>>();
+        match cfg.entry_block {
+            Some(entry) => DominatorTree::build(entry.index(), &successors, &predecessors, false),
+            None => DominatorTree::build(0, &[vec![]], &[vec![]], true),
+        }
+    }
+
+    /// Compute the post-dominator tree for a [`ControlFlowGraph`].
+    ///
+    /// This is computed over the reversed graph, starting from a virtual
+    /// exit node that every exit block and every block without successors
+    /// flows into. If the graph already has a [virtual exit block], then
+    /// that is used instead and it is the root of the tree.
+    ///
+    /// [virtual exit block]: ControlFlowGraph::add_virtual_exit
+    pub fn post_dominators(cfg: &ControlFlowGraph) -> Self {
+        let graph = &cfg.graph;
+        let node_count = graph.node_count();
+        let virtual_exit = node_count;
+        let mut successors = (0..node_count)
+            .map(|n| {
+                graph
+                    .neighbors_directed(NodeIndex::new(n), EdgeDirection::Incoming)
+                    .map(|p| p.index())
+                    .collect()
+            })
+            .collect::<Vec<Vec<usize>>>();
+        let mut predecessors = (0..node_count)
+            .map(|n| {
+                graph
+                    .neighbors_directed(NodeIndex::new(n), EdgeDirection::Outgoing)
+                    .map(|s| s.index())
+                    .collect()
+            })
+            .collect::<Vec<Vec<usize>>>();
+        let exits = match cfg.exit_block {
+            Some(exit) => vec![exit.index()],
+            None => (0..node_count)
+                .filter(|&n| {
+                    predecessors[n].is_empty() || cfg.exit_blocks.contains_key(&NodeIndex::new(n))
+                })
+                .collect::<Vec<usize>>(),
+        };
+        for &exit in &exits {
+            predecessors[exit].push(virtual_exit);
+        }
+        successors.push(exits);
+        predecessors.push(vec![]);
+        DominatorTree::build(virtual_exit, &successors, &predecessors, true)
+    }
+
+    /// Build the tree given a graph expressed as adjacency lists.
+    ///
+    /// If `virtual_root` is set, the root is the last node and is not
+    /// a real block, so it is left out of the tree.
+    fn build(
+        root: usize,
+        successors: &[Vec<usize>],
+        predecessors: &[Vec<usize>],
+        virtual_root: bool,
+    ) -> Self {
+        let mut tree = DominatorTree {
+            roots: vec![],
+            idom: BTreeMap::new(),
+            children: BTreeMap::new(),
+            frontiers: BTreeMap::new(),
+        };
+        let real_node_count = if virtual_root {
+            successors.len() - 1
+        } else {
+            successors.len()
+        };
+        let is_real = |n: usize| n < real_node_count;
+
+        let idoms = compute_idoms(root, successors, predecessors);
+        for (node, idom) in idoms.iter().enumerate() {
+            let idom = match *idom {
+                Some(idom) if is_real(node) => idom,
+                _ => continue,
+            };
+            tree.frontiers.insert(NodeIndex::new(node), BTreeSet::new());
+            if node == root || !is_real(idom) {
+                tree.roots.push(NodeIndex::new(node));
+            } else {
+                tree.idom.insert(NodeIndex::new(node), NodeIndex::new(idom));
+                tree.children
+                    .entry(NodeIndex::new(idom))
+                    .or_default()
+                    .push(NodeIndex::new(node));
+            }
+        }
+
+        // Compute dominance frontiers using the method from Cooper, Harvey
+        // and Kennedy. Each join point is in the frontier of every block
+        // on the path up the tree from each of its predecessors to its
+        // immediate dominator. The root is treated as having an extra
+        // predecessor from outside of the graph, so it is a join point
+        // as soon as anything branches back to it.
+        for (node, preds) in predecessors.iter().enumerate() {
+            if !is_real(node) || (preds.len() < 2 && node != root) {
+                continue;
+            }
+            let node_idom = match idoms[node] {
+                Some(idom) => idom,
+                None => continue,
+            };
+            for &pred in preds {
+                if idoms[pred].is_none() {
+                    continue;
+                }
+                let mut runner = pred;
+                loop {
+                    if runner == node_idom && node != root {
+                        break;
+                    }
+                    if is_real(runner) {
+                        tree.frontiers
+                            .entry(NodeIndex::new(runner))
+                            .or_default()
+                            .insert(NodeIndex::new(node));
+                    }
+                    if runner == root {
+                        break;
+                    }
+                    runner = idoms[runner].unwrap();
+                }
+            }
+        }
+        tree
+    }
+
+    /// The roots of the tree.
+    ///
+    /// For dominators, this is the entry block. For post-dominators, these
+    /// are the blocks that are immediately post-dominated by the virtual
+    /// exit node.
+    pub fn roots(&self) -> &[NodeIndex] {
+        &self.roots
+    }
+
+    /// Is this block part of the tree?
+    pub fn contains(&self, node: NodeIndex) -> bool {
+        self.frontiers.contains_key(&node)
+    }
+
+    /// The immediate dominator of a block.
+    ///
+    /// This is `None` for the roots of the tree and for blocks that are
+    /// not part of the tree.
+    pub fn immediate_dominator(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.idom.get(&node).cloned()
+    }
+
+    /// Does `a` dominate `b`? Every block dominates itself.
+    pub fn dominates(&self, a: NodeIndex, b: NodeIndex) -> bool {
+        if !self.contains(a) || !self.contains(b) {
+            return false;
+        }
+        let mut current = b;
+        loop {
+            if current == a {
+                return true;
+            }
+            match self.immediate_dominator(current) {
+                Some(idom) => current = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// Does `a` dominate `b` without being the same block?
+    pub fn strictly_dominates(&self, a: NodeIndex, b: NodeIndex) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// The blocks that are immediately dominated by a block.
+    pub fn children(&self, node: NodeIndex) -> &[NodeIndex] {
+        self.children.get(&node).map_or(&[], |c| c.as_slice())
+    }
+
+    /// The [dominance frontier] of a block.
+    ///
+    /// For post-dominators, this is the post-dominance frontier, which
+    /// gives the blocks that this block is control dependent upon.
+    ///
+    /// Returns `None` if the block is not part of the tree.
+    ///
+    /// [dominance frontier]: https://en.wikipedia.org/wiki/Dominator_(graph_theory)
+    pub fn dominance_frontier(&self, node: NodeIndex) -> Option<&BTreeSet<NodeIndex>> {
+        self.frontiers.get(&node)
+    }
+}
+
+/// Compute the immediate dominators using the iterative algorithm
+/// from "A Simple, Fast Dominance Algorithm" by Cooper, Harvey and
+/// Kennedy.
+///
+/// The root is its own immediate dominator. Nodes that are not
+/// reachable from the root have no immediate dominator.
+fn compute_idoms(
+    root: usize,
+    successors: &[Vec<usize>],
+    predecessors: &[Vec<usize>],
+) -> Vec<Option<usize>> {
+    let node_count = successors.len();
+
+    // Compute a postorder numbering of the reachable nodes.
+    let mut postorder = Vec::with_capacity(node_count);
+    let mut visited = vec![false; node_count];
+    let mut stack = vec![(root, 0)];
+    visited[root] = true;
+    while let Some((node, next_child)) = stack.last_mut() {
+        if let Some(&succ) = successors[*node].get(*next_child) {
+            *next_child += 1;
+            if !visited[succ] {
+                visited[succ] = true;
+                stack.push((succ, 0));
+            }
+        } else {
+            postorder.push(*node);
+            stack.pop();
+        }
+    }
+    let mut postorder_number = vec![usize::MAX; node_count];
+    for (number, &node) in postorder.iter().enumerate() {
+        postorder_number[node] = number;
+    }
+
+    let mut idoms: Vec<Option<usize>> = vec![None; node_count];
+    idoms[root] = Some(root);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        // Walk in reverse postorder, skipping the root.
+        for &node in postorder.iter().rev().skip(1) {
+            let mut new_idom = None;
+            for &pred in &predecessors[node] {
+                if idoms[pred].is_none() {
+                    continue;
+                }
+                new_idom = Some(match new_idom {
+                    None => pred,
+                    Some(current) => intersect(&idoms, &postorder_number, pred, current),
+                });
+            }
+            if new_idom.is_some() && idoms[node] != new_idom {
+                idoms[node] = new_idom;
+                changed = true;
+            }
+        }
+    }
+    idoms
+}
+
+fn intersect(
+    idoms: &[Option<usize>],
+    postorder_number: &[usize],
+    mut finger1: usize,
+    mut finger2: usize,
+) -> usize {
+    while finger1 != finger2 {
+        while postorder_number[finger1] < postorder_number[finger2] {
+            finger1 = idoms[finger1].unwrap();
+        }
+        while postorder_number[finger2] < postorder_number[finger1] {
+            finger2 = idoms[finger2].unwrap();
+        }
+    }
+    finger1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DominatorTree;
+    use crate::address::Address;
+    use crate::cfg::ControlFlowGraph;
+    use crate::tests::*;
+    use std::collections::BTreeSet;
+
+    fn diamond() -> Vec<TestInstruction> {
+        vec![
+            TestInstruction::new(0, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(1, Opcode::Add),
+            TestInstruction::new(2, Opcode::Jmp(Address::new(4))),
+            TestInstruction::new(3, Opcode::Add),
+            TestInstruction::new(4, Opcode::Ret),
+        ]
+    }
+
+    #[test]
+    fn empty() {
+        let insts: Vec<TestInstruction> = vec![];
+        let cfg = ControlFlowGraph::new(&insts);
+        assert!(DominatorTree::dominators(&cfg).roots().is_empty());
+        assert!(DominatorTree::post_dominators(&cfg).roots().is_empty());
+    }
+
+    #[test]
+    fn dominators() {
+        let insts = diamond();
+        let cfg = ControlFlowGraph::new(&insts);
+        let a = cfg.block_finder[&Address::new(0)];
+        let b = cfg.block_finder[&Address::new(1)];
+        let c = cfg.block_finder[&Address::new(3)];
+        let d = cfg.block_finder[&Address::new(4)];
+
+        let doms = DominatorTree::dominators(&cfg);
+        assert_eq!(doms.roots(), &[a]);
+        assert_eq!(doms.immediate_dominator(a), None);
+        assert_eq!(doms.immediate_dominator(b), Some(a));
+        assert_eq!(doms.immediate_dominator(c), Some(a));
+        assert_eq!(doms.immediate_dominator(d), Some(a));
+        assert!(doms.dominates(a, d));
+        assert!(doms.dominates(d, d));
+        assert!(!doms.strictly_dominates(d, d));
+        assert!(!doms.dominates(b, d));
+        assert_eq!(doms.children(a).len(), 3);
+        assert!(doms.children(d).is_empty());
+
+        let just_d: BTreeSet<_> = vec![d].into_iter().collect();
+        assert_eq!(doms.dominance_frontier(a), Some(&BTreeSet::new()));
+        assert_eq!(doms.dominance_frontier(b), Some(&just_d));
+        assert_eq!(doms.dominance_frontier(c), Some(&just_d));
+    }
+
+    #[test]
+    fn post_dominators() {
+        let insts = diamond();
+        let cfg = ControlFlowGraph::new(&insts);
+        let a = cfg.block_finder[&Address::new(0)];
+        let b = cfg.block_finder[&Address::new(1)];
+        let c = cfg.block_finder[&Address::new(3)];
+        let d = cfg.block_finder[&Address::new(4)];
+
+        let pdoms = DominatorTree::post_dominators(&cfg);
+        assert_eq!(pdoms.roots(), &[d]);
+        assert_eq!(pdoms.immediate_dominator(a), Some(d));
+        assert_eq!(pdoms.immediate_dominator(b), Some(d));
+        assert_eq!(pdoms.immediate_dominator(c), Some(d));
+        assert!(pdoms.dominates(d, a));
+        assert!(!pdoms.dominates(b, a));
+
+        let just_a: BTreeSet<_> = vec![a].into_iter().collect();
+        assert_eq!(pdoms.dominance_frontier(b), Some(&just_a));
+        assert_eq!(pdoms.dominance_frontier(c), Some(&just_a));
+        assert_eq!(pdoms.dominance_frontier(d), Some(&BTreeSet::new()));
+    }
+
+    #[test]
+    fn loop_frontier() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(0))),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let header = cfg.block_finder[&Address::new(0)];
+        let exit = cfg.block_finder[&Address::new(2)];
+
+        let doms = DominatorTree::dominators(&cfg);
+        let just_header: BTreeSet<_> = vec![header].into_iter().collect();
+        assert_eq!(doms.dominance_frontier(header), Some(&just_header));
+        assert_eq!(doms.immediate_dominator(exit), Some(header));
+    }
+
+    #[test]
+    fn multiple_exits() {
+        let insts = [
+            TestInstruction::new(0, Opcode::CJmp(Address::new(2))),
+            TestInstruction::new(1, Opcode::Ret),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let entry = cfg.block_finder[&Address::new(0)];
+
+        let pdoms = DominatorTree::post_dominators(&cfg);
+        assert_eq!(pdoms.roots().len(), 3);
+        assert!(pdoms.roots().contains(&entry));
+        assert_eq!(pdoms.immediate_dominator(entry), None);
+    }
+
+    #[test]
+    fn virtual_exit() {
+        let insts = [
+            TestInstruction::new(0, Opcode::CJmp(Address::new(2))),
+            TestInstruction::new(1, Opcode::Ret),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+        let mut cfg = ControlFlowGraph::new(&insts);
+        let exit = cfg.add_virtual_exit().unwrap();
+        let entry = cfg.entry_block.unwrap();
+
+        let pdoms = DominatorTree::post_dominators(&cfg);
+        assert_eq!(pdoms.roots(), &[exit]);
+        assert_eq!(pdoms.immediate_dominator(entry), Some(exit));
+        assert_eq!(pdoms.children(exit).len(), 3);
+    }
+
+    #[test]
+    fn no_exit() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::Jmp(Address::new(0))),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let pdoms = DominatorTree::post_dominators(&cfg);
+        assert!(pdoms.roots().is_empty());
+        assert!(!pdoms.contains(cfg.entry_block.unwrap()));
+    }
+}
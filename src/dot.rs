@@ -0,0 +1,208 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::basicblock::{BasicBlock, BasicBlockEdge, EdgeType};
+use crate::function::Function;
+use crate::instruction::Instruction;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use std::fmt;
+
+type NodeAttributes<'a> = Box<dyn Fn(NodeIndex, &BasicBlock) -> String + 'a>;
+type EdgeAttributes<'a> = Box<dyn Fn(EdgeIndex, &BasicBlockEdge) -> String + 'a>;
+
+/// Render the [control flow graph] of a [`Function`] in the
+/// [Graphviz] DOT format.
+///
+/// Each [`BasicBlock`] is rendered as a record node that lists the
+/// instructions within it. Edges are styled by their [`EdgeType`]:
+/// taken branches are green, fallthroughs are red, unconditional
+/// edges are blue, switch cases are orange and labeled with their case
+/// value and exception edges and edges to the virtual exit block are
+/// dashed. The entry block is marked by an edge from a point shaped node.
+///
+/// The output is produced by the `fmt::Display` implementation, so
+/// `Dot::new(&function).to_string()` gives the whole graph.
+///
+/// Additional attributes for nodes and edges can be supplied with
+/// [`Dot::with_node_attributes`] and [`Dot::with_edge_attributes`].
+///
+/// [control flow graph]: crate::ControlFlowGraph
+/// [Graphviz]: https://graphviz.org/
+pub struct Dot<'a, I: Instruction> {
+    function: &'a Function<I>,
+    node_attributes: Option<NodeAttributes<'a>>,
+    edge_attributes: Option<EdgeAttributes<'a>>,
+}
+
+impl<'a, I: Instruction> Dot<'a, I> {
+    /// Construct a `Dot` renderer for a `Function`.
+    pub fn new(function: &'a Function<I>) -> Self {
+        Dot {
+            function,
+            node_attributes: None,
+            edge_attributes: None,
+        }
+    }
+
+    /// Supply additional attributes for each node.
+    ///
+    /// The returned text is added to the attribute list for the node,
+    /// so it should be of the form `fillcolor=red style=filled`.
+    pub fn with_node_attributes<F>(mut self, f: F) -> Self
+    where
+        F: Fn(NodeIndex, &BasicBlock) -> String + 'a,
+    {
+        self.node_attributes = Some(Box::new(f));
+        self
+    }
+
+    /// Supply additional attributes for each edge.
+    ///
+    /// The returned text is added to the attribute list for the edge,
+    /// so it should be of the form `penwidth=2 label="hot"`.
+    pub fn with_edge_attributes<F>(mut self, f: F) -> Self
+    where
+        F: Fn(EdgeIndex, &BasicBlockEdge) -> String + 'a,
+    {
+        self.edge_attributes = Some(Box::new(f));
+        self
+    }
+}
+
+/// Escape text for use within a record label.
+fn escape_record(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '{' | '}' | '|' | '<' | '>' | '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\l"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn edge_style(edge_type: EdgeType) -> String {
+    match edge_type {
+        EdgeType::ConditionalTaken => "color=green".to_owned(),
+        EdgeType::ConditionalFallthrough => "color=red".to_owned(),
+        EdgeType::Unconditional => "color=blue".to_owned(),
+        EdgeType::CallFallthrough => "color=blue style=dotted".to_owned(),
+        EdgeType::IndirectJump => "color=purple".to_owned(),
+        EdgeType::SwitchCase(value) => format!("color=orange label=\"{}\"", value),
+        EdgeType::SwitchDefault => "color=orange label=\"default\"".to_owned(),
+        EdgeType::Exception => "color=black style=dashed".to_owned(),
+        EdgeType::TailCall => "color=gray style=dashed label=\"tail call\"".to_owned(),
+        EdgeType::Exit => "color=gray style=dashed".to_owned(),
+    }
+}
+
+impl<I: Instruction> fmt::Display for Dot<'_, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cfg = &self.function.control_flow_graph;
+        writeln!(f, "digraph {{")?;
+        writeln!(f, "    node [shape=record fontname=\"monospace\"];")?;
+        if let Some(entry) = cfg.entry_block {
+            writeln!(f, "    entry [shape=point];")?;
+            writeln!(f, "    entry -> bb{};", entry.index())?;
+        }
+        for idx in cfg.graph.node_indices() {
+            let block = &cfg.graph[idx];
+            let header = match block.name {
+                Some(ref name) => escape_record(name),
+                None => format!("{:#x}", block.address),
+            };
+            write!(f, "    bb{} [label=\"{{{}:|", idx.index(), header)?;
+            for &inst_idx in &block.instruction_indices {
+                let inst = &self.function.instructions[inst_idx];
+                write!(f, "{}\\l", escape_record(&inst.to_string()))?;
+            }
+            write!(f, "}}\"")?;
+            if let Some(ref attrs) = self.node_attributes {
+                write!(f, " {}", attrs(idx, block))?;
+            }
+            writeln!(f, "];")?;
+        }
+        for edge in cfg.graph.edge_indices() {
+            let (source, target) = cfg.graph.edge_endpoints(edge).unwrap();
+            let weight = &cfg.graph[edge];
+            write!(
+                f,
+                "    bb{} -> bb{} [{}",
+                source.index(),
+                target.index(),
+                edge_style(weight.edge_type)
+            )?;
+            if let Some(ref attrs) = self.edge_attributes {
+                write!(f, " {}", attrs(edge, weight))?;
+            }
+            writeln!(f, "];")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dot;
+    use crate::address::Address;
+    use crate::function::Function;
+    use crate::symbol::Symbol;
+    use crate::tests::*;
+
+    #[test]
+    fn render() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(2, Opcode::Add),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let dot = Dot::new(&f).to_string();
+
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.contains("    entry -> bb0;\n"));
+        assert!(
+            dot.contains("    bb0 [label=\"{0x0:|Add\\lCJmp(Address \\{ address: 3 \\})\\l}\"];\n")
+        );
+        assert!(dot.contains("    bb1 [label=\"{0x3:|Ret\\l}\"];\n"));
+        assert!(dot.contains("    bb0 -> bb1 [color=green];\n"));
+        assert!(dot.contains("    bb0 -> bb2 [color=red];\n"));
+        assert!(dot.contains("    bb2 -> bb1 [color=blue];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn custom_attributes() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::Jmp(Address::new(2))),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let dot = Dot::new(&f)
+            .with_node_attributes(|idx, _| format!("id=n{}", idx.index()))
+            .with_edge_attributes(|_, _| "penwidth=2".to_owned())
+            .to_string();
+
+        assert!(dot.contains("\\l}\" id=n0];\n"));
+        assert!(dot.contains("    bb0 -> bb1 [color=blue penwidth=2];\n"));
+    }
+
+    #[test]
+    fn empty() {
+        let f: Function<TestInstruction> =
+            Function::new(Symbol::new(Address::new(100), None), vec![]);
+        assert_eq!(
+            Dot::new(&f).to_string(),
+            "digraph {\n    node [shape=record fontname=\"monospace\"];\n}\n"
+        );
+    }
+}
@@ -5,8 +5,22 @@
 // except according to those terms.
 
 use crate::address::Address;
+use crate::operand::{Operand, Register};
 use std::fmt;
 
+/// A target of a multi-way branch, such as a WebAssembly `br_table`,
+/// a jump through a jump table or a JVM `tableswitch`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BranchTarget {
+    /// The [`address`] that is branched to.
+    ///
+    /// [`address`]: Address
+    pub address: Address,
+    /// The case value that selects this target, or `None` if this is
+    /// the default target.
+    pub case: Option<i64>,
+}
+
 /// An assembly instruction, bytecode operation, VM operation, etc.
 ///
 /// This trait will be implemented for a variety of backends and
@@ -32,6 +46,52 @@ pub trait Instruction: fmt::Debug + fmt::Display {
     /// Any associated `comment` text for this instruction.
     fn comment(&self) -> Option<String>;
 
+    /// The [operands] of this `Instruction`.
+    ///
+    /// [operands]: Operand
+    fn operands(&self) -> Vec<Operand> {
+        vec![]
+    }
+
+    /// The [registers] that are read by this `Instruction`.
+    ///
+    /// This includes registers that are read implicitly as well as
+    /// those used to compute the address of a memory operand.
+    ///
+    /// [registers]: Register
+    fn registers_read(&self) -> Vec<Register> {
+        vec![]
+    }
+
+    /// The [registers] that are written by this `Instruction`.
+    ///
+    /// This includes registers that are written implicitly.
+    ///
+    /// [registers]: Register
+    fn registers_written(&self) -> Vec<Register> {
+        vec![]
+    }
+
+    /// Does this instruction load from memory?
+    ///
+    /// The location that is accessed, if known, should be given
+    /// as an [`Operand::Memory`] from [`operands`].
+    ///
+    /// [`operands`]: Instruction::operands
+    fn reads_memory(&self) -> bool {
+        false
+    }
+
+    /// Does this instruction store to memory?
+    ///
+    /// The location that is accessed, if known, should be given
+    /// as an [`Operand::Memory`] from [`operands`].
+    ///
+    /// [`operands`]: Instruction::operands
+    fn writes_memory(&self) -> bool {
+        false
+    }
+
     /// Does this instruction terminate a `BasicBlock`?
     ///
     /// This is used when constructing a [control flow graph]
@@ -40,7 +100,13 @@ pub trait Instruction: fmt::Debug + fmt::Display {
     ///
     /// [`BasicBlock`]: crate::BasicBlock
     fn is_block_terminator(&self) -> bool {
-        self.is_call() || self.is_local_jump() || self.is_return()
+        self.is_call()
+            || self.is_local_jump()
+            || self.is_return()
+            || self.is_tail_call()
+            || self.is_trap()
+            || self.is_throw()
+            || !self.exception_handlers().is_empty()
     }
 
     /// Does this instruction represent a call?
@@ -52,9 +118,102 @@ pub trait Instruction: fmt::Debug + fmt::Display {
     /// Does this instruction represent a local conditional or unconditional jump?
     fn is_local_jump(&self) -> bool;
 
+    /// Does this instruction represent an indirect jump, through a
+    /// register or memory?
+    ///
+    /// Indirect jumps are also local jumps. If the target is known, it
+    /// should be reported by `target_address`.
+    fn is_indirect_jump(&self) -> bool {
+        false
+    }
+
     /// Does this instruction represent a function return?
     fn is_return(&self) -> bool;
 
+    /// Does this instruction represent a tail call?
+    ///
+    /// A tail call transfers control to another function, which returns
+    /// directly to our caller. Control does not come back to this function.
+    fn is_tail_call(&self) -> bool {
+        false
+    }
+
+    /// Does this instruction represent a tail call that may fail?
+    ///
+    /// When the tail call succeeds, control leaves the function as it
+    /// does for a [tail call]. When it fails, control continues with the
+    /// next instruction. This covers the eBPF `bpf_tail_call`. Such an
+    /// instruction is also a call, so that it is a call site.
+    ///
+    /// [tail call]: Instruction::is_tail_call
+    fn is_conditional_tail_call(&self) -> bool {
+        false
+    }
+
+    /// Does this instruction always trap or abort, so that control
+    /// never continues past it?
+    ///
+    /// This covers instructions like the WebAssembly `unreachable` or
+    /// the x86 `ud2`.
+    fn is_trap(&self) -> bool {
+        false
+    }
+
+    /// Does this instruction always throw an exception?
+    ///
+    /// Control goes to the [`exception_handlers`], if there are any
+    /// within this function, or else leaves the function.
+    ///
+    /// [`exception_handlers`]: Instruction::exception_handlers
+    fn is_throw(&self) -> bool {
+        false
+    }
+
     /// If this is a call or local jump, what is the target address?
     fn target_address(&self) -> Option<Address>;
+
+    /// If this is a local conditional jump, where does control go when
+    /// the jump is not taken?
+    ///
+    /// By default, this is `None` and control falls through to the next
+    /// instruction. This is for platforms where a conditional jump has
+    /// two targets, such as classic BPF.
+    fn fallthrough_address(&self) -> Option<Address> {
+        None
+    }
+
+    /// If this is an indirect call, what are the addresses of the
+    /// functions that it might call?
+    ///
+    /// This is only used when `target_address` is `None`. The list must
+    /// include every function that might be called, so an instruction
+    /// that may call a function it does not know about, such as one
+    /// provided by the host, should return an empty list, which means
+    /// that nothing is known about the target.
+    fn possible_call_targets(&self) -> Vec<Address> {
+        vec![]
+    }
+
+    /// If this is a multi-way branch, what are the targets?
+    ///
+    /// An instruction that reports branch targets must also be a local
+    /// jump. Each target becomes a separate edge in the [control flow
+    /// graph] and the `target_address` is not used. If this is also a
+    /// local conditional jump, then it can fall through to the next
+    /// instruction as well.
+    ///
+    /// [control flow graph]: crate::ControlFlowGraph
+    fn branch_targets(&self) -> Vec<BranchTarget> {
+        vec![]
+    }
+
+    /// If this instruction can raise an exception that is handled within
+    /// this function, what are the addresses of the handlers or landing
+    /// pads?
+    ///
+    /// There may be several handlers on platforms where the handler is
+    /// chosen by the kind of exception, such as WebAssembly.
+    fn exception_handlers(&self) -> Vec<Address> {
+        vec![]
+    }
 }
@@ -16,10 +16,11 @@
 //!   to teach [`Instruction`] how to report the correct information
 //!   for your generated code.
 //! * Reconstructing the [control flow graph] from a body of compiled
-//!   code.
-//! * **(Future)** Reconstructing loops and higher level control
-//!   flow constructs.
-//! * **(Future)** Performing [data flow analysis].
+//!   code and rendering it in the [Graphviz] DOT format.
+//! * Reconstructing loops using Havlak's algorithm.
+//! * **(Future)** Reconstructing higher level control flow constructs.
+//! * Performing [data flow analysis] with a generic worklist solver,
+//!   including liveness, reaching definitions, SSA and memory SSA.
 //! * **(Future)** Generating HTML and other rich output formats
 //!   to assist in visualizing structure and higher level presentations
 //!   of the data derived from the generated code.
@@ -67,13 +68,8 @@
 //! In the future, we want to extend this library to support a number of
 //! additional features:
 //!
-//! * Implement DOT output for the [`ControlFlowGraph`]. This can probably be done with the
-//!   help of [`petgraph`].
 //! * HTML output modes?
-//! * Implement loop finding. (Havlak)
 //! * Implement the [Capstone Engine] backend as a separate crate.
-//! * Make [`Instruction`] aware of operands, registers
-//! * Data flow support. Memory SSA?
 //! * Should we deal with mangled symbols at this level?
 //! * So much more!
 //!
@@ -84,7 +80,7 @@
 //! [Capstone Engine]: http://www.capstone-engine.org/
 //! [control flow graph]: https://en.wikipedia.org/wiki/Control_flow_graph
 //! [data flow analysis]: https://en.wikipedia.org/wiki/Data-flow_analysis
-//! [`petgraph`]: https://crates.io/crates/petgraph
+//! [Graphviz]: https://graphviz.org/
 
 #![warn(missing_docs)]
 #![deny(
@@ -99,13 +95,22 @@ extern crate petgraph;
 
 mod address;
 mod basicblock;
+mod callgraph;
 mod callgraphanalysis;
 mod cfg;
+mod dataflow;
+mod dominators;
+mod dot;
 mod function;
 mod instruction;
+mod liveness;
 mod loops;
 mod memory;
+mod memoryssa;
 mod module;
+mod operand;
+mod reachingdefinitions;
+mod ssa;
 mod symbol;
 mod target;
 #[cfg(feature = "webassembly")]
@@ -113,17 +118,31 @@ mod webassembly;
 
 pub use self::address::Address;
 pub use self::basicblock::{BasicBlock, BasicBlockEdge, EdgeType};
+pub use self::callgraph::CallGraph;
 pub use self::callgraphanalysis::{CallGraphAnalysis, CallSite, CallSiteTarget};
-pub use self::cfg::ControlFlowGraph;
+pub use self::cfg::{ControlFlowGraph, ExitType};
+pub use self::dataflow::{
+    DataFlowAnalysis, DataFlowResults, Direction, InstructionStates, Lattice,
+};
+pub use self::dominators::DominatorTree;
+pub use self::dot::Dot;
 pub use self::function::Function;
-pub use self::instruction::Instruction;
+pub use self::instruction::{BranchTarget, Instruction};
+pub use self::liveness::Liveness;
 pub use self::loops::{find_loops, LoopStructureGraph, SimpleLoop};
 pub use self::memory::{Error, Memory, Segment};
+pub use self::memoryssa::{
+    AliasOracle, AliasResult, BaseOffsetAliasOracle, ConservativeAliasOracle, MemoryAccess,
+    MemoryAccessId, MemorySsa,
+};
 pub use self::module::Module;
+pub use self::operand::{MemoryOperand, Operand, Register};
+pub use self::reachingdefinitions::{Definition, ReachingDefinitions};
+pub use self::ssa::{SsaForm, ValueDefinition, ValueId, ValueUser};
 pub use self::symbol::Symbol;
 pub use self::target::Target;
 #[cfg(feature = "webassembly")]
-pub use self::webassembly::WasmInstruction;
+pub use self::webassembly::{WasmAddressing, WasmDeclaration, WasmInstruction, Wat};
 
 #[cfg(feature = "capstone")]
 mod capstone;
@@ -135,6 +154,11 @@ mod bpf;
 #[cfg(feature = "bpf")]
 pub use self::bpf::BpfInstruction;
 
+#[cfg(feature = "cbpf")]
+mod cbpf;
+#[cfg(feature = "cbpf")]
+pub use self::cbpf::CbpfInstruction;
+
 #[cfg(feature = "burst")]
 mod burst;
 #[cfg(feature = "burst")]
@@ -149,16 +173,40 @@ mod tests {
     //! a real disassembler.
 
     use crate::address::Address;
-    use crate::instruction::Instruction;
+    use crate::instruction::{BranchTarget, Instruction};
+    use crate::operand::{MemoryOperand, Operand, Register};
 
     /// Opcodes that we'll use as instructions.
     #[derive(Debug)]
     pub enum Opcode {
         Add,
         CJmp(Address),
+        /// A conditional jump to the first address, or else to the second.
+        Branch(Address, Address),
         Jmp(Address),
         Call(Address),
+        ICall(Vec<Address>),
+        Invoke(Address, Address),
+        IJmp(Option<Address>),
+        TailCall(Address),
+        /// A tail call through a table, which may fail.
+        CondTailCall,
+        Switch(Vec<BranchTarget>),
         Ret,
+        Trap,
+        /// Throw an exception to any of the handlers.
+        Throw(Vec<Address>),
+        /// Write a constant to a register.
+        Set(u32),
+        /// Copy the second register into the first.
+        Mov(u32, u32),
+        /// Read a register.
+        Use(u32),
+        /// Load into the first register from the second register plus
+        /// an offset.
+        Load(u32, u32, i64),
+        /// Store the last register to the first register plus an offset.
+        Store(u32, i64, u32),
     }
 
     #[derive(Debug)]
@@ -196,15 +244,32 @@ mod tests {
             match self.opcode {
                 Opcode::Add => "add",
                 Opcode::CJmp(..) => "conditional-jump",
+                Opcode::Branch(..) => "branch",
                 Opcode::Jmp(..) => "jump",
                 Opcode::Call(..) => "call",
+                Opcode::ICall(..) => "indirect-call",
+                Opcode::Invoke(..) => "invoke",
+                Opcode::IJmp(..) => "indirect-jump",
+                Opcode::TailCall(..) => "tail-call",
+                Opcode::CondTailCall => "conditional-tail-call",
+                Opcode::Switch(..) => "switch",
                 Opcode::Ret => "return",
+                Opcode::Trap => "trap",
+                Opcode::Throw(..) => "throw",
+                Opcode::Set(..) => "set",
+                Opcode::Mov(..) => "move",
+                Opcode::Use(..) => "use",
+                Opcode::Load(..) => "load",
+                Opcode::Store(..) => "store",
             }
         }
 
         fn is_call(&self) -> bool {
             match self.opcode {
                 Opcode::Call(..) => true,
+                Opcode::ICall(..) => true,
+                Opcode::Invoke(..) => true,
+                Opcode::CondTailCall => true,
                 _ => false,
             }
         }
@@ -212,6 +277,7 @@ mod tests {
         fn is_local_conditional_jump(&self) -> bool {
             match self.opcode {
                 Opcode::CJmp(..) => true,
+                Opcode::Branch(..) => true,
                 _ => false,
             }
         }
@@ -219,11 +285,18 @@ mod tests {
         fn is_local_jump(&self) -> bool {
             match self.opcode {
                 Opcode::CJmp(..) => true,
+                Opcode::Branch(..) => true,
                 Opcode::Jmp(..) => true,
+                Opcode::IJmp(..) => true,
+                Opcode::Switch(..) => true,
                 _ => false,
             }
         }
 
+        fn is_indirect_jump(&self) -> bool {
+            matches!(self.opcode, Opcode::IJmp(..))
+        }
+
         fn is_return(&self) -> bool {
             match self.opcode {
                 Opcode::Ret => true,
@@ -231,13 +304,118 @@ mod tests {
             }
         }
 
+        fn is_tail_call(&self) -> bool {
+            matches!(self.opcode, Opcode::TailCall(..))
+        }
+
+        fn is_conditional_tail_call(&self) -> bool {
+            matches!(self.opcode, Opcode::CondTailCall)
+        }
+
+        fn is_trap(&self) -> bool {
+            matches!(self.opcode, Opcode::Trap)
+        }
+
+        fn is_throw(&self) -> bool {
+            matches!(self.opcode, Opcode::Throw(..))
+        }
+
         fn target_address(&self) -> Option<Address> {
             match self.opcode {
                 Opcode::CJmp(addr) => Some(addr),
+                Opcode::Branch(addr, _) => Some(addr),
                 Opcode::Jmp(addr) => Some(addr),
                 Opcode::Call(addr) => Some(addr),
+                Opcode::Invoke(addr, _) => Some(addr),
+                Opcode::IJmp(addr) => addr,
+                Opcode::TailCall(addr) => Some(addr),
                 _ => None,
             }
         }
+
+        fn fallthrough_address(&self) -> Option<Address> {
+            match self.opcode {
+                Opcode::Branch(_, addr) => Some(addr),
+                _ => None,
+            }
+        }
+
+        fn possible_call_targets(&self) -> Vec<Address> {
+            match self.opcode {
+                Opcode::ICall(ref targets) => targets.clone(),
+                _ => vec![],
+            }
+        }
+
+        fn branch_targets(&self) -> Vec<BranchTarget> {
+            match self.opcode {
+                Opcode::Switch(ref targets) => targets.clone(),
+                _ => vec![],
+            }
+        }
+
+        fn exception_handlers(&self) -> Vec<Address> {
+            match self.opcode {
+                Opcode::Invoke(_, handler) => vec![handler],
+                Opcode::Throw(ref handlers) => handlers.clone(),
+                _ => vec![],
+            }
+        }
+
+        fn operands(&self) -> Vec<Operand> {
+            let memory = |base, displacement| {
+                Operand::Memory(MemoryOperand {
+                    base: Some(Register::Machine(base)),
+                    index: None,
+                    scale: 1,
+                    displacement,
+                    size: Some(8),
+                })
+            };
+            match self.opcode {
+                Opcode::Load(dst, base, offset) => {
+                    vec![
+                        Operand::Register(Register::Machine(dst)),
+                        memory(base, offset),
+                    ]
+                }
+                Opcode::Store(base, offset, src) => {
+                    vec![
+                        memory(base, offset),
+                        Operand::Register(Register::Machine(src)),
+                    ]
+                }
+                _ => vec![],
+            }
+        }
+
+        fn registers_read(&self) -> Vec<Register> {
+            match self.opcode {
+                Opcode::Mov(_, src) | Opcode::Use(src) | Opcode::Load(_, src, _) => {
+                    vec![Register::Machine(src)]
+                }
+                Opcode::Store(base, _, src) => {
+                    vec![Register::Machine(base), Register::Machine(src)]
+                }
+                _ => vec![],
+            }
+        }
+
+        fn registers_written(&self) -> Vec<Register> {
+            match self.opcode {
+                Opcode::Set(dst) | Opcode::Mov(dst, _) | Opcode::Load(dst, _, _) => {
+                    vec![Register::Machine(dst)]
+                }
+                _ => vec![],
+            }
+        }
+
+        fn reads_memory(&self) -> bool {
+            matches!(self.opcode, Opcode::Load(..))
+        }
+
+        fn writes_memory(&self) -> bool {
+            matches!(self.opcode, Opcode::Store(..))
+        }
     }
 }
@@ -0,0 +1,192 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::address::Address;
+use crate::dataflow::{DataFlowAnalysis, DataFlowResults, Direction};
+use crate::function::Function;
+use crate::instruction::Instruction;
+use crate::operand::Register;
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The [live registers] at each point within a [`Function`].
+///
+/// A register is live at a point if there is a path from that point
+/// to an instruction that reads the register without the register
+/// being written first. The registers read and written by each
+/// instruction come from [`Instruction::registers_read`] and
+/// [`Instruction::registers_written`].
+///
+/// Live registers are available for each [basic block] as well as
+/// for each instruction, either by index or by [`Address`].
+///
+/// [basic block]: crate::BasicBlock
+/// [live registers]: https://en.wikipedia.org/wiki/Live_variable_analysis
+#[derive(Debug)]
+pub struct Liveness {
+    blocks: DataFlowResults<BTreeSet<Register>>,
+    live_in: BTreeMap<usize, BTreeSet<Register>>,
+    live_out: BTreeMap<usize, BTreeSet<Register>>,
+    instruction_finder: BTreeMap<Address, usize>,
+}
+
+struct LivenessAnalysis;
+
+impl<I: Instruction> DataFlowAnalysis<I> for LivenessAnalysis {
+    type Domain = BTreeSet<Register>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn bottom(&self) -> Self::Domain {
+        BTreeSet::new()
+    }
+
+    fn transfer_instruction(&self, _index: usize, instruction: &I, state: &mut Self::Domain) {
+        for reg in instruction.registers_written() {
+            state.remove(&reg);
+        }
+        state.extend(instruction.registers_read());
+    }
+}
+
+impl Liveness {
+    /// Compute the live registers for a [`Function`].
+    pub fn new<I: Instruction>(function: &Function<I>) -> Self {
+        let cfg = &function.control_flow_graph;
+        let blocks = LivenessAnalysis.solve(cfg, &function.instructions);
+        let mut live_in = BTreeMap::new();
+        let mut live_out = BTreeMap::new();
+        for node in cfg.graph.node_indices() {
+            for state in
+                blocks.instruction_states(&LivenessAnalysis, cfg, node, &function.instructions)
+            {
+                live_in.insert(state.index, state.before);
+                live_out.insert(state.index, state.after);
+            }
+        }
+        let instruction_finder = function
+            .instructions
+            .iter()
+            .enumerate()
+            .map(|(idx, inst)| (inst.address(), idx))
+            .collect();
+        Liveness {
+            blocks,
+            live_in,
+            live_out,
+            instruction_finder,
+        }
+    }
+
+    /// The registers that are live at the start of a block.
+    pub fn block_live_in(&self, block: NodeIndex) -> Option<&BTreeSet<Register>> {
+        self.blocks.entry(block)
+    }
+
+    /// The registers that are live at the end of a block.
+    pub fn block_live_out(&self, block: NodeIndex) -> Option<&BTreeSet<Register>> {
+        self.blocks.exit(block)
+    }
+
+    /// The registers that are live before the instruction at `index`
+    /// executes.
+    pub fn live_in(&self, index: usize) -> Option<&BTreeSet<Register>> {
+        self.live_in.get(&index)
+    }
+
+    /// The registers that are live after the instruction at `index`
+    /// executes.
+    pub fn live_out(&self, index: usize) -> Option<&BTreeSet<Register>> {
+        self.live_out.get(&index)
+    }
+
+    /// The registers that are live when execution reaches `address`,
+    /// such as at a breakpoint. This is the same as the registers that
+    /// are live before the instruction at that address.
+    pub fn live_at(&self, address: Address) -> Option<&BTreeSet<Register>> {
+        self.instruction_finder
+            .get(&address)
+            .and_then(|&idx| self.live_in(idx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Liveness;
+    use crate::address::Address;
+    use crate::function::Function;
+    use crate::operand::Register;
+    use crate::symbol::Symbol;
+    use crate::tests::*;
+    use std::collections::BTreeSet;
+
+    fn regs(regs: &[u32]) -> BTreeSet<Register> {
+        regs.iter().map(|&r| Register::Machine(r)).collect()
+    }
+
+    #[test]
+    fn straight_line() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::Mov(2, 1)),
+            TestInstruction::new(2, Opcode::Use(2)),
+            TestInstruction::new(3, Opcode::Use(3)),
+            TestInstruction::new(4, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let liveness = Liveness::new(&f);
+
+        assert_eq!(liveness.live_in(0), Some(&regs(&[3])));
+        assert_eq!(liveness.live_out(0), Some(&regs(&[1, 3])));
+        assert_eq!(liveness.live_in(2), Some(&regs(&[2, 3])));
+        assert_eq!(liveness.live_out(3), Some(&regs(&[])));
+        assert_eq!(liveness.live_at(Address::new(1)), Some(&regs(&[1, 3])));
+        assert_eq!(liveness.live_at(Address::new(10)), None);
+
+        let entry = f.control_flow_graph.entry_block.unwrap();
+        assert_eq!(liveness.block_live_in(entry), Some(&regs(&[3])));
+    }
+
+    #[test]
+    fn branches() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(4))),
+            TestInstruction::new(2, Opcode::Use(1)),
+            TestInstruction::new(3, Opcode::Jmp(Address::new(5))),
+            TestInstruction::new(4, Opcode::Set(2)),
+            TestInstruction::new(5, Opcode::Use(2)),
+            TestInstruction::new(6, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let liveness = Liveness::new(&f);
+        let cfg = &f.control_flow_graph;
+        let entry = cfg.entry_block.unwrap();
+        let join = cfg.block_finder[&Address::new(5)];
+
+        assert_eq!(liveness.block_live_in(entry), Some(&regs(&[2])));
+        assert_eq!(liveness.block_live_out(entry), Some(&regs(&[1, 2])));
+        assert_eq!(liveness.block_live_in(join), Some(&regs(&[2])));
+        assert_eq!(liveness.live_in(4), Some(&regs(&[])));
+    }
+
+    #[test]
+    fn loops() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::Mov(1, 1)),
+            TestInstruction::new(2, Opcode::CJmp(Address::new(1))),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let liveness = Liveness::new(&f);
+
+        assert_eq!(liveness.live_out(2), Some(&regs(&[1])));
+        assert_eq!(liveness.live_in(0), Some(&regs(&[])));
+    }
+}
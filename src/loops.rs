@@ -4,36 +4,55 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-#![allow(missing_docs)]
-
 use crate::cfg::ControlFlowGraph;
 use petgraph::graph::NodeIndex;
+use petgraph::EdgeDirection;
+use std::collections::BTreeSet;
 
+/// A loop within a [`LoopStructureGraph`].
+///
+/// Loops refer to each other by their index within
+/// [`LoopStructureGraph::loops`].
 #[derive(Debug, Default)]
-pub struct SimpleLoop<'lsg> {
+pub struct SimpleLoop {
+    /// The [basic blocks] that belong to this loop, including the
+    /// header, but not including blocks belonging to nested loops.
+    ///
+    /// [basic blocks]: crate::BasicBlock
     pub basic_blocks: Vec<NodeIndex>,
-    pub children: Vec<&'lsg SimpleLoop<'lsg>>,
+    /// The loops nested directly within this loop.
+    pub children: Vec<usize>,
+    /// The unique number of this loop. The root loop is `0`.
     pub counter: usize,
-
-    // pub parent: ...
+    /// The loop that this loop is nested directly within. This is
+    /// only `None` for the root loop.
+    pub parent: Option<usize>,
+    /// The header of this loop. The root loop has no header.
     pub header: Option<NodeIndex>,
-
+    /// Is this the artificial loop at the root of the loop forest?
     pub is_root: bool,
+    /// Is this a reducible loop? Irreducible loops have more than one
+    /// entry point.
     pub is_reducible: bool,
+    /// How deeply nested loops within this loop go. A loop with no
+    /// children has a nesting level of `0`.
     pub nesting_level: usize,
+    /// How deep this loop is nested. The root loop has a depth of `0`.
     pub depth_level: usize,
 }
 
-impl SimpleLoop<'_> {
+impl SimpleLoop {
+    /// Construct a new `SimpleLoop`.
     pub fn new(counter: usize) -> Self {
         SimpleLoop {
             counter,
+            is_reducible: true,
             ..Default::default()
         }
     }
 }
 
-#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum BasicBlockType {
     /// Uninitialized basic block
     Top,
@@ -45,8 +64,6 @@ enum BasicBlockType {
     Single,
     /// Irreducible loop
     Irreducible,
-    /// Dead basic block
-    Dead,
 }
 
 /// Maintain loop structure for a given [`ControlFlowGraph`]
@@ -63,18 +80,135 @@ enum BasicBlockType {
 ///      loop-3    1                1
 ///        loop-2  0                2
 /// ```
-pub struct LoopStructureGraph<'lsg> {
+///
+/// `loop-0` is the root loop. It is always present as the first
+/// entry in `loops` and contains every block that is not part of
+/// some other loop.
+#[derive(Debug)]
+pub struct LoopStructureGraph {
+    /// The counter to be given to the next loop that is created.
     pub loop_counter: usize,
-    pub loops: Vec<SimpleLoop<'lsg>>,
-    pub root: SimpleLoop<'lsg>,
+    /// All loops, indexed by their `counter`. The root loop is first.
+    pub loops: Vec<SimpleLoop>,
 }
 
-impl Default for LoopStructureGraph<'_> {
+impl Default for LoopStructureGraph {
     fn default() -> Self {
+        let mut root = SimpleLoop::new(0);
+        root.is_root = true;
         LoopStructureGraph {
             loop_counter: 1,
-            loops: vec![],
-            root: SimpleLoop::new(0),
+            loops: vec![root],
+        }
+    }
+}
+
+impl LoopStructureGraph {
+    /// Construct a new `LoopStructureGraph` containing only the root loop.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The root of the loop forest.
+    pub fn root(&self) -> &SimpleLoop {
+        &self.loops[0]
+    }
+
+    fn create_new_loop(&mut self) -> usize {
+        let counter = self.loop_counter;
+        self.loop_counter += 1;
+        self.loops.push(SimpleLoop::new(counter));
+        counter
+    }
+
+    /// Attach top level loops to the root and compute the nesting
+    /// and depth levels for every loop.
+    fn calculate_nesting_level(&mut self) {
+        for idx in 1..self.loops.len() {
+            let parent = *self.loops[idx].parent.get_or_insert(0);
+            self.loops[parent].children.push(idx);
+        }
+        self.calculate_nesting_level_rec(0, 0);
+    }
+
+    fn calculate_nesting_level_rec(&mut self, idx: usize, depth: usize) {
+        self.loops[idx].depth_level = depth;
+        for child_pos in 0..self.loops[idx].children.len() {
+            let child = self.loops[idx].children[child_pos];
+            self.calculate_nesting_level_rec(child, depth + 1);
+            let nesting_level = self.loops[child].nesting_level + 1;
+            let current = &mut self.loops[idx];
+            current.nesting_level = current.nesting_level.max(nesting_level);
+        }
+    }
+}
+
+/// Marker for blocks that were not reached by the depth first search.
+const UNVISITED: usize = usize::MAX;
+
+/// A union-find structure over the depth first numbering of the
+/// basic blocks, used to collapse loops into their headers.
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        UnionFind {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find_set(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Path compression.
+        let mut current = node;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    fn union(&mut self, node: usize, into: usize) {
+        self.parent[node] = into;
+    }
+}
+
+/// Is `w` an ancestor of `v` in the depth first spanning tree?
+fn is_ancestor(w: usize, v: usize, last: &[usize]) -> bool {
+    w <= v && v <= last[w]
+}
+
+/// Number the blocks reachable from the entry block in depth first
+/// preorder, recording for each the number of its last descendant.
+fn do_dfs(
+    cfg: &ControlFlowGraph,
+    entry: NodeIndex,
+    number: &mut [usize],
+    nodes: &mut Vec<NodeIndex>,
+    last: &mut Vec<usize>,
+) {
+    let mut stack = vec![(entry, cfg.graph.neighbors(entry).detach())];
+    number[entry.index()] = 0;
+    nodes.push(entry);
+    last.push(0);
+    while let Some((node, walker)) = stack.last_mut() {
+        if let Some(next) = walker.next_node(&cfg.graph) {
+            if number[next.index()] == UNVISITED {
+                number[next.index()] = nodes.len();
+                nodes.push(next);
+                last.push(0);
+                stack.push((next, cfg.graph.neighbors(next).detach()));
+            }
+        } else {
+            let current = number[node.index()];
+            last[current] = nodes.len() - 1;
+            stack.pop();
         }
     }
 }
@@ -85,12 +219,278 @@ impl Default for LoopStructureGraph<'_> {
 /// Variable names and step numbering has been chosen to be identical
 /// to the nomenclature in Havlak's paper (which, in turn, is similar
 /// to the one used by Tarjan).
-pub fn find_loops<'havlak>(
-    cfg: &'havlak ControlFlowGraph,
-    _lsg: &'havlak mut LoopStructureGraph<'havlak>,
-) -> usize {
-    if cfg.entry_block.is_none() {
-        return 0;
-    }
-    unimplemented!();
+///
+/// Any loops already in `lsg` are discarded, so it can be reused.
+///
+/// Returns the number of loops found, not counting the root loop.
+pub fn find_loops(cfg: &ControlFlowGraph, lsg: &mut LoopStructureGraph) -> usize {
+    *lsg = LoopStructureGraph::new();
+    let entry = match cfg.entry_block {
+        Some(entry) => entry,
+        None => return 0,
+    };
+
+    // Step a:
+    //   - initialize all nodes as unvisited.
+    //   - depth-first traversal and numbering.
+    //   - unreached BB's are never numbered and are ignored from here on.
+    let mut number = vec![UNVISITED; cfg.graph.node_count()];
+    let mut nodes = Vec::with_capacity(cfg.graph.node_count());
+    let mut last = Vec::with_capacity(cfg.graph.node_count());
+    do_dfs(cfg, entry, &mut number, &mut nodes, &mut last);
+    let size = nodes.len();
+
+    let mut non_back_preds: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); size];
+    let mut back_preds: Vec<Vec<usize>> = vec![vec![]; size];
+    let mut types: Vec<BasicBlockType> = vec![BasicBlockType::Top; size];
+    let mut loop_of: Vec<Option<usize>> = vec![None; size];
+    let mut union_find = UnionFind::new(size);
+
+    // Step b:
+    //   - iterate over all nodes.
+    //
+    //   A backedge comes from a descendant in the DFS tree, and non-backedges
+    //   from non-descendants (following Tarjan).
+    //
+    //   - check incoming edges 'v' and add them to either
+    //     - the list of backedges (back_preds) or
+    //     - the list of non-backedges (non_back_preds)
+    for w in 0..size {
+        types[w] = BasicBlockType::NonHeader;
+        for pred in cfg
+            .graph
+            .neighbors_directed(nodes[w], EdgeDirection::Incoming)
+        {
+            let v = number[pred.index()];
+            if v == UNVISITED {
+                // Predecessors that are not reachable are dead code.
+                continue;
+            }
+            if is_ancestor(w, v, &last) {
+                back_preds[w].push(v);
+            } else {
+                non_back_preds[w].insert(v);
+            }
+        }
+    }
+    // Step c:
+    //   - iterate over all nodes from the bottom up.
+    //
+    //   The outer loop, unchanged from Tarjan. It does nothing except
+    //   for those nodes which are the destinations of backedges.
+    //   For a header node w, we chase backward from the sources of the
+    //   backedges adding nodes to the set P, representing the body of
+    //   the loop headed by w.
+    //
+    //   By running through the nodes in reverse of the DFST preorder,
+    //   we ensure that inner loop headers will be processed before the
+    //   headers for surrounding loops.
+    for w in (0..size).rev() {
+        // This is 'P' in Havlak's paper.
+        let mut node_pool: Vec<usize> = vec![];
+
+        // Step d:
+        for &v in &back_preds[w] {
+            if v != w {
+                node_pool.push(union_find.find_set(v));
+            } else {
+                types[w] = BasicBlockType::Single;
+            }
+        }
+        node_pool.sort_unstable();
+        node_pool.dedup();
+
+        // Copy node_pool to work_list.
+        let mut work_list = node_pool.clone();
+
+        if !node_pool.is_empty() {
+            types[w] = BasicBlockType::Reducible;
+        }
+
+        // Work the list.
+        while let Some(x) = work_list.pop() {
+            // Step e:
+            //
+            // Step e represents the main difference from Tarjan's method.
+            // Chasing upwards from the sources of a node w's backedges. If
+            // there is a node y' that is not a descendant of w, w is marked
+            // the header of an irreducible loop, there is another entry
+            // into this loop that avoids w.
+            let preds: Vec<usize> = non_back_preds[x].iter().cloned().collect();
+            for y in preds {
+                let ydash = union_find.find_set(y);
+
+                if !is_ancestor(w, ydash, &last) {
+                    types[w] = BasicBlockType::Irreducible;
+                    non_back_preds[w].insert(ydash);
+                } else if ydash != w && !node_pool.contains(&ydash) {
+                    work_list.push(ydash);
+                    node_pool.push(ydash);
+                }
+            }
+        }
+
+        // Collapse/Unionize nodes in a SCC to a single node.
+        // For every SCC found, create a loop descriptor and link it in.
+        if !node_pool.is_empty() || types[w] == BasicBlockType::Single {
+            let new_loop = lsg.create_new_loop();
+            {
+                let l = &mut lsg.loops[new_loop];
+                l.header = Some(nodes[w]);
+                l.basic_blocks.push(nodes[w]);
+                l.is_reducible = types[w] != BasicBlockType::Irreducible;
+            }
+            loop_of[w] = Some(new_loop);
+
+            for &node in &node_pool {
+                // Add nodes to loop descriptor.
+                union_find.union(node, w);
+
+                // Nested loops are not added, but linked together.
+                if let Some(inner) = loop_of[node] {
+                    lsg.loops[inner].parent = Some(new_loop);
+                } else {
+                    lsg.loops[new_loop].basic_blocks.push(nodes[node]);
+                }
+            }
+        }
+    }
+
+    // Every block that is not part of a loop belongs to the root.
+    for w in 0..size {
+        if loop_of[w].is_none() && union_find.find_set(w) == w {
+            lsg.loops[0].basic_blocks.push(nodes[w]);
+        }
+    }
+
+    lsg.calculate_nesting_level();
+    lsg.loops.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_loops, LoopStructureGraph};
+    use crate::address::Address;
+    use crate::cfg::ControlFlowGraph;
+    use crate::tests::*;
+
+    #[test]
+    fn no_loops() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(2, Opcode::Add),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let mut lsg = LoopStructureGraph::new();
+        assert_eq!(find_loops(&cfg, &mut lsg), 0);
+        assert_eq!(lsg.root().basic_blocks.len(), 3);
+        assert_eq!(lsg.root().nesting_level, 0);
+    }
+
+    #[test]
+    fn empty_graph() {
+        let insts: Vec<TestInstruction> = vec![];
+        let cfg = ControlFlowGraph::new(&insts);
+        let mut lsg = LoopStructureGraph::new();
+        assert_eq!(find_loops(&cfg, &mut lsg), 0);
+    }
+
+    #[test]
+    fn self_loop() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(0))),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let mut lsg = LoopStructureGraph::new();
+        assert_eq!(find_loops(&cfg, &mut lsg), 1);
+
+        let header = cfg.block_finder[&Address::new(0)];
+        let l = &lsg.loops[1];
+        assert_eq!(l.header, Some(header));
+        assert_eq!(l.basic_blocks, vec![header]);
+        assert!(l.is_reducible);
+        assert_eq!(l.parent, Some(0));
+        assert_eq!(l.depth_level, 1);
+        assert_eq!(lsg.root().children, vec![1]);
+        assert_eq!(lsg.root().nesting_level, 1);
+    }
+
+    #[test]
+    fn reused_graph() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(0))),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let mut lsg = LoopStructureGraph::new();
+        assert_eq!(find_loops(&cfg, &mut lsg), 1);
+        assert_eq!(find_loops(&cfg, &mut lsg), 1);
+        assert_eq!(lsg.loops.len(), 2);
+        assert_eq!(lsg.root().children, vec![1]);
+        assert_eq!(lsg.root().basic_blocks.len(), 1);
+
+        let empty: Vec<TestInstruction> = vec![];
+        assert_eq!(find_loops(&ControlFlowGraph::new(&empty), &mut lsg), 0);
+        assert_eq!(lsg.loops.len(), 1);
+    }
+
+    #[test]
+    fn nested_loops() {
+        let insts = [
+            TestInstruction::new(0, Opcode::Add),
+            TestInstruction::new(1, Opcode::Add),
+            TestInstruction::new(2, Opcode::Add),
+            TestInstruction::new(3, Opcode::CJmp(Address::new(2))),
+            TestInstruction::new(4, Opcode::CJmp(Address::new(1))),
+            TestInstruction::new(5, Opcode::Ret),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let mut lsg = LoopStructureGraph::new();
+        assert_eq!(find_loops(&cfg, &mut lsg), 2);
+
+        let outer_header = cfg.block_finder[&Address::new(1)];
+        let inner_header = cfg.block_finder[&Address::new(2)];
+        let outer = lsg
+            .loops
+            .iter()
+            .find(|l| l.header == Some(outer_header))
+            .unwrap();
+        let inner = lsg
+            .loops
+            .iter()
+            .find(|l| l.header == Some(inner_header))
+            .unwrap();
+
+        assert_eq!(inner.parent, Some(outer.counter));
+        assert_eq!(outer.children, vec![inner.counter]);
+        assert_eq!(outer.depth_level, 1);
+        assert_eq!(inner.depth_level, 2);
+        assert_eq!(outer.nesting_level, 1);
+        assert_eq!(inner.nesting_level, 0);
+        assert_eq!(lsg.root().nesting_level, 2);
+        assert!(outer.is_reducible && inner.is_reducible);
+    }
+
+    #[test]
+    fn irreducible_loop() {
+        // Two blocks that jump to each other, both of which can be
+        // entered from the entry block.
+        let insts = [
+            TestInstruction::new(0, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(1, Opcode::Add),
+            TestInstruction::new(2, Opcode::CJmp(Address::new(5))),
+            TestInstruction::new(3, Opcode::Add),
+            TestInstruction::new(4, Opcode::Jmp(Address::new(1))),
+            TestInstruction::new(5, Opcode::Ret),
+        ];
+        let cfg = ControlFlowGraph::new(&insts);
+        let mut lsg = LoopStructureGraph::new();
+        assert_eq!(find_loops(&cfg, &mut lsg), 1);
+        assert!(!lsg.loops[1].is_reducible);
+    }
 }
@@ -0,0 +1,547 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::dominators::DominatorTree;
+use crate::function::Function;
+use crate::instruction::Instruction;
+use crate::operand::{MemoryOperand, Operand, Register};
+use petgraph::graph::NodeIndex;
+use petgraph::EdgeDirection;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Whether two memory accesses can refer to the same memory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AliasResult {
+    /// The accesses never overlap.
+    NoAlias,
+    /// The accesses might overlap.
+    MayAlias,
+    /// The accesses are always to exactly the same memory.
+    MustAlias,
+}
+
+/// Answers whether two memory accesses alias.
+///
+/// This is used by [`MemorySsa`] to skip over stores that can not
+/// affect a load when finding the [clobbering access] for the load.
+///
+/// [clobbering access]: MemorySsa::clobbering_access
+pub trait AliasOracle {
+    /// Determine whether the memory described by `a` and `b` can overlap.
+    fn alias(&self, a: &MemoryOperand, b: &MemoryOperand) -> AliasResult;
+}
+
+/// An [`AliasOracle`] that assumes that any two accesses may alias.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConservativeAliasOracle;
+
+impl AliasOracle for ConservativeAliasOracle {
+    fn alias(&self, _a: &MemoryOperand, _b: &MemoryOperand) -> AliasResult {
+        AliasResult::MayAlias
+    }
+}
+
+/// An [`AliasOracle`] for accesses relative to registers that hold the
+/// same value throughout a function, such as a frame pointer.
+///
+/// Two accesses with the same invariant base register and no index
+/// register are compared by their displacement and size. Everything
+/// else may alias.
+///
+/// For example, in BPF programs `r10` is a read-only frame pointer, so
+/// stack slots can be told apart with `BaseOffsetAliasOracle::new(&[Register::Machine(10)])`.
+#[derive(Clone, Debug, Default)]
+pub struct BaseOffsetAliasOracle {
+    invariant_registers: BTreeSet<Register>,
+}
+
+impl BaseOffsetAliasOracle {
+    /// Construct an oracle where `registers` are known to be invariant.
+    pub fn new(registers: &[Register]) -> Self {
+        BaseOffsetAliasOracle {
+            invariant_registers: registers.iter().cloned().collect(),
+        }
+    }
+}
+
+impl AliasOracle for BaseOffsetAliasOracle {
+    fn alias(&self, a: &MemoryOperand, b: &MemoryOperand) -> AliasResult {
+        let base = match (a.base, b.base) {
+            (Some(a), Some(b)) if a == b => a,
+            _ => return AliasResult::MayAlias,
+        };
+        if !self.invariant_registers.contains(&base) || a.index.is_some() || b.index.is_some() {
+            return AliasResult::MayAlias;
+        }
+        let (a_size, b_size) = match (a.size, b.size) {
+            (Some(a), Some(b)) => (i64::from(a), i64::from(b)),
+            _ => return AliasResult::MayAlias,
+        };
+        if a.displacement == b.displacement && a_size == b_size {
+            AliasResult::MustAlias
+        } else if a.displacement + a_size <= b.displacement
+            || b.displacement + b_size <= a.displacement
+        {
+            AliasResult::NoAlias
+        } else {
+            AliasResult::MayAlias
+        }
+    }
+}
+
+/// Identifies a [`MemoryAccess`] within a [`MemorySsa`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MemoryAccessId(usize);
+
+impl MemoryAccessId {
+    /// The index of this access within [`MemorySsa::accesses`].
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A version of memory, or a use of one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MemoryAccess {
+    /// The state of memory on entry to the function.
+    LiveOnEntry,
+    /// The instruction at `index` writes to memory, creating a new
+    /// version. Calls are always treated as writing to memory.
+    Def {
+        /// The index of the instruction within the [`Function`].
+        index: usize,
+        /// The version of memory before this instruction.
+        defining: MemoryAccessId,
+    },
+    /// The instruction at `index` reads from memory without writing to it.
+    Use {
+        /// The index of the instruction within the [`Function`].
+        index: usize,
+        /// The version of memory that is read.
+        defining: MemoryAccessId,
+    },
+    /// The versions of memory from each predecessor are merged at the
+    /// start of a block.
+    Phi {
+        /// The block that the phi is in.
+        block: NodeIndex,
+        /// The incoming version of memory from each predecessor block. In
+        /// the entry block, [`MemoryAccess::LiveOnEntry`] is listed with the
+        /// entry block itself as its predecessor.
+        operands: Vec<(NodeIndex, MemoryAccessId)>,
+    },
+}
+
+/// [Memory SSA] for a [`Function`].
+///
+/// Memory is treated as a single variable. Each instruction that
+/// [writes to memory] creates a new version of it with a
+/// [`MemoryAccess::Def`] and each instruction that only [reads from
+/// memory] gets a [`MemoryAccess::Use`] of the current version. Where
+/// versions meet, a [`MemoryAccess::Phi`] is placed using the dominance
+/// frontiers from the [`DominatorTree`].
+///
+/// The immediate version used by a load is often a store to unrelated
+/// memory. An [`AliasOracle`] is used to find the [clobbering access]
+/// for each use, the nearest dominating access that might actually
+/// write the memory that is read.
+///
+/// [clobbering access]: MemorySsa::clobbering_access
+/// [Memory SSA]: https://llvm.org/docs/MemorySSA.html
+/// [reads from memory]: Instruction::reads_memory
+/// [writes to memory]: Instruction::writes_memory
+#[derive(Debug)]
+pub struct MemorySsa {
+    accesses: Vec<MemoryAccess>,
+    locations: BTreeMap<MemoryAccessId, MemoryOperand>,
+    instruction_accesses: BTreeMap<usize, MemoryAccessId>,
+    phis: BTreeMap<NodeIndex, MemoryAccessId>,
+    clobbers: BTreeMap<MemoryAccessId, MemoryAccessId>,
+}
+
+impl MemorySsa {
+    /// Construct the memory SSA form of a [`Function`], using `oracle`
+    /// to find the clobbering access for each use.
+    pub fn new<I: Instruction, A: AliasOracle>(function: &Function<I>, oracle: &A) -> Self {
+        let cfg = &function.control_flow_graph;
+        let dominators = DominatorTree::dominators(cfg);
+        let mut mssa = MemorySsa {
+            accesses: vec![MemoryAccess::LiveOnEntry],
+            locations: BTreeMap::new(),
+            instruction_accesses: BTreeMap::new(),
+            phis: BTreeMap::new(),
+            clobbers: BTreeMap::new(),
+        };
+        let is_def = |inst: &I| inst.writes_memory() || inst.is_call();
+
+        // Place phis on the iterated dominance frontier of the blocks
+        // that write to memory.
+        let def_blocks = cfg
+            .graph
+            .node_indices()
+            .filter(|&node| {
+                dominators.contains(node)
+                    && cfg.graph[node]
+                        .instruction_indices
+                        .iter()
+                        .any(|&idx| is_def(&function.instructions[idx]))
+            })
+            .collect::<BTreeSet<_>>();
+        let mut worklist = def_blocks.iter().cloned().collect::<Vec<_>>();
+        while let Some(node) = worklist.pop() {
+            let frontier = match dominators.dominance_frontier(node) {
+                Some(frontier) => frontier,
+                None => continue,
+            };
+            for &y in frontier {
+                if !mssa.phis.contains_key(&y) {
+                    let phi = mssa.add_access(MemoryAccess::Phi {
+                        block: y,
+                        operands: vec![],
+                    });
+                    mssa.phis.insert(y, phi);
+                    if !def_blocks.contains(&y) {
+                        worklist.push(y);
+                    }
+                }
+            }
+        }
+
+        // The entry block can also be a loop header, so its phi merges
+        // in the state of memory on entry.
+        let live_on_entry = mssa.live_on_entry();
+        for &root in dominators.roots() {
+            if let Some(&phi) = mssa.phis.get(&root) {
+                if let MemoryAccess::Phi {
+                    ref mut operands, ..
+                } = mssa.accesses[phi.0]
+                {
+                    operands.push((root, live_on_entry));
+                }
+            }
+        }
+
+        // Rename by walking the dominator tree, keeping a stack of the
+        // current version of memory.
+        let mut versions = vec![mssa.live_on_entry()];
+        let mut walk = dominators
+            .roots()
+            .iter()
+            .map(|&root| (root, false))
+            .collect::<Vec<_>>();
+        let mut pushed = BTreeMap::new();
+        while let Some((node, done)) = walk.pop() {
+            if done {
+                let count = pushed.remove(&node).unwrap_or(0);
+                versions.truncate(versions.len() - count);
+                continue;
+            }
+            let mut count = 0;
+            if let Some(&phi) = mssa.phis.get(&node) {
+                versions.push(phi);
+                count += 1;
+            }
+            for &idx in &cfg.graph[node].instruction_indices {
+                let inst = &function.instructions[idx];
+                let current = *versions.last().unwrap();
+                let access = if is_def(inst) {
+                    let def = mssa.add_access(MemoryAccess::Def {
+                        index: idx,
+                        defining: current,
+                    });
+                    versions.push(def);
+                    count += 1;
+                    def
+                } else if inst.reads_memory() {
+                    mssa.add_access(MemoryAccess::Use {
+                        index: idx,
+                        defining: current,
+                    })
+                } else {
+                    continue;
+                };
+                mssa.instruction_accesses.insert(idx, access);
+                // The location of a call is unknown.
+                if !inst.is_call() {
+                    let location = inst.operands().into_iter().find_map(|op| match op {
+                        Operand::Memory(mem) => Some(mem),
+                        _ => None,
+                    });
+                    if let Some(location) = location {
+                        mssa.locations.insert(access, location);
+                    }
+                }
+            }
+            let current = *versions.last().unwrap();
+            let successors = cfg
+                .graph
+                .neighbors_directed(node, EdgeDirection::Outgoing)
+                .collect::<BTreeSet<_>>();
+            for succ in successors {
+                if let Some(&phi) = mssa.phis.get(&succ) {
+                    if let MemoryAccess::Phi {
+                        ref mut operands, ..
+                    } = mssa.accesses[phi.0]
+                    {
+                        operands.push((node, current));
+                    }
+                }
+            }
+            pushed.insert(node, count);
+            walk.push((node, true));
+            for &child in dominators.children(node).iter().rev() {
+                walk.push((child, false));
+            }
+        }
+
+        let uses = mssa
+            .instruction_accesses
+            .values()
+            .cloned()
+            .filter(|&access| matches!(mssa.accesses[access.0], MemoryAccess::Use { .. }))
+            .collect::<Vec<_>>();
+        for access in uses {
+            let clobber = mssa.find_clobber(access, oracle);
+            mssa.clobbers.insert(access, clobber);
+        }
+        mssa
+    }
+
+    fn add_access(&mut self, access: MemoryAccess) -> MemoryAccessId {
+        let id = MemoryAccessId(self.accesses.len());
+        self.accesses.push(access);
+        id
+    }
+
+    /// Walk up the chain of definitions from a use, skipping those
+    /// that the oracle says can not write to the memory being read.
+    fn find_clobber<A: AliasOracle>(&self, access: MemoryAccessId, oracle: &A) -> MemoryAccessId {
+        let mut current = match self.accesses[access.0] {
+            MemoryAccess::Use { defining, .. } => defining,
+            _ => return access,
+        };
+        let location = match self.locations.get(&access) {
+            Some(location) => location,
+            None => return current,
+        };
+        loop {
+            match self.accesses[current.0] {
+                MemoryAccess::Def { defining, .. } => match self.locations.get(&current) {
+                    Some(def_location)
+                        if oracle.alias(location, def_location) == AliasResult::NoAlias =>
+                    {
+                        current = defining;
+                    }
+                    _ => return current,
+                },
+                _ => return current,
+            }
+        }
+    }
+
+    /// The access that represents the state of memory on entry to
+    /// the function.
+    pub fn live_on_entry(&self) -> MemoryAccessId {
+        MemoryAccessId(0)
+    }
+
+    /// All of the accesses, indexed by [`MemoryAccessId::index`].
+    pub fn accesses(&self) -> &[MemoryAccess] {
+        &self.accesses
+    }
+
+    /// Get an access.
+    pub fn access(&self, access: MemoryAccessId) -> Option<&MemoryAccess> {
+        self.accesses.get(access.0)
+    }
+
+    /// The access for the instruction at `index`, if it accesses memory.
+    pub fn instruction_access(&self, index: usize) -> Option<MemoryAccessId> {
+        self.instruction_accesses.get(&index).cloned()
+    }
+
+    /// The phi at the start of a block, if there is one.
+    pub fn phi(&self, block: NodeIndex) -> Option<MemoryAccessId> {
+        self.phis.get(&block).cloned()
+    }
+
+    /// The memory accessed by a def or use, if it is known.
+    pub fn location(&self, access: MemoryAccessId) -> Option<&MemoryOperand> {
+        self.locations.get(&access)
+    }
+
+    /// The nearest access that may write the memory read by a use.
+    ///
+    /// This is `None` if `access` is not a [`MemoryAccess::Use`].
+    pub fn clobbering_access(&self, access: MemoryAccessId) -> Option<MemoryAccessId> {
+        self.clobbers.get(&access).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AliasOracle, AliasResult, BaseOffsetAliasOracle, ConservativeAliasOracle, MemoryAccess,
+        MemorySsa,
+    };
+    use crate::address::Address;
+    use crate::function::Function;
+    use crate::operand::{MemoryOperand, Register};
+    use crate::symbol::Symbol;
+    use crate::tests::*;
+
+    fn slot(base: u32, displacement: i64, size: u32) -> MemoryOperand {
+        MemoryOperand {
+            base: Some(Register::Machine(base)),
+            index: None,
+            scale: 1,
+            displacement,
+            size: Some(size),
+        }
+    }
+
+    #[test]
+    fn base_offset_oracle() {
+        let oracle = BaseOffsetAliasOracle::new(&[Register::Machine(10)]);
+        assert_eq!(
+            oracle.alias(&slot(10, -8, 8), &slot(10, -16, 8)),
+            AliasResult::NoAlias
+        );
+        assert_eq!(
+            oracle.alias(&slot(10, -8, 8), &slot(10, -8, 8)),
+            AliasResult::MustAlias
+        );
+        assert_eq!(
+            oracle.alias(&slot(10, -8, 8), &slot(10, -4, 4)),
+            AliasResult::MayAlias
+        );
+        assert_eq!(
+            oracle.alias(&slot(1, -8, 8), &slot(1, -16, 8)),
+            AliasResult::MayAlias
+        );
+        assert_eq!(
+            oracle.alias(&slot(10, -8, 8), &slot(1, -8, 8)),
+            AliasResult::MayAlias
+        );
+    }
+
+    #[test]
+    fn straight_line() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Store(10, -8, 1)),
+            TestInstruction::new(1, Opcode::Store(10, -16, 2)),
+            TestInstruction::new(2, Opcode::Load(3, 10, -8)),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+
+        let mssa = MemorySsa::new(&f, &ConservativeAliasOracle);
+        let first = mssa.instruction_access(0).unwrap();
+        let second = mssa.instruction_access(1).unwrap();
+        let load = mssa.instruction_access(2).unwrap();
+        assert_eq!(
+            mssa.access(first),
+            Some(&MemoryAccess::Def {
+                index: 0,
+                defining: mssa.live_on_entry()
+            })
+        );
+        assert_eq!(
+            mssa.access(load),
+            Some(&MemoryAccess::Use {
+                index: 2,
+                defining: second
+            })
+        );
+        assert_eq!(mssa.clobbering_access(load), Some(second));
+        assert_eq!(mssa.location(load), Some(&slot(10, -8, 8)));
+        assert_eq!(mssa.instruction_access(3), None);
+
+        let oracle = BaseOffsetAliasOracle::new(&[Register::Machine(10)]);
+        let mssa = MemorySsa::new(&f, &oracle);
+        let first = mssa.instruction_access(0).unwrap();
+        let load = mssa.instruction_access(2).unwrap();
+        assert_eq!(mssa.clobbering_access(load), Some(first));
+    }
+
+    #[test]
+    fn calls_clobber() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Store(10, -8, 1)),
+            TestInstruction::new(1, Opcode::Call(Address::new(100))),
+            TestInstruction::new(2, Opcode::Load(3, 10, -8)),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let oracle = BaseOffsetAliasOracle::new(&[Register::Machine(10)]);
+        let mssa = MemorySsa::new(&f, &oracle);
+        let call = mssa.instruction_access(1).unwrap();
+        let load = mssa.instruction_access(2).unwrap();
+
+        assert_eq!(mssa.location(call), None);
+        assert_eq!(mssa.clobbering_access(load), Some(call));
+    }
+
+    #[test]
+    fn phi() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Store(10, -8, 1)),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(2, Opcode::Store(10, -16, 2)),
+            TestInstruction::new(3, Opcode::Load(3, 10, -8)),
+            TestInstruction::new(4, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let oracle = BaseOffsetAliasOracle::new(&[Register::Machine(10)]);
+        let mssa = MemorySsa::new(&f, &oracle);
+        let join = f.control_flow_graph.block_finder[&Address::new(3)];
+        let phi = mssa.phi(join).unwrap();
+        let first = mssa.instruction_access(0).unwrap();
+        let second = mssa.instruction_access(2).unwrap();
+        let load = mssa.instruction_access(3).unwrap();
+
+        match mssa.access(phi) {
+            Some(MemoryAccess::Phi { block, operands }) => {
+                assert_eq!(*block, join);
+                let mut versions = operands.iter().map(|&(_, v)| v).collect::<Vec<_>>();
+                versions.sort();
+                assert_eq!(versions, vec![first, second]);
+            }
+            other => panic!("expected a phi, got {:?}", other),
+        }
+        // Walking stops at phis.
+        assert_eq!(mssa.clobbering_access(load), Some(phi));
+        assert_eq!(mssa.phi(f.control_flow_graph.entry_block.unwrap()), None);
+    }
+
+    #[test]
+    fn entry_loop_phi() {
+        // The entry block is a loop header, so the load may see either
+        // the memory on entry or the store from the previous iteration.
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Load(3, 10, -8)),
+            TestInstruction::new(1, Opcode::Store(10, -8, 1)),
+            TestInstruction::new(2, Opcode::CJmp(Address::new(0))),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(0), None), insts);
+        let mssa = MemorySsa::new(&f, &ConservativeAliasOracle);
+        let entry = f.control_flow_graph.entry_block.unwrap();
+        let phi = mssa.phi(entry).unwrap();
+        let store = mssa.instruction_access(1).unwrap();
+        let load = mssa.instruction_access(0).unwrap();
+
+        match mssa.access(phi) {
+            Some(MemoryAccess::Phi { operands, .. }) => {
+                let mut versions = operands.iter().map(|&(_, v)| v).collect::<Vec<_>>();
+                versions.sort();
+                assert_eq!(versions, vec![mssa.live_on_entry(), store]);
+            }
+            other => panic!("expected a phi, got {:?}", other),
+        }
+        assert_eq!(mssa.clobbering_access(load), Some(phi));
+    }
+}
@@ -4,10 +4,21 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use super::{Function, Instruction};
+#[cfg(feature = "webassembly")]
+use super::{Address, WasmDeclaration};
+use super::{Function, Instruction, Symbol};
+#[cfg(feature = "webassembly")]
+use std::collections::HashMap;
 
 /// A shared library or other component of a target.
 pub struct Module<I: Instruction> {
     /// Functions defined within this module
     pub functions: Vec<Function<I>>,
+    /// Functions imported from elsewhere. These have no body within
+    /// this module, but may still be called by its functions.
+    pub imported_functions: Vec<Symbol>,
+    /// The signature and local variables of each WebAssembly function,
+    /// by the address of the function.
+    #[cfg(feature = "webassembly")]
+    pub declarations: HashMap<Address, WasmDeclaration>,
 }
@@ -0,0 +1,83 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::address::Address;
+use std::fmt;
+
+/// A register, or something that a backend treats like one.
+///
+/// The numbering of registers is specific to each backend. For
+/// example, a backend for a CPU might use the register numbers
+/// from its disassembler, while a backend for a stack based VM
+/// might use local and global variables.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Register {
+    /// A machine or virtual machine register.
+    Machine(u32),
+    /// A local variable, such as a WebAssembly local.
+    Local(u32),
+    /// A global variable, such as a WebAssembly global.
+    Global(u32),
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Register::Machine(n) => write!(f, "r{}", n),
+            Register::Local(n) => write!(f, "local{}", n),
+            Register::Global(n) => write!(f, "global{}", n),
+        }
+    }
+}
+
+/// A reference to memory, computed as
+/// `base + index * scale + displacement`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MemoryOperand {
+    /// The base register, if any.
+    pub base: Option<Register>,
+    /// The index register, if any.
+    pub index: Option<Register>,
+    /// The amount that the index register is multiplied by.
+    pub scale: u8,
+    /// A constant offset.
+    pub displacement: i64,
+    /// The number of bytes accessed, if known.
+    pub size: Option<u32>,
+}
+
+/// An operand of an [`Instruction`].
+///
+/// [`Instruction`]: crate::Instruction
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Operand {
+    /// A register.
+    Register(Register),
+    /// An immediate value.
+    Immediate(i64),
+    /// A reference to memory.
+    Memory(MemoryOperand),
+    /// An address, such as the target of a jump or call.
+    Address(Address),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Register;
+
+    #[test]
+    fn register_fmt() {
+        assert_eq!("r3", format!("{}", Register::Machine(3)));
+        assert_eq!("local0", format!("{}", Register::Local(0)));
+        assert_eq!("global12", format!("{}", Register::Global(12)));
+    }
+
+    #[test]
+    fn register_ordering() {
+        assert!(Register::Machine(10) < Register::Local(0));
+        assert!(Register::Local(1) < Register::Local(2));
+    }
+}
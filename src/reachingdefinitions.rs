@@ -0,0 +1,238 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::dataflow::{DataFlowAnalysis, DataFlowResults, Direction};
+use crate::function::Function;
+use crate::instruction::Instruction;
+use crate::operand::Register;
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A write to a register by the instruction at `index`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Definition {
+    /// The index of the instruction within the [`Function`].
+    pub index: usize,
+    /// The register that is written.
+    pub register: Register,
+}
+
+/// The [reaching definitions] within a [`Function`], along with
+/// use-def and def-use chains between its instructions.
+///
+/// A definition reaches a point if there is a path from the definition
+/// to that point along which the register is not written again. Each
+/// register read by an instruction is linked to the definitions that
+/// reach it (its use-def chain) and each definition is linked to the
+/// instructions that read it (its def-use chain).
+///
+/// Values that flow in from outside of the function, such as arguments,
+/// have no definition within it, so a read of such a value has no
+/// definitions on the paths from the entry block.
+///
+/// [reaching definitions]: https://en.wikipedia.org/wiki/Reaching_definition
+#[derive(Debug)]
+pub struct ReachingDefinitions {
+    blocks: DataFlowResults<BTreeSet<Definition>>,
+    reaching: BTreeMap<usize, BTreeSet<Definition>>,
+    use_def: BTreeMap<(usize, Register), BTreeSet<usize>>,
+    def_use: BTreeMap<Definition, BTreeSet<usize>>,
+}
+
+struct ReachingDefinitionsAnalysis;
+
+impl<I: Instruction> DataFlowAnalysis<I> for ReachingDefinitionsAnalysis {
+    type Domain = BTreeSet<Definition>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn bottom(&self) -> Self::Domain {
+        BTreeSet::new()
+    }
+
+    fn transfer_instruction(&self, index: usize, instruction: &I, state: &mut Self::Domain) {
+        for register in instruction.registers_written() {
+            state.retain(|def| def.register != register);
+            state.insert(Definition { index, register });
+        }
+    }
+}
+
+impl ReachingDefinitions {
+    /// Compute the reaching definitions for a [`Function`].
+    pub fn new<I: Instruction>(function: &Function<I>) -> Self {
+        let cfg = &function.control_flow_graph;
+        let blocks = ReachingDefinitionsAnalysis.solve(cfg, &function.instructions);
+        let mut reaching = BTreeMap::new();
+        let mut use_def = BTreeMap::new();
+        let mut def_use = BTreeMap::new();
+        for node in cfg.graph.node_indices() {
+            for state in blocks.instruction_states(
+                &ReachingDefinitionsAnalysis,
+                cfg,
+                node,
+                &function.instructions,
+            ) {
+                let inst = &function.instructions[state.index];
+                for register in inst.registers_read() {
+                    let defs = state
+                        .before
+                        .iter()
+                        .filter(|def| def.register == register)
+                        .collect::<Vec<_>>();
+                    for &def in &defs {
+                        def_use
+                            .entry(*def)
+                            .or_insert_with(BTreeSet::new)
+                            .insert(state.index);
+                    }
+                    use_def.insert(
+                        (state.index, register),
+                        defs.iter().map(|def| def.index).collect(),
+                    );
+                }
+                for register in inst.registers_written() {
+                    def_use
+                        .entry(Definition {
+                            index: state.index,
+                            register,
+                        })
+                        .or_insert_with(BTreeSet::new);
+                }
+                reaching.insert(state.index, state.before);
+            }
+        }
+        ReachingDefinitions {
+            blocks,
+            reaching,
+            use_def,
+            def_use,
+        }
+    }
+
+    /// The definitions that reach the start of a block.
+    pub fn block_entry(&self, block: NodeIndex) -> Option<&BTreeSet<Definition>> {
+        self.blocks.entry(block)
+    }
+
+    /// The definitions that reach the end of a block.
+    pub fn block_exit(&self, block: NodeIndex) -> Option<&BTreeSet<Definition>> {
+        self.blocks.exit(block)
+    }
+
+    /// The definitions that reach the instruction at `index`, before
+    /// it executes.
+    pub fn reaching(&self, index: usize) -> Option<&BTreeSet<Definition>> {
+        self.reaching.get(&index)
+    }
+
+    /// The use-def chain for a register read by the instruction at
+    /// `index`: the indices of the instructions whose definitions of
+    /// the register reach it.
+    ///
+    /// This is `None` if the instruction does not read the register.
+    pub fn definitions(&self, index: usize, register: Register) -> Option<&BTreeSet<usize>> {
+        self.use_def.get(&(index, register))
+    }
+
+    /// The def-use chain for a register written by the instruction at
+    /// `index`: the indices of the instructions that read the value.
+    ///
+    /// This is `None` if the instruction does not write the register.
+    pub fn uses(&self, index: usize, register: Register) -> Option<&BTreeSet<usize>> {
+        self.def_use.get(&Definition { index, register })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Definition, ReachingDefinitions};
+    use crate::address::Address;
+    use crate::function::Function;
+    use crate::operand::Register;
+    use crate::symbol::Symbol;
+    use crate::tests::*;
+    use std::collections::BTreeSet;
+
+    fn set(items: &[usize]) -> BTreeSet<usize> {
+        items.iter().cloned().collect()
+    }
+
+    #[test]
+    fn chains() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(2, Opcode::Set(1)),
+            TestInstruction::new(3, Opcode::Mov(2, 1)),
+            TestInstruction::new(4, Opcode::Use(2)),
+            TestInstruction::new(5, Opcode::Use(3)),
+            TestInstruction::new(6, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let defs = ReachingDefinitions::new(&f);
+        let r1 = Register::Machine(1);
+        let r2 = Register::Machine(2);
+        let r3 = Register::Machine(3);
+
+        assert_eq!(defs.definitions(3, r1), Some(&set(&[0, 2])));
+        assert_eq!(defs.definitions(4, r2), Some(&set(&[3])));
+        assert_eq!(defs.definitions(5, r3), Some(&set(&[])));
+        assert_eq!(defs.definitions(4, r1), None);
+
+        assert_eq!(defs.uses(0, r1), Some(&set(&[3])));
+        assert_eq!(defs.uses(2, r1), Some(&set(&[3])));
+        assert_eq!(defs.uses(3, r2), Some(&set(&[4])));
+        assert_eq!(defs.uses(3, r1), None);
+
+        let join = f.control_flow_graph.block_finder[&Address::new(3)];
+        let entry = defs.block_entry(join).unwrap();
+        assert!(entry.contains(&Definition {
+            index: 0,
+            register: r1
+        }));
+        assert!(entry.contains(&Definition {
+            index: 2,
+            register: r1
+        }));
+    }
+
+    #[test]
+    fn killed() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::Set(1)),
+            TestInstruction::new(2, Opcode::Use(1)),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let defs = ReachingDefinitions::new(&f);
+        let r1 = Register::Machine(1);
+
+        assert_eq!(defs.definitions(2, r1), Some(&set(&[1])));
+        assert_eq!(defs.uses(0, r1), Some(&set(&[])));
+        assert_eq!(defs.reaching(2).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn loops() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::Mov(1, 1)),
+            TestInstruction::new(2, Opcode::CJmp(Address::new(1))),
+            TestInstruction::new(3, Opcode::Use(1)),
+            TestInstruction::new(4, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let defs = ReachingDefinitions::new(&f);
+        let r1 = Register::Machine(1);
+
+        assert_eq!(defs.definitions(1, r1), Some(&set(&[0, 1])));
+        assert_eq!(defs.uses(1, r1), Some(&set(&[1, 3])));
+    }
+}
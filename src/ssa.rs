@@ -0,0 +1,459 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::dominators::DominatorTree;
+use crate::function::Function;
+use crate::instruction::Instruction;
+use crate::liveness::Liveness;
+use crate::operand::Register;
+use petgraph::graph::NodeIndex;
+use petgraph::EdgeDirection;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Identifies a value within an [`SsaForm`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueId(usize);
+
+impl ValueId {
+    /// The index of this value within [`SsaForm::values`].
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Where an SSA value is defined.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ValueDefinition {
+    /// The value that a register holds on entry to the function.
+    Entry(Register),
+    /// A value written to a register by the instruction at `index`.
+    Instruction {
+        /// The index of the instruction within the [`Function`].
+        index: usize,
+        /// The register that is written.
+        register: Register,
+    },
+    /// A phi function at the start of a block, merging the values
+    /// of a register from each predecessor.
+    Phi {
+        /// The block that the phi function is in.
+        block: NodeIndex,
+        /// The register that the phi function merges.
+        register: Register,
+        /// The incoming value from each predecessor block. In the entry
+        /// block, the value on entry to the function is listed with the
+        /// entry block itself as its predecessor.
+        operands: Vec<(NodeIndex, ValueId)>,
+    },
+}
+
+impl ValueDefinition {
+    /// The register that holds this value.
+    pub fn register(&self) -> Register {
+        match *self {
+            ValueDefinition::Entry(register)
+            | ValueDefinition::Instruction { register, .. }
+            | ValueDefinition::Phi { register, .. } => register,
+        }
+    }
+}
+
+/// Something that uses an SSA value.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ValueUser {
+    /// The instruction at this index reads the value.
+    Instruction(usize),
+    /// The value is an operand of this phi function.
+    Phi(ValueId),
+}
+
+/// The [static single assignment form] of a [`Function`].
+///
+/// Every write to a [`Register`] creates a new value and every read
+/// of a register is linked to the single value that it sees. Where
+/// different values of a register meet, a phi function is placed at
+/// the start of the block, using the dominance frontiers from the
+/// [`DominatorTree`]. Phi functions are only placed where the register
+/// is live, so there are no dead phi functions.
+///
+/// The instructions themselves are left unchanged. Instead, values are
+/// mapped back to the original instruction indices, so that the value
+/// read or written by an instruction can be found with [`used_value`]
+/// and [`defined_value`].
+///
+/// Blocks that can not be reached from the entry block are not renamed,
+/// so their instructions have no values.
+///
+/// [`defined_value`]: SsaForm::defined_value
+/// [static single assignment form]: https://en.wikipedia.org/wiki/Static_single_assignment_form
+/// [`used_value`]: SsaForm::used_value
+#[derive(Debug)]
+pub struct SsaForm {
+    values: Vec<ValueDefinition>,
+    users: Vec<BTreeSet<ValueUser>>,
+    phis: BTreeMap<NodeIndex, Vec<ValueId>>,
+    uses: BTreeMap<(usize, Register), ValueId>,
+    defs: BTreeMap<(usize, Register), ValueId>,
+    entry_values: BTreeMap<Register, ValueId>,
+}
+
+impl SsaForm {
+    /// Construct the SSA form of a [`Function`].
+    pub fn new<I: Instruction>(function: &Function<I>) -> Self {
+        let cfg = &function.control_flow_graph;
+        let dominators = DominatorTree::dominators(cfg);
+        let liveness = Liveness::new(function);
+        let mut ssa = SsaForm {
+            values: vec![],
+            users: vec![],
+            phis: BTreeMap::new(),
+            uses: BTreeMap::new(),
+            defs: BTreeMap::new(),
+            entry_values: BTreeMap::new(),
+        };
+
+        // Find the blocks that write each register.
+        let mut def_blocks: BTreeMap<Register, BTreeSet<NodeIndex>> = BTreeMap::new();
+        for node in cfg.graph.node_indices() {
+            if !dominators.contains(node) {
+                continue;
+            }
+            for &idx in &cfg.graph[node].instruction_indices {
+                for register in function.instructions[idx].registers_written() {
+                    def_blocks.entry(register).or_default().insert(node);
+                }
+            }
+        }
+
+        // Place phi functions on the iterated dominance frontier.
+        for (&register, blocks) in &def_blocks {
+            let mut has_phi = BTreeSet::new();
+            let mut worklist = blocks.iter().cloned().collect::<Vec<_>>();
+            while let Some(node) = worklist.pop() {
+                let frontier = match dominators.dominance_frontier(node) {
+                    Some(frontier) => frontier,
+                    None => continue,
+                };
+                for &y in frontier {
+                    let live = liveness
+                        .block_live_in(y)
+                        .is_some_and(|live| live.contains(&register));
+                    if live && has_phi.insert(y) {
+                        let phi = ssa.add_value(ValueDefinition::Phi {
+                            block: y,
+                            register,
+                            operands: vec![],
+                        });
+                        ssa.phis.entry(y).or_default().push(phi);
+                        if !blocks.contains(&y) {
+                            worklist.push(y);
+                        }
+                    }
+                }
+            }
+        }
+
+        // The entry block can also be a loop header, so its phi functions
+        // merge in the values that the registers hold on entry.
+        for &root in dominators.roots() {
+            let phis = ssa.phis.get(&root).cloned().unwrap_or_default();
+            for phi in phis {
+                let register = ssa.values[phi.0].register();
+                let value = ssa.current_value(&BTreeMap::new(), register);
+                if let ValueDefinition::Phi {
+                    ref mut operands, ..
+                } = ssa.values[phi.0]
+                {
+                    operands.push((root, value));
+                }
+                ssa.users[value.0].insert(ValueUser::Phi(phi));
+            }
+        }
+
+        // Rename by walking the dominator tree, keeping a stack of the
+        // current value of each register.
+        let mut stacks: BTreeMap<Register, Vec<ValueId>> = BTreeMap::new();
+        let mut walk = dominators
+            .roots()
+            .iter()
+            .map(|&root| (root, false))
+            .collect::<Vec<_>>();
+        let mut pushed: BTreeMap<NodeIndex, Vec<Register>> = BTreeMap::new();
+        while let Some((node, done)) = walk.pop() {
+            if done {
+                for register in pushed.remove(&node).unwrap_or_default() {
+                    stacks.get_mut(&register).and_then(|stack| stack.pop());
+                }
+                continue;
+            }
+            let mut defined = vec![];
+            for &phi in ssa.phis.get(&node).map_or(&[][..], |phis| &phis[..]) {
+                let register = ssa.values[phi.0].register();
+                stacks.entry(register).or_default().push(phi);
+                defined.push(register);
+            }
+            for &idx in &cfg.graph[node].instruction_indices {
+                let inst = &function.instructions[idx];
+                for register in inst.registers_read() {
+                    let value = ssa.current_value(&stacks, register);
+                    ssa.uses.insert((idx, register), value);
+                    ssa.users[value.0].insert(ValueUser::Instruction(idx));
+                }
+                for register in inst.registers_written() {
+                    let value = ssa.add_value(ValueDefinition::Instruction {
+                        index: idx,
+                        register,
+                    });
+                    ssa.defs.insert((idx, register), value);
+                    stacks.entry(register).or_default().push(value);
+                    defined.push(register);
+                }
+            }
+            let successors = cfg
+                .graph
+                .neighbors_directed(node, EdgeDirection::Outgoing)
+                .collect::<BTreeSet<_>>();
+            for succ in successors {
+                let phis = ssa.phis.get(&succ).cloned().unwrap_or_default();
+                for phi in phis {
+                    let register = ssa.values[phi.0].register();
+                    let value = ssa.current_value(&stacks, register);
+                    if let ValueDefinition::Phi {
+                        ref mut operands, ..
+                    } = ssa.values[phi.0]
+                    {
+                        operands.push((node, value));
+                    }
+                    ssa.users[value.0].insert(ValueUser::Phi(phi));
+                }
+            }
+            pushed.insert(node, defined);
+            walk.push((node, true));
+            for &child in dominators.children(node).iter().rev() {
+                walk.push((child, false));
+            }
+        }
+        ssa
+    }
+
+    fn add_value(&mut self, definition: ValueDefinition) -> ValueId {
+        let id = ValueId(self.values.len());
+        self.values.push(definition);
+        self.users.push(BTreeSet::new());
+        id
+    }
+
+    /// The value of a register at the current point of renaming. If the
+    /// register has not been written, this is its value on entry.
+    fn current_value(
+        &mut self,
+        stacks: &BTreeMap<Register, Vec<ValueId>>,
+        register: Register,
+    ) -> ValueId {
+        if let Some(&value) = stacks.get(&register).and_then(|stack| stack.last()) {
+            return value;
+        }
+        if let Some(&value) = self.entry_values.get(&register) {
+            return value;
+        }
+        let value = self.add_value(ValueDefinition::Entry(register));
+        self.entry_values.insert(register, value);
+        value
+    }
+
+    /// All of the values, indexed by [`ValueId::index`].
+    pub fn values(&self) -> &[ValueDefinition] {
+        &self.values
+    }
+
+    /// Get the definition of a value.
+    pub fn value(&self, value: ValueId) -> Option<&ValueDefinition> {
+        self.values.get(value.0)
+    }
+
+    /// The phi functions at the start of a block.
+    pub fn phis(&self, block: NodeIndex) -> &[ValueId] {
+        self.phis.get(&block).map_or(&[], |phis| &phis[..])
+    }
+
+    /// The value of `register` read by the instruction at `index`.
+    pub fn used_value(&self, index: usize, register: Register) -> Option<ValueId> {
+        self.uses.get(&(index, register)).cloned()
+    }
+
+    /// The value of `register` written by the instruction at `index`.
+    pub fn defined_value(&self, index: usize, register: Register) -> Option<ValueId> {
+        self.defs.get(&(index, register)).cloned()
+    }
+
+    /// The value that `register` holds on entry to the function, if
+    /// it is read before being written.
+    pub fn entry_value(&self, register: Register) -> Option<ValueId> {
+        self.entry_values.get(&register).cloned()
+    }
+
+    /// The instructions and phi functions that use a value.
+    pub fn users(&self, value: ValueId) -> Option<&BTreeSet<ValueUser>> {
+        self.users.get(value.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SsaForm, ValueDefinition, ValueUser};
+    use crate::address::Address;
+    use crate::function::Function;
+    use crate::operand::Register;
+    use crate::symbol::Symbol;
+    use crate::tests::*;
+
+    #[test]
+    fn straight_line() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::Mov(1, 1)),
+            TestInstruction::new(2, Opcode::Use(1)),
+            TestInstruction::new(3, Opcode::Use(2)),
+            TestInstruction::new(4, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let ssa = SsaForm::new(&f);
+        let r1 = Register::Machine(1);
+        let r2 = Register::Machine(2);
+
+        let v0 = ssa.defined_value(0, r1).unwrap();
+        let v1 = ssa.defined_value(1, r1).unwrap();
+        assert_ne!(v0, v1);
+        assert_eq!(ssa.used_value(1, r1), Some(v0));
+        assert_eq!(ssa.used_value(2, r1), Some(v1));
+        assert_eq!(
+            ssa.value(v1),
+            Some(&ValueDefinition::Instruction {
+                index: 1,
+                register: r1
+            })
+        );
+        assert!(ssa.users(v1).unwrap().contains(&ValueUser::Instruction(2)));
+
+        let entry = ssa.entry_value(r2).unwrap();
+        assert_eq!(ssa.used_value(3, r2), Some(entry));
+        assert_eq!(ssa.value(entry), Some(&ValueDefinition::Entry(r2)));
+        assert!(ssa
+            .phis(f.control_flow_graph.entry_block.unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn diamond_phi() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(2, Opcode::Set(1)),
+            TestInstruction::new(3, Opcode::Use(1)),
+            TestInstruction::new(4, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let ssa = SsaForm::new(&f);
+        let cfg = &f.control_flow_graph;
+        let r1 = Register::Machine(1);
+        let join = cfg.block_finder[&Address::new(3)];
+
+        assert_eq!(ssa.phis(join).len(), 1);
+        let phi = ssa.phis(join)[0];
+        assert_eq!(ssa.used_value(3, r1), Some(phi));
+        match ssa.value(phi) {
+            Some(ValueDefinition::Phi {
+                block,
+                register,
+                operands,
+            }) => {
+                assert_eq!(*block, join);
+                assert_eq!(*register, r1);
+                let mut values = operands.iter().map(|&(_, v)| v).collect::<Vec<_>>();
+                values.sort();
+                assert_eq!(
+                    values,
+                    vec![
+                        ssa.defined_value(0, r1).unwrap(),
+                        ssa.defined_value(2, r1).unwrap()
+                    ]
+                );
+            }
+            other => panic!("expected a phi, got {:?}", other),
+        }
+        let v0 = ssa.defined_value(0, r1).unwrap();
+        assert_eq!(
+            ssa.users(v0).unwrap().iter().collect::<Vec<_>>(),
+            vec![&ValueUser::Phi(phi)]
+        );
+    }
+
+    #[test]
+    fn loop_phi() {
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::Mov(1, 1)),
+            TestInstruction::new(2, Opcode::CJmp(Address::new(1))),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let ssa = SsaForm::new(&f);
+        let header = f.control_flow_graph.block_finder[&Address::new(1)];
+        let r1 = Register::Machine(1);
+
+        assert_eq!(ssa.phis(header).len(), 1);
+        let phi = ssa.phis(header)[0];
+        assert_eq!(ssa.used_value(1, r1), Some(phi));
+        let v1 = ssa.defined_value(1, r1).unwrap();
+        assert!(ssa.users(v1).unwrap().contains(&ValueUser::Phi(phi)));
+    }
+
+    #[test]
+    fn entry_loop_phi() {
+        // The entry block is a loop header, so r1 is either the value
+        // on entry or the value from the back edge.
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Mov(1, 1)),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(0))),
+            TestInstruction::new(2, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(0), None), insts);
+        let ssa = SsaForm::new(&f);
+        let entry = f.control_flow_graph.entry_block.unwrap();
+        let r1 = Register::Machine(1);
+
+        assert_eq!(ssa.phis(entry).len(), 1);
+        let phi = ssa.phis(entry)[0];
+        assert_eq!(ssa.used_value(0, r1), Some(phi));
+        let on_entry = ssa.entry_value(r1).unwrap();
+        assert_eq!(ssa.value(on_entry), Some(&ValueDefinition::Entry(r1)));
+        assert!(ssa.users(on_entry).unwrap().contains(&ValueUser::Phi(phi)));
+        match ssa.value(phi) {
+            Some(ValueDefinition::Phi { operands, .. }) => {
+                let mut values = operands.iter().map(|&(_, v)| v).collect::<Vec<_>>();
+                values.sort();
+                assert_eq!(values, vec![on_entry, ssa.defined_value(0, r1).unwrap()]);
+            }
+            other => panic!("expected a phi, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pruned() {
+        // r1 is written on both paths, but is dead at the join.
+        let insts = vec![
+            TestInstruction::new(0, Opcode::Set(1)),
+            TestInstruction::new(1, Opcode::CJmp(Address::new(3))),
+            TestInstruction::new(2, Opcode::Set(1)),
+            TestInstruction::new(3, Opcode::Ret),
+        ];
+        let f = Function::new(Symbol::new(Address::new(100), None), insts);
+        let ssa = SsaForm::new(&f);
+        let join = f.control_flow_graph.block_finder[&Address::new(3)];
+
+        assert!(ssa.phis(join).is_empty());
+    }
+}
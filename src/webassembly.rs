@@ -4,371 +4,2970 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-extern crate parity_wasm;
+extern crate wasmparser;
 
-use self::parity_wasm::elements::{
-    deserialize_file, External, Instruction, Instructions, Internal,
+use self::wasmparser::{
+    AbstractHeapType, BinaryReader, BlockType, Catch, CompositeInnerType, ElementItems,
+    ElementKind, ExternalKind, FuncType, HeapType, KnownCustom, MemArg, Name, NameSectionReader,
+    Operator, OperatorsReader, Parser, Payload, RefType, TypeRef, UnpackedIndex, ValType,
 };
 use super::address::Address;
 use super::function::Function;
-use super::instruction;
+use super::instruction::{self, BranchTarget};
 use super::module::Module;
+use super::operand::{MemoryOperand, Operand, Register};
 use super::symbol::Symbol;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::fs;
+use std::iter;
 use std::path::Path;
+use std::slice;
+
+/// How the [addresses] of WebAssembly instructions are assigned when
+/// loading a [`Module`].
+///
+/// Function [symbols] are always addressed by their index in the
+/// function index space.
+///
+/// [addresses]: Address
+/// [symbols]: Symbol
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WasmAddressing {
+    /// The position of the instruction within its function body.
+    Ordinal,
+    /// The byte offset of the instruction within the module.
+    ModuleOffset,
+    /// The byte offset of the instruction from the start of the
+    /// contents of the code section, as used by DWARF.
+    CodeSectionOffset,
+}
+
+/// How an instruction affects structured control flow.
+#[derive(Clone, Debug)]
+enum Control {
+    None,
+    Block,
+    Loop,
+    If,
+    Else,
+    End,
+    /// A legacy `try` block.
+    Try,
+    /// A `try_table`, with the label of each of its `catch` clauses and
+    /// whether that clause catches every exception.
+    TryTable(Vec<(u32, bool)>),
+    /// A legacy `catch` or, if every exception is caught, `catch_all`.
+    Catch(bool),
+    /// A legacy `delegate`, which ends a `try` block.
+    Delegate(u32),
+    Br(u32),
+    BrIf(u32),
+    BrTable(Vec<u32>, u32),
+    Return,
+    /// A call, with the index of the function if it is called directly.
+    Call(Option<u32>),
+    /// A tail call, with the index of the function if it is called directly.
+    TailCall(Option<u32>),
+    Throw,
+    Unreachable,
+}
+
+impl Control {
+    fn new(op: &Operator) -> Self {
+        match *op {
+            Operator::Block { .. } => Control::Block,
+            Operator::Loop { .. } => Control::Loop,
+            Operator::If { .. } => Control::If,
+            Operator::Else => Control::Else,
+            Operator::End => Control::End,
+            Operator::Try { .. } => Control::Try,
+            Operator::TryTable { ref try_table } => Control::TryTable(
+                try_table
+                    .catches
+                    .iter()
+                    .map(|catch| match *catch {
+                        Catch::One { label, .. } | Catch::OneRef { label, .. } => (label, false),
+                        Catch::All { label } | Catch::AllRef { label } => (label, true),
+                    })
+                    .collect(),
+            ),
+            Operator::Catch { .. } => Control::Catch(false),
+            Operator::CatchAll => Control::Catch(true),
+            Operator::Delegate { relative_depth } => Control::Delegate(relative_depth),
+            Operator::Br { relative_depth } => Control::Br(relative_depth),
+            // Branches on references are conditional, just like `br_if`.
+            Operator::BrIf { relative_depth }
+            | Operator::BrOnNull { relative_depth }
+            | Operator::BrOnNonNull { relative_depth } => Control::BrIf(relative_depth),
+            Operator::BrTable { ref targets } => Control::BrTable(
+                targets.targets().filter_map(Result::ok).collect(),
+                targets.default(),
+            ),
+            Operator::Return => Control::Return,
+            Operator::Call { function_index } => Control::Call(Some(function_index)),
+            Operator::CallIndirect { .. } | Operator::CallRef { .. } => Control::Call(None),
+            Operator::ReturnCall { function_index } => Control::TailCall(Some(function_index)),
+            Operator::ReturnCallIndirect { .. } | Operator::ReturnCallRef { .. } => {
+                Control::TailCall(None)
+            }
+            Operator::Throw { .. } | Operator::Rethrow { .. } | Operator::ThrowRef => {
+                Control::Throw
+            }
+            Operator::Unreachable => Control::Unreachable,
+            _ => Control::None,
+        }
+    }
+}
 
 /// A representation of a WebAssembly instruction.
+///
+/// Instructions are decoded with [`wasmparser`], which covers the
+/// proposals that recent toolchains use, such as SIMD, bulk memory,
+/// reference types, multi-value, tail calls and exception handling.
+/// Instructions from other proposals are shown as `unknown`.
+///
+/// [`wasmparser`]: https://docs.rs/wasmparser
 #[derive(Debug)]
 pub struct WasmInstruction {
-    idx: u64,
-    insn: Instruction,
+    address: Address,
+    encoded_len: usize,
+    mnemonic: &'static str,
+    immediates: String,
+    operands: Vec<Operand>,
+    registers_read: Vec<Register>,
+    registers_written: Vec<Register>,
+    reads_memory: bool,
+    writes_memory: bool,
+    control: Control,
+    call_targets: Vec<Address>,
+    local_name: Option<String>,
+    target: Option<Address>,
+    branch_targets: Vec<BranchTarget>,
+    exception_handlers: Vec<Address>,
+    is_function_end: bool,
+}
+
+/// The signature and local variables of a WebAssembly function, as kept
+/// by a [`Module`] for each of its functions.
+#[derive(Debug)]
+pub struct WasmDeclaration {
+    type_index: u32,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// The local variables after the parameters, in groups of the
+    /// same type.
+    locals: Vec<(u32, ValType)>,
+    /// The names of the parameters and local variables.
+    local_names: HashMap<u32, String>,
 }
 
 impl WasmInstruction {
-    /// Create a `WasmInstruction` from an opcode.
-    pub fn new(idx: u64, insn: Instruction) -> Self {
-        WasmInstruction { idx, insn }
+    /// Create a `WasmInstruction` from a decoded operator.
+    ///
+    /// The targets of structured control flow instructions are not
+    /// known until the whole function body has been seen, so they are
+    /// resolved by [`Function::from_wasm`].
+    fn new(address: Address, encoded_len: usize, op: &Operator) -> Self {
+        let mnemonic = mnemonic(op);
+        let memarg = memarg(op);
+        let (reads_memory, writes_memory) = memory_access(op);
+        let (registers_read, registers_written) = match *op {
+            Operator::LocalGet { local_index } => (vec![Register::Local(local_index)], vec![]),
+            Operator::LocalSet { local_index } | Operator::LocalTee { local_index } => {
+                (vec![], vec![Register::Local(local_index)])
+            }
+            Operator::GlobalGet { global_index } => (vec![Register::Global(global_index)], vec![]),
+            Operator::GlobalSet { global_index } => (vec![], vec![Register::Global(global_index)]),
+            _ => (vec![], vec![]),
+        };
+        WasmInstruction {
+            address,
+            encoded_len,
+            mnemonic,
+            immediates: immediates(op, memarg),
+            operands: operands(op, memarg),
+            registers_read,
+            registers_written,
+            reads_memory,
+            writes_memory,
+            control: Control::new(op),
+            call_targets: vec![],
+            local_name: None,
+            target: None,
+            branch_targets: vec![],
+            exception_handlers: vec![],
+            is_function_end: false,
+        }
+    }
+
+    /// The length of the encoding of this instruction, in bytes.
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len
+    }
+
+    /// The name of the local variable that is accessed by this instruction.
+    pub fn local_name(&self) -> Option<&str> {
+        self.local_name.as_deref()
+    }
+
+    /// Can this instruction throw an exception that may be caught
+    /// within this function?
+    ///
+    /// An exception from the function called by a tail call is thrown
+    /// to our caller instead.
+    fn can_throw(&self) -> bool {
+        matches!(self.control, Control::Call(_) | Control::Throw)
     }
 }
 
 impl instruction::Instruction for WasmInstruction {
     fn address(&self) -> Address {
-        Address::new(self.idx)
+        self.address
     }
 
     fn comment(&self) -> Option<String> {
-        None
+        self.local_name.clone()
+    }
+
+    fn operands(&self) -> Vec<Operand> {
+        self.operands.clone()
+    }
+
+    fn reads_memory(&self) -> bool {
+        self.reads_memory
+    }
+
+    fn writes_memory(&self) -> bool {
+        self.writes_memory
+    }
+
+    fn registers_read(&self) -> Vec<Register> {
+        self.registers_read.clone()
+    }
+
+    fn registers_written(&self) -> Vec<Register> {
+        self.registers_written.clone()
     }
 
     fn mnemonic(&self) -> &str {
-        match self.insn {
-            Instruction::Unreachable => "unreachable",
-            Instruction::Nop => "nop",
-            Instruction::Block(..) => "block",
-            Instruction::Loop(..) => "loop",
-            Instruction::If(..) => "if",
-            Instruction::Else => "else",
-            Instruction::End => "end",
-            Instruction::Br(..) => "br",
-            Instruction::BrIf(..) => "br_if",
-            Instruction::BrTable(..) => "br_table",
-            Instruction::Return => "return",
-            Instruction::Call(..) => "call",
-            Instruction::CallIndirect(..) => "call_indirect",
-            Instruction::Drop => "drop",
-            Instruction::Select => "select",
-            Instruction::GetLocal(..) => "get_local",
-            Instruction::SetLocal(..) => "set_local",
-            Instruction::TeeLocal(..) => "tee_local",
-            Instruction::GetGlobal(..) => "get_global",
-            Instruction::SetGlobal(..) => "set_global",
-            Instruction::I32Load(..) => "i32.load",
-            Instruction::I64Load(..) => "i64.load",
-            Instruction::F32Load(..) => "f32.load",
-            Instruction::F64Load(..) => "f64.load",
-            Instruction::I32Load8S(..) => "i32.load8_s",
-            Instruction::I32Load8U(..) => "i32.load8_u",
-            Instruction::I32Load16S(..) => "i32.load16_s",
-            Instruction::I32Load16U(..) => "i32.load16_u",
-            Instruction::I64Load8S(..) => "i64.load8_s",
-            Instruction::I64Load8U(..) => "i64.load8_u",
-            Instruction::I64Load16S(..) => "i64.load16_s",
-            Instruction::I64Load16U(..) => "i64.load16_u",
-            Instruction::I64Load32S(..) => "i64.load32_s",
-            Instruction::I64Load32U(..) => "i64.load32_u",
-            Instruction::I32Store(..) => "i32.store",
-            Instruction::I64Store(..) => "i64.store",
-            Instruction::F32Store(..) => "f32.store",
-            Instruction::F64Store(..) => "f64.store",
-            Instruction::I32Store8(..) => "i32.store8",
-            Instruction::I32Store16(..) => "i32.store16",
-            Instruction::I64Store8(..) => "i64.store8",
-            Instruction::I64Store16(..) => "i64.store16",
-            Instruction::I64Store32(..) => "i64.store32",
-            Instruction::CurrentMemory(..) => "current_memory",
-            Instruction::GrowMemory(..) => "grow_memory",
-            Instruction::I32Const(..) => "i32.const",
-            Instruction::I64Const(..) => "i64.const",
-            Instruction::F32Const(..) => "f32.const",
-            Instruction::F64Const(..) => "f64.const",
-            Instruction::I32Eq => "i32.eq",
-            Instruction::I32Eqz => "i32.eqz",
-            Instruction::I32Ne => "i32.ne",
-            Instruction::I32LtS => "i32.lt_s",
-            Instruction::I32LtU => "i32.lt_u",
-            Instruction::I32GtS => "i32.gt_s",
-            Instruction::I32GtU => "i32.gt_u",
-            Instruction::I32LeS => "i32.le_s",
-            Instruction::I32LeU => "i32.le_u",
-            Instruction::I32GeS => "i32.ge_s",
-            Instruction::I32GeU => "i32.ge_u",
-            Instruction::I64Eq => "i64.eq",
-            Instruction::I64Eqz => "i64.eqz",
-            Instruction::I64Ne => "i64.ne",
-            Instruction::I64LtS => "i64.lt_s",
-            Instruction::I64LtU => "i64.lt_u",
-            Instruction::I64GtS => "i64.gt_s",
-            Instruction::I64GtU => "i64.gt_u",
-            Instruction::I64LeS => "i64.le_s",
-            Instruction::I64LeU => "i64.le_u",
-            Instruction::I64GeS => "i64.ge_s",
-            Instruction::I64GeU => "i64.ge_u",
-            Instruction::F32Eq => "f32.eq",
-            Instruction::F32Ne => "f32.ne",
-            Instruction::F32Lt => "f32.lt",
-            Instruction::F32Gt => "f32.gt",
-            Instruction::F32Le => "f32.le",
-            Instruction::F32Ge => "f32.ge",
-            Instruction::F64Eq => "f64.eq",
-            Instruction::F64Ne => "f64.ne",
-            Instruction::F64Lt => "f64.lt",
-            Instruction::F64Gt => "f64.gt",
-            Instruction::F64Le => "f64.le",
-            Instruction::F64Ge => "f64.ge",
-            Instruction::I32Clz => "i32.clz",
-            Instruction::I32Ctz => "i32.ctz",
-            Instruction::I32Popcnt => "i32.popcnt",
-            Instruction::I32Add => "i32.add",
-            Instruction::I32Sub => "i32.sub",
-            Instruction::I32Mul => "i32.mul",
-            Instruction::I32DivS => "i32.div_s",
-            Instruction::I32DivU => "i32.div_u",
-            Instruction::I32RemS => "i32.rem_s",
-            Instruction::I32RemU => "i32.rem_u",
-            Instruction::I32And => "i32.and",
-            Instruction::I32Or => "i32.or",
-            Instruction::I32Xor => "i32.xor",
-            Instruction::I32Shl => "i32.shl",
-            Instruction::I32ShrS => "i32.shr_s",
-            Instruction::I32ShrU => "i32.shr_u",
-            Instruction::I32Rotl => "i32.rotl",
-            Instruction::I32Rotr => "i32.rotr",
-            Instruction::I64Clz => "i64.clz",
-            Instruction::I64Ctz => "i64.ctz",
-            Instruction::I64Popcnt => "i64.popcnt",
-            Instruction::I64Add => "i64.add",
-            Instruction::I64Sub => "i64.sub",
-            Instruction::I64Mul => "i64.mul",
-            Instruction::I64DivS => "i64.div_s",
-            Instruction::I64DivU => "i64.div_u",
-            Instruction::I64RemS => "i64.rem_s",
-            Instruction::I64RemU => "i64.rem_u",
-            Instruction::I64And => "i64.and",
-            Instruction::I64Or => "i64.or",
-            Instruction::I64Xor => "i64.xor",
-            Instruction::I64Shl => "i64.shl",
-            Instruction::I64ShrS => "i64.shr_s",
-            Instruction::I64ShrU => "i64.shr_u",
-            Instruction::I64Rotl => "i64.rotl",
-            Instruction::I64Rotr => "i64.rotr",
-            Instruction::F32Abs => "f32.abs",
-            Instruction::F32Neg => "f32.neg",
-            Instruction::F32Ceil => "f32.ceil",
-            Instruction::F32Floor => "f32.floor",
-            Instruction::F32Trunc => "f32.trunc",
-            Instruction::F32Nearest => "f32.nearest",
-            Instruction::F32Sqrt => "f32.sqrt",
-            Instruction::F32Add => "f32.add",
-            Instruction::F32Sub => "f32.sub",
-            Instruction::F32Mul => "f32.mul",
-            Instruction::F32Div => "f32.div",
-            Instruction::F32Min => "f32.min",
-            Instruction::F32Max => "f32.max",
-            Instruction::F32Copysign => "f32.copysign",
-            Instruction::F64Abs => "f64.abs",
-            Instruction::F64Neg => "f64.neg",
-            Instruction::F64Ceil => "f64.ceil",
-            Instruction::F64Floor => "f64.floor",
-            Instruction::F64Trunc => "f64.trunc",
-            Instruction::F64Nearest => "f64.nearest",
-            Instruction::F64Sqrt => "f64.sqrt",
-            Instruction::F64Add => "f64.add",
-            Instruction::F64Sub => "f64.sub",
-            Instruction::F64Mul => "f64.mul",
-            Instruction::F64Div => "f64.div",
-            Instruction::F64Min => "f64.min",
-            Instruction::F64Max => "f64.max",
-            Instruction::F64Copysign => "f64.copysign",
-            Instruction::I32WrapI64 => "i32.wrap/i64",
-            Instruction::I32TruncSF32 => "i32.trunc_s/f32",
-            Instruction::I32TruncUF32 => "i32.trunc_u/f32",
-            Instruction::I32TruncSF64 => "i32.trunc_s/f64",
-            Instruction::I32TruncUF64 => "i32.trunc_u/f64",
-            Instruction::I64ExtendSI32 => "i64.extend_s/i32",
-            Instruction::I64ExtendUI32 => "i64.extend_u/i32",
-            Instruction::I64TruncSF32 => "i64.trunc_s/f32",
-            Instruction::I64TruncUF32 => "i64.trunc_u/f32",
-            Instruction::I64TruncSF64 => "i64.trunc_s/f64",
-            Instruction::I64TruncUF64 => "i64.trunc_u/f64",
-            Instruction::F32ConvertSI32 => "f32.convert_s/i32",
-            Instruction::F32ConvertUI32 => "f32.convert_u/i32",
-            Instruction::F32ConvertSI64 => "f32.convert_s/i64",
-            Instruction::F32ConvertUI64 => "f32.convert_u/i64",
-            Instruction::F32DemoteF64 => "f32.demote/f64",
-            Instruction::F64ConvertSI32 => "f64.convert_s/i32",
-            Instruction::F64ConvertUI32 => "f64.convert_u/i32",
-            Instruction::F64ConvertSI64 => "f64.convert_s/i64",
-            Instruction::F64ConvertUI64 => "f64.convert_u/i64",
-            Instruction::F64PromoteF32 => "f64.promote/f32",
-            Instruction::I32ReinterpretF32 => "i32.reinterpret/f32",
-            Instruction::I64ReinterpretF64 => "i64.reinterpret/f64",
-            Instruction::F32ReinterpretI32 => "f32.reinterpret/i32",
-            Instruction::F64ReinterpretI64 => "f64.reinterpret/i64",
-            Instruction::AtomicWake(_) => "atomic.wake",
-            Instruction::I32AtomicWait(_) => "i32.atomic.wait",
-            Instruction::I64AtomicWait(_) => "i64.atomic.wait",
-            Instruction::I32AtomicLoad(_) => "i32.atomic.load",
-            Instruction::I64AtomicLoad(_) => "i64.atomic.load",
-            Instruction::I32AtomicLoad8u(_) => "i32.atomic.load8_u",
-            Instruction::I32AtomicLoad16u(_) => "i32.atomic.load16_u",
-            Instruction::I64AtomicLoad8u(_) => "i64.atomic.load8_u",
-            Instruction::I64AtomicLoad16u(_) => "i64.atomic.load16_u",
-            Instruction::I64AtomicLoad32u(_) => "i64.atomic.load32_u",
-            Instruction::I32AtomicStore(_) => "i32.atomic.store",
-            Instruction::I64AtomicStore(_) => "i64.atomic.store",
-            Instruction::I32AtomicStore8u(_) => "i32.atomic.store8_u",
-            Instruction::I32AtomicStore16u(_) => "i32.atomic.store16_u",
-            Instruction::I64AtomicStore8u(_) => "i64.atomic.store8_u",
-            Instruction::I64AtomicStore16u(_) => "i64.atomic.store16_u",
-            Instruction::I64AtomicStore32u(_) => "i64.atomic.store32_u",
-            Instruction::I32AtomicRmwAdd(_) => "i32.atomic.rmw.add",
-            Instruction::I64AtomicRmwAdd(_) => "i64.atomic.rmw.add",
-            Instruction::I32AtomicRmwAdd8u(_) => "i32.atomic.rmw8_u.add",
-            Instruction::I32AtomicRmwAdd16u(_) => "i32.atomic.rmw16_u.add",
-            Instruction::I64AtomicRmwAdd8u(_) => "i64.atomic.rmw8_u.add",
-            Instruction::I64AtomicRmwAdd16u(_) => "i64.atomic.rmw16_u.add",
-            Instruction::I64AtomicRmwAdd32u(_) => "i64.atomic.rmw32_u.add",
-            Instruction::I32AtomicRmwSub(_) => "i32.atomic.rmw.sub",
-            Instruction::I64AtomicRmwSub(_) => "i64.atomic.rmw.sub",
-            Instruction::I32AtomicRmwSub8u(_) => "i32.atomic.rmw8_u.sub",
-            Instruction::I32AtomicRmwSub16u(_) => "i32.atomic.rmw16_u.sub",
-            Instruction::I64AtomicRmwSub8u(_) => "i64.atomic.rmw8_u.sub",
-            Instruction::I64AtomicRmwSub16u(_) => "i64.atomic.rmw16_u.sub",
-            Instruction::I64AtomicRmwSub32u(_) => "i64.atomic.rmw32_u.sub",
-            Instruction::I32AtomicRmwAnd(_) => "i32.atomic.rmw.and",
-            Instruction::I64AtomicRmwAnd(_) => "i64.atomic.rmw.and",
-            Instruction::I32AtomicRmwAnd8u(_) => "i32.atomic.rmw8_u.and",
-            Instruction::I32AtomicRmwAnd16u(_) => "i32.atomic.rmw16_u.and",
-            Instruction::I64AtomicRmwAnd8u(_) => "i64.atomic.rmw8_u.and",
-            Instruction::I64AtomicRmwAnd16u(_) => "i64.atomic.rmw16_u.and",
-            Instruction::I64AtomicRmwAnd32u(_) => "i64.atomic.rmw32_u.and",
-            Instruction::I32AtomicRmwOr(_) => "i32.atomic.rmw.or",
-            Instruction::I64AtomicRmwOr(_) => "i64.atomic.rmw.or",
-            Instruction::I32AtomicRmwOr8u(_) => "i32.atomic.rmw8_u.or",
-            Instruction::I32AtomicRmwOr16u(_) => "i32.atomic.rmw16_u.or",
-            Instruction::I64AtomicRmwOr8u(_) => "i64.atomic.rmw8_u.or",
-            Instruction::I64AtomicRmwOr16u(_) => "i64.atomic.rmw16_u.or",
-            Instruction::I64AtomicRmwOr32u(_) => "i64.atomic.rmw32_u.or",
-            Instruction::I32AtomicRmwXor(_) => "i32.atomic.rmw.xor",
-            Instruction::I64AtomicRmwXor(_) => "i64.atomic.rmw.xor",
-            Instruction::I32AtomicRmwXor8u(_) => "i32.atomic.rmw8_u.xor",
-            Instruction::I32AtomicRmwXor16u(_) => "i32.atomic.rmw16_u.xor",
-            Instruction::I64AtomicRmwXor8u(_) => "i64.atomic.rmw8_u.xor",
-            Instruction::I64AtomicRmwXor16u(_) => "i64.atomic.rmw16_u.xor",
-            Instruction::I64AtomicRmwXor32u(_) => "i64.atomic.rmw32_u.xor",
-            Instruction::I32AtomicRmwXchg(_) => "i32.atomic.rmw.xchg",
-            Instruction::I64AtomicRmwXchg(_) => "i64.atomic.rmw.xchg",
-            Instruction::I32AtomicRmwXchg8u(_) => "i32.atomic.rmw8_u.xchg",
-            Instruction::I32AtomicRmwXchg16u(_) => "i32.atomic.rmw16_u.xchg",
-            Instruction::I64AtomicRmwXchg8u(_) => "i64.atomic.rmw8_u.xchg",
-            Instruction::I64AtomicRmwXchg16u(_) => "i64.atomic.rmw16_u.xchg",
-            Instruction::I64AtomicRmwXchg32u(_) => "i64.atomic.rmw32_u.xchg",
-            Instruction::I32AtomicRmwCmpxchg(_) => "i32.atomic.rmw.cmpxchg",
-            Instruction::I64AtomicRmwCmpxchg(_) => "i64.atomic.rmw.cmpxchg",
-            Instruction::I32AtomicRmwCmpxchg8u(_) => "i32.atomic.rmw8_u.cmpxchg",
-            Instruction::I32AtomicRmwCmpxchg16u(_) => "i32.atomic.rmw16_u.cmpxchg",
-            Instruction::I64AtomicRmwCmpxchg8u(_) => "i64.atomic.rmw8_u.cmpxchg",
-            Instruction::I64AtomicRmwCmpxchg16u(_) => "i64.atomic.rmw16_u.cmpxchg",
-            Instruction::I64AtomicRmwCmpxchg32u(_) => "i64.atomic.rmw32_u.cmpxchg",
-        }
+        self.mnemonic
     }
 
     fn is_call(&self) -> bool {
-        matches!(
-            self.insn,
-            Instruction::Call(..) | Instruction::CallIndirect(..)
-        )
+        matches!(self.control, Control::Call(_))
     }
 
     fn is_local_conditional_jump(&self) -> bool {
-        matches!(
-            self.insn,
-            Instruction::If(..) | Instruction::BrIf(..) | Instruction::BrTable(..)
-        )
+        matches!(self.control, Control::If | Control::BrIf(_))
     }
 
+    /// An `else` is reached at the end of the `then` arm of an `if`,
+    /// so it jumps past the `else` arm. Likewise, a `catch` jumps past
+    /// the handlers that follow it.
     fn is_local_jump(&self) -> bool {
-        self.is_local_conditional_jump() || matches!(self.insn, Instruction::Br(..))
+        self.is_local_conditional_jump()
+            || matches!(
+                self.control,
+                Control::Br(_) | Control::BrTable(..) | Control::Else | Control::Catch(_)
+            )
     }
 
+    /// The `end` of the function body returns, just like `return`.
     fn is_return(&self) -> bool {
-        matches!(self.insn, Instruction::Return)
+        self.is_function_end || matches!(self.control, Control::Return)
+    }
+
+    /// The `return_call` instructions call another function, which
+    /// returns directly to our caller.
+    fn is_tail_call(&self) -> bool {
+        matches!(self.control, Control::TailCall(_))
+    }
+
+    fn is_trap(&self) -> bool {
+        matches!(self.control, Control::Unreachable)
+    }
+
+    /// This is `throw`, `throw_ref` or the legacy `rethrow`.
+    fn is_throw(&self) -> bool {
+        matches!(self.control, Control::Throw)
     }
 
     fn target_address(&self) -> Option<Address> {
-        match self.insn {
-            Instruction::Call(a) => Some(Address::new(u64::from(a))),
-            _ => None,
+        match self.control {
+            Control::Call(Some(index)) | Control::TailCall(Some(index)) => {
+                Some(Address::new(u64::from(index)))
+            }
+            _ => self.target,
+        }
+    }
+
+    fn branch_targets(&self) -> Vec<BranchTarget> {
+        self.branch_targets.clone()
+    }
+
+    fn possible_call_targets(&self) -> Vec<Address> {
+        self.call_targets.clone()
+    }
+
+    fn exception_handlers(&self) -> Vec<Address> {
+        self.exception_handlers.clone()
+    }
+}
+
+/// The mnemonic of an operator, as used by the WebAssembly text format.
+fn mnemonic(op: &Operator) -> &'static str {
+    match *op {
+        Operator::Unreachable => "unreachable",
+        Operator::Nop => "nop",
+        Operator::Block { .. } => "block",
+        Operator::Loop { .. } => "loop",
+        Operator::If { .. } => "if",
+        Operator::Else => "else",
+        Operator::End => "end",
+        Operator::Br { .. } => "br",
+        Operator::BrIf { .. } => "br_if",
+        Operator::BrTable { .. } => "br_table",
+        Operator::Return => "return",
+        Operator::Call { .. } => "call",
+        Operator::CallIndirect { .. } => "call_indirect",
+        Operator::Drop => "drop",
+        Operator::Select => "select",
+        Operator::LocalGet { .. } => "local.get",
+        Operator::LocalSet { .. } => "local.set",
+        Operator::LocalTee { .. } => "local.tee",
+        Operator::GlobalGet { .. } => "global.get",
+        Operator::GlobalSet { .. } => "global.set",
+        Operator::I32Load { .. } => "i32.load",
+        Operator::I64Load { .. } => "i64.load",
+        Operator::F32Load { .. } => "f32.load",
+        Operator::F64Load { .. } => "f64.load",
+        Operator::I32Load8S { .. } => "i32.load8_s",
+        Operator::I32Load8U { .. } => "i32.load8_u",
+        Operator::I32Load16S { .. } => "i32.load16_s",
+        Operator::I32Load16U { .. } => "i32.load16_u",
+        Operator::I64Load8S { .. } => "i64.load8_s",
+        Operator::I64Load8U { .. } => "i64.load8_u",
+        Operator::I64Load16S { .. } => "i64.load16_s",
+        Operator::I64Load16U { .. } => "i64.load16_u",
+        Operator::I64Load32S { .. } => "i64.load32_s",
+        Operator::I64Load32U { .. } => "i64.load32_u",
+        Operator::I32Store { .. } => "i32.store",
+        Operator::I64Store { .. } => "i64.store",
+        Operator::F32Store { .. } => "f32.store",
+        Operator::F64Store { .. } => "f64.store",
+        Operator::I32Store8 { .. } => "i32.store8",
+        Operator::I32Store16 { .. } => "i32.store16",
+        Operator::I64Store8 { .. } => "i64.store8",
+        Operator::I64Store16 { .. } => "i64.store16",
+        Operator::I64Store32 { .. } => "i64.store32",
+        Operator::MemorySize { .. } => "memory.size",
+        Operator::MemoryGrow { .. } => "memory.grow",
+        Operator::I32Const { .. } => "i32.const",
+        Operator::I64Const { .. } => "i64.const",
+        Operator::F32Const { .. } => "f32.const",
+        Operator::F64Const { .. } => "f64.const",
+        Operator::I32Eqz => "i32.eqz",
+        Operator::I32Eq => "i32.eq",
+        Operator::I32Ne => "i32.ne",
+        Operator::I32LtS => "i32.lt_s",
+        Operator::I32LtU => "i32.lt_u",
+        Operator::I32GtS => "i32.gt_s",
+        Operator::I32GtU => "i32.gt_u",
+        Operator::I32LeS => "i32.le_s",
+        Operator::I32LeU => "i32.le_u",
+        Operator::I32GeS => "i32.ge_s",
+        Operator::I32GeU => "i32.ge_u",
+        Operator::I64Eqz => "i64.eqz",
+        Operator::I64Eq => "i64.eq",
+        Operator::I64Ne => "i64.ne",
+        Operator::I64LtS => "i64.lt_s",
+        Operator::I64LtU => "i64.lt_u",
+        Operator::I64GtS => "i64.gt_s",
+        Operator::I64GtU => "i64.gt_u",
+        Operator::I64LeS => "i64.le_s",
+        Operator::I64LeU => "i64.le_u",
+        Operator::I64GeS => "i64.ge_s",
+        Operator::I64GeU => "i64.ge_u",
+        Operator::F32Eq => "f32.eq",
+        Operator::F32Ne => "f32.ne",
+        Operator::F32Lt => "f32.lt",
+        Operator::F32Gt => "f32.gt",
+        Operator::F32Le => "f32.le",
+        Operator::F32Ge => "f32.ge",
+        Operator::F64Eq => "f64.eq",
+        Operator::F64Ne => "f64.ne",
+        Operator::F64Lt => "f64.lt",
+        Operator::F64Gt => "f64.gt",
+        Operator::F64Le => "f64.le",
+        Operator::F64Ge => "f64.ge",
+        Operator::I32Clz => "i32.clz",
+        Operator::I32Ctz => "i32.ctz",
+        Operator::I32Popcnt => "i32.popcnt",
+        Operator::I32Add => "i32.add",
+        Operator::I32Sub => "i32.sub",
+        Operator::I32Mul => "i32.mul",
+        Operator::I32DivS => "i32.div_s",
+        Operator::I32DivU => "i32.div_u",
+        Operator::I32RemS => "i32.rem_s",
+        Operator::I32RemU => "i32.rem_u",
+        Operator::I32And => "i32.and",
+        Operator::I32Or => "i32.or",
+        Operator::I32Xor => "i32.xor",
+        Operator::I32Shl => "i32.shl",
+        Operator::I32ShrS => "i32.shr_s",
+        Operator::I32ShrU => "i32.shr_u",
+        Operator::I32Rotl => "i32.rotl",
+        Operator::I32Rotr => "i32.rotr",
+        Operator::I64Clz => "i64.clz",
+        Operator::I64Ctz => "i64.ctz",
+        Operator::I64Popcnt => "i64.popcnt",
+        Operator::I64Add => "i64.add",
+        Operator::I64Sub => "i64.sub",
+        Operator::I64Mul => "i64.mul",
+        Operator::I64DivS => "i64.div_s",
+        Operator::I64DivU => "i64.div_u",
+        Operator::I64RemS => "i64.rem_s",
+        Operator::I64RemU => "i64.rem_u",
+        Operator::I64And => "i64.and",
+        Operator::I64Or => "i64.or",
+        Operator::I64Xor => "i64.xor",
+        Operator::I64Shl => "i64.shl",
+        Operator::I64ShrS => "i64.shr_s",
+        Operator::I64ShrU => "i64.shr_u",
+        Operator::I64Rotl => "i64.rotl",
+        Operator::I64Rotr => "i64.rotr",
+        Operator::F32Abs => "f32.abs",
+        Operator::F32Neg => "f32.neg",
+        Operator::F32Ceil => "f32.ceil",
+        Operator::F32Floor => "f32.floor",
+        Operator::F32Trunc => "f32.trunc",
+        Operator::F32Nearest => "f32.nearest",
+        Operator::F32Sqrt => "f32.sqrt",
+        Operator::F32Add => "f32.add",
+        Operator::F32Sub => "f32.sub",
+        Operator::F32Mul => "f32.mul",
+        Operator::F32Div => "f32.div",
+        Operator::F32Min => "f32.min",
+        Operator::F32Max => "f32.max",
+        Operator::F32Copysign => "f32.copysign",
+        Operator::F64Abs => "f64.abs",
+        Operator::F64Neg => "f64.neg",
+        Operator::F64Ceil => "f64.ceil",
+        Operator::F64Floor => "f64.floor",
+        Operator::F64Trunc => "f64.trunc",
+        Operator::F64Nearest => "f64.nearest",
+        Operator::F64Sqrt => "f64.sqrt",
+        Operator::F64Add => "f64.add",
+        Operator::F64Sub => "f64.sub",
+        Operator::F64Mul => "f64.mul",
+        Operator::F64Div => "f64.div",
+        Operator::F64Min => "f64.min",
+        Operator::F64Max => "f64.max",
+        Operator::F64Copysign => "f64.copysign",
+        Operator::I32WrapI64 => "i32.wrap_i64",
+        Operator::I32TruncF32S => "i32.trunc_f32_s",
+        Operator::I32TruncF32U => "i32.trunc_f32_u",
+        Operator::I32TruncF64S => "i32.trunc_f64_s",
+        Operator::I32TruncF64U => "i32.trunc_f64_u",
+        Operator::I64ExtendI32S => "i64.extend_i32_s",
+        Operator::I64ExtendI32U => "i64.extend_i32_u",
+        Operator::I64TruncF32S => "i64.trunc_f32_s",
+        Operator::I64TruncF32U => "i64.trunc_f32_u",
+        Operator::I64TruncF64S => "i64.trunc_f64_s",
+        Operator::I64TruncF64U => "i64.trunc_f64_u",
+        Operator::F32ConvertI32S => "f32.convert_i32_s",
+        Operator::F32ConvertI32U => "f32.convert_i32_u",
+        Operator::F32ConvertI64S => "f32.convert_i64_s",
+        Operator::F32ConvertI64U => "f32.convert_i64_u",
+        Operator::F32DemoteF64 => "f32.demote_f64",
+        Operator::F64ConvertI32S => "f64.convert_i32_s",
+        Operator::F64ConvertI32U => "f64.convert_i32_u",
+        Operator::F64ConvertI64S => "f64.convert_i64_s",
+        Operator::F64ConvertI64U => "f64.convert_i64_u",
+        Operator::F64PromoteF32 => "f64.promote_f32",
+        Operator::I32ReinterpretF32 => "i32.reinterpret_f32",
+        Operator::I64ReinterpretF64 => "i64.reinterpret_f64",
+        Operator::F32ReinterpretI32 => "f32.reinterpret_i32",
+        Operator::F64ReinterpretI64 => "f64.reinterpret_i64",
+        Operator::I32Extend8S => "i32.extend8_s",
+        Operator::I32Extend16S => "i32.extend16_s",
+        Operator::I64Extend8S => "i64.extend8_s",
+        Operator::I64Extend16S => "i64.extend16_s",
+        Operator::I64Extend32S => "i64.extend32_s",
+        Operator::I32TruncSatF32S => "i32.trunc_sat_f32_s",
+        Operator::I32TruncSatF32U => "i32.trunc_sat_f32_u",
+        Operator::I32TruncSatF64S => "i32.trunc_sat_f64_s",
+        Operator::I32TruncSatF64U => "i32.trunc_sat_f64_u",
+        Operator::I64TruncSatF32S => "i64.trunc_sat_f32_s",
+        Operator::I64TruncSatF32U => "i64.trunc_sat_f32_u",
+        Operator::I64TruncSatF64S => "i64.trunc_sat_f64_s",
+        Operator::I64TruncSatF64U => "i64.trunc_sat_f64_u",
+        Operator::MemoryInit { .. } => "memory.init",
+        Operator::DataDrop { .. } => "data.drop",
+        Operator::MemoryCopy { .. } => "memory.copy",
+        Operator::MemoryFill { .. } => "memory.fill",
+        Operator::TableInit { .. } => "table.init",
+        Operator::ElemDrop { .. } => "elem.drop",
+        Operator::TableCopy { .. } => "table.copy",
+        Operator::TypedSelect { .. } => "select",
+        Operator::TypedSelectMulti { .. } => "select",
+        Operator::RefNull { .. } => "ref.null",
+        Operator::RefIsNull => "ref.is_null",
+        Operator::RefFunc { .. } => "ref.func",
+        Operator::TableFill { .. } => "table.fill",
+        Operator::TableGet { .. } => "table.get",
+        Operator::TableSet { .. } => "table.set",
+        Operator::TableGrow { .. } => "table.grow",
+        Operator::TableSize { .. } => "table.size",
+        Operator::ReturnCall { .. } => "return_call",
+        Operator::ReturnCallIndirect { .. } => "return_call_indirect",
+        Operator::MemoryAtomicNotify { .. } => "memory.atomic.notify",
+        Operator::MemoryAtomicWait32 { .. } => "memory.atomic.wait32",
+        Operator::MemoryAtomicWait64 { .. } => "memory.atomic.wait64",
+        Operator::AtomicFence => "atomic.fence",
+        Operator::I32AtomicLoad { .. } => "i32.atomic.load",
+        Operator::I64AtomicLoad { .. } => "i64.atomic.load",
+        Operator::I32AtomicLoad8U { .. } => "i32.atomic.load8_u",
+        Operator::I32AtomicLoad16U { .. } => "i32.atomic.load16_u",
+        Operator::I64AtomicLoad8U { .. } => "i64.atomic.load8_u",
+        Operator::I64AtomicLoad16U { .. } => "i64.atomic.load16_u",
+        Operator::I64AtomicLoad32U { .. } => "i64.atomic.load32_u",
+        Operator::I32AtomicStore { .. } => "i32.atomic.store",
+        Operator::I64AtomicStore { .. } => "i64.atomic.store",
+        Operator::I32AtomicStore8 { .. } => "i32.atomic.store8",
+        Operator::I32AtomicStore16 { .. } => "i32.atomic.store16",
+        Operator::I64AtomicStore8 { .. } => "i64.atomic.store8",
+        Operator::I64AtomicStore16 { .. } => "i64.atomic.store16",
+        Operator::I64AtomicStore32 { .. } => "i64.atomic.store32",
+        Operator::I32AtomicRmwAdd { .. } => "i32.atomic.rmw.add",
+        Operator::I64AtomicRmwAdd { .. } => "i64.atomic.rmw.add",
+        Operator::I32AtomicRmw8AddU { .. } => "i32.atomic.rmw8.add_u",
+        Operator::I32AtomicRmw16AddU { .. } => "i32.atomic.rmw16.add_u",
+        Operator::I64AtomicRmw8AddU { .. } => "i64.atomic.rmw8.add_u",
+        Operator::I64AtomicRmw16AddU { .. } => "i64.atomic.rmw16.add_u",
+        Operator::I64AtomicRmw32AddU { .. } => "i64.atomic.rmw32.add_u",
+        Operator::I32AtomicRmwSub { .. } => "i32.atomic.rmw.sub",
+        Operator::I64AtomicRmwSub { .. } => "i64.atomic.rmw.sub",
+        Operator::I32AtomicRmw8SubU { .. } => "i32.atomic.rmw8.sub_u",
+        Operator::I32AtomicRmw16SubU { .. } => "i32.atomic.rmw16.sub_u",
+        Operator::I64AtomicRmw8SubU { .. } => "i64.atomic.rmw8.sub_u",
+        Operator::I64AtomicRmw16SubU { .. } => "i64.atomic.rmw16.sub_u",
+        Operator::I64AtomicRmw32SubU { .. } => "i64.atomic.rmw32.sub_u",
+        Operator::I32AtomicRmwAnd { .. } => "i32.atomic.rmw.and",
+        Operator::I64AtomicRmwAnd { .. } => "i64.atomic.rmw.and",
+        Operator::I32AtomicRmw8AndU { .. } => "i32.atomic.rmw8.and_u",
+        Operator::I32AtomicRmw16AndU { .. } => "i32.atomic.rmw16.and_u",
+        Operator::I64AtomicRmw8AndU { .. } => "i64.atomic.rmw8.and_u",
+        Operator::I64AtomicRmw16AndU { .. } => "i64.atomic.rmw16.and_u",
+        Operator::I64AtomicRmw32AndU { .. } => "i64.atomic.rmw32.and_u",
+        Operator::I32AtomicRmwOr { .. } => "i32.atomic.rmw.or",
+        Operator::I64AtomicRmwOr { .. } => "i64.atomic.rmw.or",
+        Operator::I32AtomicRmw8OrU { .. } => "i32.atomic.rmw8.or_u",
+        Operator::I32AtomicRmw16OrU { .. } => "i32.atomic.rmw16.or_u",
+        Operator::I64AtomicRmw8OrU { .. } => "i64.atomic.rmw8.or_u",
+        Operator::I64AtomicRmw16OrU { .. } => "i64.atomic.rmw16.or_u",
+        Operator::I64AtomicRmw32OrU { .. } => "i64.atomic.rmw32.or_u",
+        Operator::I32AtomicRmwXor { .. } => "i32.atomic.rmw.xor",
+        Operator::I64AtomicRmwXor { .. } => "i64.atomic.rmw.xor",
+        Operator::I32AtomicRmw8XorU { .. } => "i32.atomic.rmw8.xor_u",
+        Operator::I32AtomicRmw16XorU { .. } => "i32.atomic.rmw16.xor_u",
+        Operator::I64AtomicRmw8XorU { .. } => "i64.atomic.rmw8.xor_u",
+        Operator::I64AtomicRmw16XorU { .. } => "i64.atomic.rmw16.xor_u",
+        Operator::I64AtomicRmw32XorU { .. } => "i64.atomic.rmw32.xor_u",
+        Operator::I32AtomicRmwXchg { .. } => "i32.atomic.rmw.xchg",
+        Operator::I64AtomicRmwXchg { .. } => "i64.atomic.rmw.xchg",
+        Operator::I32AtomicRmw8XchgU { .. } => "i32.atomic.rmw8.xchg_u",
+        Operator::I32AtomicRmw16XchgU { .. } => "i32.atomic.rmw16.xchg_u",
+        Operator::I64AtomicRmw8XchgU { .. } => "i64.atomic.rmw8.xchg_u",
+        Operator::I64AtomicRmw16XchgU { .. } => "i64.atomic.rmw16.xchg_u",
+        Operator::I64AtomicRmw32XchgU { .. } => "i64.atomic.rmw32.xchg_u",
+        Operator::I32AtomicRmwCmpxchg { .. } => "i32.atomic.rmw.cmpxchg",
+        Operator::I64AtomicRmwCmpxchg { .. } => "i64.atomic.rmw.cmpxchg",
+        Operator::I32AtomicRmw8CmpxchgU { .. } => "i32.atomic.rmw8.cmpxchg_u",
+        Operator::I32AtomicRmw16CmpxchgU { .. } => "i32.atomic.rmw16.cmpxchg_u",
+        Operator::I64AtomicRmw8CmpxchgU { .. } => "i64.atomic.rmw8.cmpxchg_u",
+        Operator::I64AtomicRmw16CmpxchgU { .. } => "i64.atomic.rmw16.cmpxchg_u",
+        Operator::I64AtomicRmw32CmpxchgU { .. } => "i64.atomic.rmw32.cmpxchg_u",
+        Operator::V128Load { .. } => "v128.load",
+        Operator::V128Load8x8S { .. } => "v128.load8x8_s",
+        Operator::V128Load8x8U { .. } => "v128.load8x8_u",
+        Operator::V128Load16x4S { .. } => "v128.load16x4_s",
+        Operator::V128Load16x4U { .. } => "v128.load16x4_u",
+        Operator::V128Load32x2S { .. } => "v128.load32x2_s",
+        Operator::V128Load32x2U { .. } => "v128.load32x2_u",
+        Operator::V128Load8Splat { .. } => "v128.load8_splat",
+        Operator::V128Load16Splat { .. } => "v128.load16_splat",
+        Operator::V128Load32Splat { .. } => "v128.load32_splat",
+        Operator::V128Load64Splat { .. } => "v128.load64_splat",
+        Operator::V128Load32Zero { .. } => "v128.load32_zero",
+        Operator::V128Load64Zero { .. } => "v128.load64_zero",
+        Operator::V128Store { .. } => "v128.store",
+        Operator::V128Load8Lane { .. } => "v128.load8_lane",
+        Operator::V128Load16Lane { .. } => "v128.load16_lane",
+        Operator::V128Load32Lane { .. } => "v128.load32_lane",
+        Operator::V128Load64Lane { .. } => "v128.load64_lane",
+        Operator::V128Store8Lane { .. } => "v128.store8_lane",
+        Operator::V128Store16Lane { .. } => "v128.store16_lane",
+        Operator::V128Store32Lane { .. } => "v128.store32_lane",
+        Operator::V128Store64Lane { .. } => "v128.store64_lane",
+        Operator::V128Const { .. } => "v128.const",
+        Operator::I8x16Shuffle { .. } => "i8x16.shuffle",
+        Operator::I8x16ExtractLaneS { .. } => "i8x16.extract_lane_s",
+        Operator::I8x16ExtractLaneU { .. } => "i8x16.extract_lane_u",
+        Operator::I8x16ReplaceLane { .. } => "i8x16.replace_lane",
+        Operator::I16x8ExtractLaneS { .. } => "i16x8.extract_lane_s",
+        Operator::I16x8ExtractLaneU { .. } => "i16x8.extract_lane_u",
+        Operator::I16x8ReplaceLane { .. } => "i16x8.replace_lane",
+        Operator::I32x4ExtractLane { .. } => "i32x4.extract_lane",
+        Operator::I32x4ReplaceLane { .. } => "i32x4.replace_lane",
+        Operator::I64x2ExtractLane { .. } => "i64x2.extract_lane",
+        Operator::I64x2ReplaceLane { .. } => "i64x2.replace_lane",
+        Operator::F32x4ExtractLane { .. } => "f32x4.extract_lane",
+        Operator::F32x4ReplaceLane { .. } => "f32x4.replace_lane",
+        Operator::F64x2ExtractLane { .. } => "f64x2.extract_lane",
+        Operator::F64x2ReplaceLane { .. } => "f64x2.replace_lane",
+        Operator::I8x16Swizzle => "i8x16.swizzle",
+        Operator::I8x16Splat => "i8x16.splat",
+        Operator::I16x8Splat => "i16x8.splat",
+        Operator::I32x4Splat => "i32x4.splat",
+        Operator::I64x2Splat => "i64x2.splat",
+        Operator::F32x4Splat => "f32x4.splat",
+        Operator::F64x2Splat => "f64x2.splat",
+        Operator::I8x16Eq => "i8x16.eq",
+        Operator::I8x16Ne => "i8x16.ne",
+        Operator::I8x16LtS => "i8x16.lt_s",
+        Operator::I8x16LtU => "i8x16.lt_u",
+        Operator::I8x16GtS => "i8x16.gt_s",
+        Operator::I8x16GtU => "i8x16.gt_u",
+        Operator::I8x16LeS => "i8x16.le_s",
+        Operator::I8x16LeU => "i8x16.le_u",
+        Operator::I8x16GeS => "i8x16.ge_s",
+        Operator::I8x16GeU => "i8x16.ge_u",
+        Operator::I16x8Eq => "i16x8.eq",
+        Operator::I16x8Ne => "i16x8.ne",
+        Operator::I16x8LtS => "i16x8.lt_s",
+        Operator::I16x8LtU => "i16x8.lt_u",
+        Operator::I16x8GtS => "i16x8.gt_s",
+        Operator::I16x8GtU => "i16x8.gt_u",
+        Operator::I16x8LeS => "i16x8.le_s",
+        Operator::I16x8LeU => "i16x8.le_u",
+        Operator::I16x8GeS => "i16x8.ge_s",
+        Operator::I16x8GeU => "i16x8.ge_u",
+        Operator::I32x4Eq => "i32x4.eq",
+        Operator::I32x4Ne => "i32x4.ne",
+        Operator::I32x4LtS => "i32x4.lt_s",
+        Operator::I32x4LtU => "i32x4.lt_u",
+        Operator::I32x4GtS => "i32x4.gt_s",
+        Operator::I32x4GtU => "i32x4.gt_u",
+        Operator::I32x4LeS => "i32x4.le_s",
+        Operator::I32x4LeU => "i32x4.le_u",
+        Operator::I32x4GeS => "i32x4.ge_s",
+        Operator::I32x4GeU => "i32x4.ge_u",
+        Operator::I64x2Eq => "i64x2.eq",
+        Operator::I64x2Ne => "i64x2.ne",
+        Operator::I64x2LtS => "i64x2.lt_s",
+        Operator::I64x2GtS => "i64x2.gt_s",
+        Operator::I64x2LeS => "i64x2.le_s",
+        Operator::I64x2GeS => "i64x2.ge_s",
+        Operator::F32x4Eq => "f32x4.eq",
+        Operator::F32x4Ne => "f32x4.ne",
+        Operator::F32x4Lt => "f32x4.lt",
+        Operator::F32x4Gt => "f32x4.gt",
+        Operator::F32x4Le => "f32x4.le",
+        Operator::F32x4Ge => "f32x4.ge",
+        Operator::F64x2Eq => "f64x2.eq",
+        Operator::F64x2Ne => "f64x2.ne",
+        Operator::F64x2Lt => "f64x2.lt",
+        Operator::F64x2Gt => "f64x2.gt",
+        Operator::F64x2Le => "f64x2.le",
+        Operator::F64x2Ge => "f64x2.ge",
+        Operator::V128Not => "v128.not",
+        Operator::V128And => "v128.and",
+        Operator::V128AndNot => "v128.andnot",
+        Operator::V128Or => "v128.or",
+        Operator::V128Xor => "v128.xor",
+        Operator::V128Bitselect => "v128.bitselect",
+        Operator::V128AnyTrue => "v128.any_true",
+        Operator::I8x16Abs => "i8x16.abs",
+        Operator::I8x16Neg => "i8x16.neg",
+        Operator::I8x16Popcnt => "i8x16.popcnt",
+        Operator::I8x16AllTrue => "i8x16.all_true",
+        Operator::I8x16Bitmask => "i8x16.bitmask",
+        Operator::I8x16NarrowI16x8S => "i8x16.narrow_i16x8_s",
+        Operator::I8x16NarrowI16x8U => "i8x16.narrow_i16x8_u",
+        Operator::I8x16Shl => "i8x16.shl",
+        Operator::I8x16ShrS => "i8x16.shr_s",
+        Operator::I8x16ShrU => "i8x16.shr_u",
+        Operator::I8x16Add => "i8x16.add",
+        Operator::I8x16AddSatS => "i8x16.add_sat_s",
+        Operator::I8x16AddSatU => "i8x16.add_sat_u",
+        Operator::I8x16Sub => "i8x16.sub",
+        Operator::I8x16SubSatS => "i8x16.sub_sat_s",
+        Operator::I8x16SubSatU => "i8x16.sub_sat_u",
+        Operator::I8x16MinS => "i8x16.min_s",
+        Operator::I8x16MinU => "i8x16.min_u",
+        Operator::I8x16MaxS => "i8x16.max_s",
+        Operator::I8x16MaxU => "i8x16.max_u",
+        Operator::I8x16AvgrU => "i8x16.avgr_u",
+        Operator::I16x8ExtAddPairwiseI8x16S => "i16x8.extadd_pairwise_i8x16_s",
+        Operator::I16x8ExtAddPairwiseI8x16U => "i16x8.extadd_pairwise_i8x16_u",
+        Operator::I16x8Abs => "i16x8.abs",
+        Operator::I16x8Neg => "i16x8.neg",
+        Operator::I16x8Q15MulrSatS => "i16x8.q15mulr_sat_s",
+        Operator::I16x8AllTrue => "i16x8.all_true",
+        Operator::I16x8Bitmask => "i16x8.bitmask",
+        Operator::I16x8NarrowI32x4S => "i16x8.narrow_i32x4_s",
+        Operator::I16x8NarrowI32x4U => "i16x8.narrow_i32x4_u",
+        Operator::I16x8ExtendLowI8x16S => "i16x8.extend_low_i8x16_s",
+        Operator::I16x8ExtendHighI8x16S => "i16x8.extend_high_i8x16_s",
+        Operator::I16x8ExtendLowI8x16U => "i16x8.extend_low_i8x16_u",
+        Operator::I16x8ExtendHighI8x16U => "i16x8.extend_high_i8x16_u",
+        Operator::I16x8Shl => "i16x8.shl",
+        Operator::I16x8ShrS => "i16x8.shr_s",
+        Operator::I16x8ShrU => "i16x8.shr_u",
+        Operator::I16x8Add => "i16x8.add",
+        Operator::I16x8AddSatS => "i16x8.add_sat_s",
+        Operator::I16x8AddSatU => "i16x8.add_sat_u",
+        Operator::I16x8Sub => "i16x8.sub",
+        Operator::I16x8SubSatS => "i16x8.sub_sat_s",
+        Operator::I16x8SubSatU => "i16x8.sub_sat_u",
+        Operator::I16x8Mul => "i16x8.mul",
+        Operator::I16x8MinS => "i16x8.min_s",
+        Operator::I16x8MinU => "i16x8.min_u",
+        Operator::I16x8MaxS => "i16x8.max_s",
+        Operator::I16x8MaxU => "i16x8.max_u",
+        Operator::I16x8AvgrU => "i16x8.avgr_u",
+        Operator::I16x8ExtMulLowI8x16S => "i16x8.extmul_low_i8x16_s",
+        Operator::I16x8ExtMulHighI8x16S => "i16x8.extmul_high_i8x16_s",
+        Operator::I16x8ExtMulLowI8x16U => "i16x8.extmul_low_i8x16_u",
+        Operator::I16x8ExtMulHighI8x16U => "i16x8.extmul_high_i8x16_u",
+        Operator::I32x4ExtAddPairwiseI16x8S => "i32x4.extadd_pairwise_i16x8_s",
+        Operator::I32x4ExtAddPairwiseI16x8U => "i32x4.extadd_pairwise_i16x8_u",
+        Operator::I32x4Abs => "i32x4.abs",
+        Operator::I32x4Neg => "i32x4.neg",
+        Operator::I32x4AllTrue => "i32x4.all_true",
+        Operator::I32x4Bitmask => "i32x4.bitmask",
+        Operator::I32x4ExtendLowI16x8S => "i32x4.extend_low_i16x8_s",
+        Operator::I32x4ExtendHighI16x8S => "i32x4.extend_high_i16x8_s",
+        Operator::I32x4ExtendLowI16x8U => "i32x4.extend_low_i16x8_u",
+        Operator::I32x4ExtendHighI16x8U => "i32x4.extend_high_i16x8_u",
+        Operator::I32x4Shl => "i32x4.shl",
+        Operator::I32x4ShrS => "i32x4.shr_s",
+        Operator::I32x4ShrU => "i32x4.shr_u",
+        Operator::I32x4Add => "i32x4.add",
+        Operator::I32x4Sub => "i32x4.sub",
+        Operator::I32x4Mul => "i32x4.mul",
+        Operator::I32x4MinS => "i32x4.min_s",
+        Operator::I32x4MinU => "i32x4.min_u",
+        Operator::I32x4MaxS => "i32x4.max_s",
+        Operator::I32x4MaxU => "i32x4.max_u",
+        Operator::I32x4DotI16x8S => "i32x4.dot_i16x8_s",
+        Operator::I32x4ExtMulLowI16x8S => "i32x4.extmul_low_i16x8_s",
+        Operator::I32x4ExtMulHighI16x8S => "i32x4.extmul_high_i16x8_s",
+        Operator::I32x4ExtMulLowI16x8U => "i32x4.extmul_low_i16x8_u",
+        Operator::I32x4ExtMulHighI16x8U => "i32x4.extmul_high_i16x8_u",
+        Operator::I64x2Abs => "i64x2.abs",
+        Operator::I64x2Neg => "i64x2.neg",
+        Operator::I64x2AllTrue => "i64x2.all_true",
+        Operator::I64x2Bitmask => "i64x2.bitmask",
+        Operator::I64x2ExtendLowI32x4S => "i64x2.extend_low_i32x4_s",
+        Operator::I64x2ExtendHighI32x4S => "i64x2.extend_high_i32x4_s",
+        Operator::I64x2ExtendLowI32x4U => "i64x2.extend_low_i32x4_u",
+        Operator::I64x2ExtendHighI32x4U => "i64x2.extend_high_i32x4_u",
+        Operator::I64x2Shl => "i64x2.shl",
+        Operator::I64x2ShrS => "i64x2.shr_s",
+        Operator::I64x2ShrU => "i64x2.shr_u",
+        Operator::I64x2Add => "i64x2.add",
+        Operator::I64x2Sub => "i64x2.sub",
+        Operator::I64x2Mul => "i64x2.mul",
+        Operator::I64x2ExtMulLowI32x4S => "i64x2.extmul_low_i32x4_s",
+        Operator::I64x2ExtMulHighI32x4S => "i64x2.extmul_high_i32x4_s",
+        Operator::I64x2ExtMulLowI32x4U => "i64x2.extmul_low_i32x4_u",
+        Operator::I64x2ExtMulHighI32x4U => "i64x2.extmul_high_i32x4_u",
+        Operator::F32x4Ceil => "f32x4.ceil",
+        Operator::F32x4Floor => "f32x4.floor",
+        Operator::F32x4Trunc => "f32x4.trunc",
+        Operator::F32x4Nearest => "f32x4.nearest",
+        Operator::F32x4Abs => "f32x4.abs",
+        Operator::F32x4Neg => "f32x4.neg",
+        Operator::F32x4Sqrt => "f32x4.sqrt",
+        Operator::F32x4Add => "f32x4.add",
+        Operator::F32x4Sub => "f32x4.sub",
+        Operator::F32x4Mul => "f32x4.mul",
+        Operator::F32x4Div => "f32x4.div",
+        Operator::F32x4Min => "f32x4.min",
+        Operator::F32x4Max => "f32x4.max",
+        Operator::F32x4PMin => "f32x4.pmin",
+        Operator::F32x4PMax => "f32x4.pmax",
+        Operator::F64x2Ceil => "f64x2.ceil",
+        Operator::F64x2Floor => "f64x2.floor",
+        Operator::F64x2Trunc => "f64x2.trunc",
+        Operator::F64x2Nearest => "f64x2.nearest",
+        Operator::F64x2Abs => "f64x2.abs",
+        Operator::F64x2Neg => "f64x2.neg",
+        Operator::F64x2Sqrt => "f64x2.sqrt",
+        Operator::F64x2Add => "f64x2.add",
+        Operator::F64x2Sub => "f64x2.sub",
+        Operator::F64x2Mul => "f64x2.mul",
+        Operator::F64x2Div => "f64x2.div",
+        Operator::F64x2Min => "f64x2.min",
+        Operator::F64x2Max => "f64x2.max",
+        Operator::F64x2PMin => "f64x2.pmin",
+        Operator::F64x2PMax => "f64x2.pmax",
+        Operator::I32x4TruncSatF32x4S => "i32x4.trunc_sat_f32x4_s",
+        Operator::I32x4TruncSatF32x4U => "i32x4.trunc_sat_f32x4_u",
+        Operator::F32x4ConvertI32x4S => "f32x4.convert_i32x4_s",
+        Operator::F32x4ConvertI32x4U => "f32x4.convert_i32x4_u",
+        Operator::I32x4TruncSatF64x2SZero => "i32x4.trunc_sat_f64x2_s_zero",
+        Operator::I32x4TruncSatF64x2UZero => "i32x4.trunc_sat_f64x2_u_zero",
+        Operator::F64x2ConvertLowI32x4S => "f64x2.convert_low_i32x4_s",
+        Operator::F64x2ConvertLowI32x4U => "f64x2.convert_low_i32x4_u",
+        Operator::F32x4DemoteF64x2Zero => "f32x4.demote_f64x2_zero",
+        Operator::F64x2PromoteLowF32x4 => "f64x2.promote_low_f32x4",
+        Operator::I8x16RelaxedSwizzle => "i8x16.relaxed_swizzle",
+        Operator::I32x4RelaxedTruncF32x4S => "i32x4.relaxed_trunc_f32x4_s",
+        Operator::I32x4RelaxedTruncF32x4U => "i32x4.relaxed_trunc_f32x4_u",
+        Operator::I32x4RelaxedTruncF64x2SZero => "i32x4.relaxed_trunc_f64x2_s_zero",
+        Operator::I32x4RelaxedTruncF64x2UZero => "i32x4.relaxed_trunc_f64x2_u_zero",
+        Operator::F32x4RelaxedMadd => "f32x4.relaxed_madd",
+        Operator::F32x4RelaxedNmadd => "f32x4.relaxed_nmadd",
+        Operator::F64x2RelaxedMadd => "f64x2.relaxed_madd",
+        Operator::F64x2RelaxedNmadd => "f64x2.relaxed_nmadd",
+        Operator::I8x16RelaxedLaneselect => "i8x16.relaxed_laneselect",
+        Operator::I16x8RelaxedLaneselect => "i16x8.relaxed_laneselect",
+        Operator::I32x4RelaxedLaneselect => "i32x4.relaxed_laneselect",
+        Operator::I64x2RelaxedLaneselect => "i64x2.relaxed_laneselect",
+        Operator::F32x4RelaxedMin => "f32x4.relaxed_min",
+        Operator::F32x4RelaxedMax => "f32x4.relaxed_max",
+        Operator::F64x2RelaxedMin => "f64x2.relaxed_min",
+        Operator::F64x2RelaxedMax => "f64x2.relaxed_max",
+        Operator::I16x8RelaxedQ15mulrS => "i16x8.relaxed_q15mulr_s",
+        Operator::I16x8RelaxedDotI8x16I7x16S => "i16x8.relaxed_dot_i8x16_i7x16_s",
+        Operator::I32x4RelaxedDotI8x16I7x16AddS => "i32x4.relaxed_dot_i8x16_i7x16_add_s",
+        Operator::TryTable { .. } => "try_table",
+        Operator::Throw { .. } => "throw",
+        Operator::ThrowRef => "throw_ref",
+        Operator::Try { .. } => "try",
+        Operator::Catch { .. } => "catch",
+        Operator::Rethrow { .. } => "rethrow",
+        Operator::Delegate { .. } => "delegate",
+        Operator::CatchAll => "catch_all",
+        Operator::CallRef { .. } => "call_ref",
+        Operator::ReturnCallRef { .. } => "return_call_ref",
+        Operator::RefAsNonNull => "ref.as_non_null",
+        Operator::BrOnNull { .. } => "br_on_null",
+        Operator::BrOnNonNull { .. } => "br_on_non_null",
+        _ => "unknown",
+    }
+}
+
+/// The memory access of an operator that loads from or stores to
+/// linear memory.
+fn memarg(op: &Operator) -> Option<MemArg> {
+    match *op {
+        Operator::I32Load { memarg, .. }
+        | Operator::I64Load { memarg, .. }
+        | Operator::F32Load { memarg, .. }
+        | Operator::F64Load { memarg, .. }
+        | Operator::I32Load8S { memarg, .. }
+        | Operator::I32Load8U { memarg, .. }
+        | Operator::I32Load16S { memarg, .. }
+        | Operator::I32Load16U { memarg, .. }
+        | Operator::I64Load8S { memarg, .. }
+        | Operator::I64Load8U { memarg, .. }
+        | Operator::I64Load16S { memarg, .. }
+        | Operator::I64Load16U { memarg, .. }
+        | Operator::I64Load32S { memarg, .. }
+        | Operator::I64Load32U { memarg, .. }
+        | Operator::I32Store { memarg, .. }
+        | Operator::I64Store { memarg, .. }
+        | Operator::F32Store { memarg, .. }
+        | Operator::F64Store { memarg, .. }
+        | Operator::I32Store8 { memarg, .. }
+        | Operator::I32Store16 { memarg, .. }
+        | Operator::I64Store8 { memarg, .. }
+        | Operator::I64Store16 { memarg, .. }
+        | Operator::I64Store32 { memarg, .. }
+        | Operator::MemoryAtomicNotify { memarg, .. }
+        | Operator::MemoryAtomicWait32 { memarg, .. }
+        | Operator::MemoryAtomicWait64 { memarg, .. }
+        | Operator::I32AtomicLoad { memarg, .. }
+        | Operator::I64AtomicLoad { memarg, .. }
+        | Operator::I32AtomicLoad8U { memarg, .. }
+        | Operator::I32AtomicLoad16U { memarg, .. }
+        | Operator::I64AtomicLoad8U { memarg, .. }
+        | Operator::I64AtomicLoad16U { memarg, .. }
+        | Operator::I64AtomicLoad32U { memarg, .. }
+        | Operator::I32AtomicStore { memarg, .. }
+        | Operator::I64AtomicStore { memarg, .. }
+        | Operator::I32AtomicStore8 { memarg, .. }
+        | Operator::I32AtomicStore16 { memarg, .. }
+        | Operator::I64AtomicStore8 { memarg, .. }
+        | Operator::I64AtomicStore16 { memarg, .. }
+        | Operator::I64AtomicStore32 { memarg, .. }
+        | Operator::I32AtomicRmwAdd { memarg, .. }
+        | Operator::I64AtomicRmwAdd { memarg, .. }
+        | Operator::I32AtomicRmw8AddU { memarg, .. }
+        | Operator::I32AtomicRmw16AddU { memarg, .. }
+        | Operator::I64AtomicRmw8AddU { memarg, .. }
+        | Operator::I64AtomicRmw16AddU { memarg, .. }
+        | Operator::I64AtomicRmw32AddU { memarg, .. }
+        | Operator::I32AtomicRmwSub { memarg, .. }
+        | Operator::I64AtomicRmwSub { memarg, .. }
+        | Operator::I32AtomicRmw8SubU { memarg, .. }
+        | Operator::I32AtomicRmw16SubU { memarg, .. }
+        | Operator::I64AtomicRmw8SubU { memarg, .. }
+        | Operator::I64AtomicRmw16SubU { memarg, .. }
+        | Operator::I64AtomicRmw32SubU { memarg, .. }
+        | Operator::I32AtomicRmwAnd { memarg, .. }
+        | Operator::I64AtomicRmwAnd { memarg, .. }
+        | Operator::I32AtomicRmw8AndU { memarg, .. }
+        | Operator::I32AtomicRmw16AndU { memarg, .. }
+        | Operator::I64AtomicRmw8AndU { memarg, .. }
+        | Operator::I64AtomicRmw16AndU { memarg, .. }
+        | Operator::I64AtomicRmw32AndU { memarg, .. }
+        | Operator::I32AtomicRmwOr { memarg, .. }
+        | Operator::I64AtomicRmwOr { memarg, .. }
+        | Operator::I32AtomicRmw8OrU { memarg, .. }
+        | Operator::I32AtomicRmw16OrU { memarg, .. }
+        | Operator::I64AtomicRmw8OrU { memarg, .. }
+        | Operator::I64AtomicRmw16OrU { memarg, .. }
+        | Operator::I64AtomicRmw32OrU { memarg, .. }
+        | Operator::I32AtomicRmwXor { memarg, .. }
+        | Operator::I64AtomicRmwXor { memarg, .. }
+        | Operator::I32AtomicRmw8XorU { memarg, .. }
+        | Operator::I32AtomicRmw16XorU { memarg, .. }
+        | Operator::I64AtomicRmw8XorU { memarg, .. }
+        | Operator::I64AtomicRmw16XorU { memarg, .. }
+        | Operator::I64AtomicRmw32XorU { memarg, .. }
+        | Operator::I32AtomicRmwXchg { memarg, .. }
+        | Operator::I64AtomicRmwXchg { memarg, .. }
+        | Operator::I32AtomicRmw8XchgU { memarg, .. }
+        | Operator::I32AtomicRmw16XchgU { memarg, .. }
+        | Operator::I64AtomicRmw8XchgU { memarg, .. }
+        | Operator::I64AtomicRmw16XchgU { memarg, .. }
+        | Operator::I64AtomicRmw32XchgU { memarg, .. }
+        | Operator::I32AtomicRmwCmpxchg { memarg, .. }
+        | Operator::I64AtomicRmwCmpxchg { memarg, .. }
+        | Operator::I32AtomicRmw8CmpxchgU { memarg, .. }
+        | Operator::I32AtomicRmw16CmpxchgU { memarg, .. }
+        | Operator::I64AtomicRmw8CmpxchgU { memarg, .. }
+        | Operator::I64AtomicRmw16CmpxchgU { memarg, .. }
+        | Operator::I64AtomicRmw32CmpxchgU { memarg, .. }
+        | Operator::V128Load { memarg, .. }
+        | Operator::V128Load8x8S { memarg, .. }
+        | Operator::V128Load8x8U { memarg, .. }
+        | Operator::V128Load16x4S { memarg, .. }
+        | Operator::V128Load16x4U { memarg, .. }
+        | Operator::V128Load32x2S { memarg, .. }
+        | Operator::V128Load32x2U { memarg, .. }
+        | Operator::V128Load8Splat { memarg, .. }
+        | Operator::V128Load16Splat { memarg, .. }
+        | Operator::V128Load32Splat { memarg, .. }
+        | Operator::V128Load64Splat { memarg, .. }
+        | Operator::V128Load32Zero { memarg, .. }
+        | Operator::V128Load64Zero { memarg, .. }
+        | Operator::V128Store { memarg, .. }
+        | Operator::V128Load8Lane { memarg, .. }
+        | Operator::V128Load16Lane { memarg, .. }
+        | Operator::V128Load32Lane { memarg, .. }
+        | Operator::V128Load64Lane { memarg, .. }
+        | Operator::V128Store8Lane { memarg, .. }
+        | Operator::V128Store16Lane { memarg, .. }
+        | Operator::V128Store32Lane { memarg, .. }
+        | Operator::V128Store64Lane { memarg, .. } => Some(memarg),
+        _ => None,
+    }
+}
+
+/// Does an operator read memory, and does it write memory?
+///
+/// Every operator with a memory argument reads memory, except for the
+/// stores. Atomic read-modify-write operators both read and write it.
+fn memory_access(op: &Operator) -> (bool, bool) {
+    match *op {
+        Operator::I32Store { .. }
+        | Operator::I64Store { .. }
+        | Operator::F32Store { .. }
+        | Operator::F64Store { .. }
+        | Operator::I32Store8 { .. }
+        | Operator::I32Store16 { .. }
+        | Operator::I64Store8 { .. }
+        | Operator::I64Store16 { .. }
+        | Operator::I64Store32 { .. }
+        | Operator::I32AtomicStore { .. }
+        | Operator::I64AtomicStore { .. }
+        | Operator::I32AtomicStore8 { .. }
+        | Operator::I32AtomicStore16 { .. }
+        | Operator::I64AtomicStore8 { .. }
+        | Operator::I64AtomicStore16 { .. }
+        | Operator::I64AtomicStore32 { .. }
+        | Operator::V128Store { .. }
+        | Operator::V128Store8Lane { .. }
+        | Operator::V128Store16Lane { .. }
+        | Operator::V128Store32Lane { .. }
+        | Operator::V128Store64Lane { .. } => (false, true),
+        Operator::I32AtomicRmwAdd { .. }
+        | Operator::I64AtomicRmwAdd { .. }
+        | Operator::I32AtomicRmw8AddU { .. }
+        | Operator::I32AtomicRmw16AddU { .. }
+        | Operator::I64AtomicRmw8AddU { .. }
+        | Operator::I64AtomicRmw16AddU { .. }
+        | Operator::I64AtomicRmw32AddU { .. }
+        | Operator::I32AtomicRmwSub { .. }
+        | Operator::I64AtomicRmwSub { .. }
+        | Operator::I32AtomicRmw8SubU { .. }
+        | Operator::I32AtomicRmw16SubU { .. }
+        | Operator::I64AtomicRmw8SubU { .. }
+        | Operator::I64AtomicRmw16SubU { .. }
+        | Operator::I64AtomicRmw32SubU { .. }
+        | Operator::I32AtomicRmwAnd { .. }
+        | Operator::I64AtomicRmwAnd { .. }
+        | Operator::I32AtomicRmw8AndU { .. }
+        | Operator::I32AtomicRmw16AndU { .. }
+        | Operator::I64AtomicRmw8AndU { .. }
+        | Operator::I64AtomicRmw16AndU { .. }
+        | Operator::I64AtomicRmw32AndU { .. }
+        | Operator::I32AtomicRmwOr { .. }
+        | Operator::I64AtomicRmwOr { .. }
+        | Operator::I32AtomicRmw8OrU { .. }
+        | Operator::I32AtomicRmw16OrU { .. }
+        | Operator::I64AtomicRmw8OrU { .. }
+        | Operator::I64AtomicRmw16OrU { .. }
+        | Operator::I64AtomicRmw32OrU { .. }
+        | Operator::I32AtomicRmwXor { .. }
+        | Operator::I64AtomicRmwXor { .. }
+        | Operator::I32AtomicRmw8XorU { .. }
+        | Operator::I32AtomicRmw16XorU { .. }
+        | Operator::I64AtomicRmw8XorU { .. }
+        | Operator::I64AtomicRmw16XorU { .. }
+        | Operator::I64AtomicRmw32XorU { .. }
+        | Operator::I32AtomicRmwXchg { .. }
+        | Operator::I64AtomicRmwXchg { .. }
+        | Operator::I32AtomicRmw8XchgU { .. }
+        | Operator::I32AtomicRmw16XchgU { .. }
+        | Operator::I64AtomicRmw8XchgU { .. }
+        | Operator::I64AtomicRmw16XchgU { .. }
+        | Operator::I64AtomicRmw32XchgU { .. }
+        | Operator::I32AtomicRmwCmpxchg { .. }
+        | Operator::I64AtomicRmwCmpxchg { .. }
+        | Operator::I32AtomicRmw8CmpxchgU { .. }
+        | Operator::I32AtomicRmw16CmpxchgU { .. }
+        | Operator::I64AtomicRmw8CmpxchgU { .. }
+        | Operator::I64AtomicRmw16CmpxchgU { .. }
+        | Operator::I64AtomicRmw32CmpxchgU { .. }
+        | Operator::MemoryCopy { .. } => (true, true),
+        Operator::MemoryGrow { .. } | Operator::MemoryFill { .. } | Operator::MemoryInit { .. } => {
+            (false, true)
+        }
+        _ => (memarg(op).is_some(), false),
+    }
+}
+
+fn operands(op: &Operator, memarg: Option<MemArg>) -> Vec<Operand> {
+    if let Some(memarg) = memarg {
+        // The address is taken from the stack, so there is no base
+        // register. The natural alignment is the size of the access.
+        return vec![Operand::Memory(MemoryOperand {
+            base: None,
+            index: None,
+            scale: 1,
+            displacement: memarg.offset as i64,
+            size: Some(1 << memarg.max_align),
+        })];
+    }
+    match *op {
+        Operator::Br { relative_depth }
+        | Operator::BrIf { relative_depth }
+        | Operator::BrOnNull { relative_depth }
+        | Operator::BrOnNonNull { relative_depth } => {
+            vec![Operand::Immediate(i64::from(relative_depth))]
+        }
+        Operator::BrTable { ref targets } => targets
+            .targets()
+            .chain(Some(Ok(targets.default())))
+            .filter_map(Result::ok)
+            .map(|depth| Operand::Immediate(i64::from(depth)))
+            .collect(),
+        Operator::Call { function_index }
+        | Operator::ReturnCall { function_index }
+        | Operator::RefFunc { function_index } => {
+            vec![Operand::Address(Address::new(u64::from(function_index)))]
+        }
+        Operator::CallIndirect { type_index, .. }
+        | Operator::ReturnCallIndirect { type_index, .. }
+        | Operator::CallRef { type_index }
+        | Operator::ReturnCallRef { type_index } => {
+            vec![Operand::Immediate(i64::from(type_index))]
         }
+        Operator::LocalGet { local_index }
+        | Operator::LocalSet { local_index }
+        | Operator::LocalTee { local_index } => {
+            vec![Operand::Register(Register::Local(local_index))]
+        }
+        Operator::GlobalGet { global_index } | Operator::GlobalSet { global_index } => {
+            vec![Operand::Register(Register::Global(global_index))]
+        }
+        Operator::Throw { tag_index } => vec![Operand::Immediate(i64::from(tag_index))],
+        Operator::I32Const { value } => vec![Operand::Immediate(i64::from(value))],
+        Operator::I64Const { value } => vec![Operand::Immediate(value)],
+        // Floating point constants are given by their bit patterns.
+        Operator::F32Const { value } => vec![Operand::Immediate(i64::from(value.bits()))],
+        Operator::F64Const { value } => vec![Operand::Immediate(value.bits() as i64)],
+        _ => vec![],
+    }
+}
+
+/// The immediates of an operator, as they are written in the text format.
+fn immediates(op: &Operator, memarg: Option<MemArg>) -> String {
+    if let Some(memarg) = memarg {
+        let mut text = vec![];
+        if memarg.memory != 0 {
+            text.push(memarg.memory.to_string());
+        }
+        if memarg.offset != 0 {
+            text.push(format!("offset={}", memarg.offset));
+        }
+        if memarg.align != memarg.max_align {
+            text.push(format!("align={}", 1u64 << memarg.align));
+        }
+        if let Some(lane) = lane(op) {
+            text.push(lane.to_string());
+        }
+        return text.join(" ");
+    }
+    if let Some(lane) = lane(op) {
+        return lane.to_string();
+    }
+    let nonzero = |index: u32| {
+        if index == 0 {
+            String::new()
+        } else {
+            index.to_string()
+        }
+    };
+    match *op {
+        Operator::Block { blockty }
+        | Operator::Loop { blockty }
+        | Operator::If { blockty }
+        | Operator::Try { blockty } => block_type(blockty),
+        Operator::TryTable { ref try_table } => {
+            let mut text = vec![block_type(try_table.ty)];
+            text.extend(try_table.catches.iter().map(|catch| match *catch {
+                Catch::One { tag, label } => format!("(catch {} {})", tag, label),
+                Catch::OneRef { tag, label } => format!("(catch_ref {} {})", tag, label),
+                Catch::All { label } => format!("(catch_all {})", label),
+                Catch::AllRef { label } => format!("(catch_all_ref {})", label),
+            }));
+            text.retain(|t| !t.is_empty());
+            text.join(" ")
+        }
+        Operator::Br { relative_depth }
+        | Operator::BrIf { relative_depth }
+        | Operator::BrOnNull { relative_depth }
+        | Operator::BrOnNonNull { relative_depth }
+        | Operator::Rethrow { relative_depth }
+        | Operator::Delegate { relative_depth } => relative_depth.to_string(),
+        Operator::BrTable { ref targets } => targets
+            .targets()
+            .chain(Some(Ok(targets.default())))
+            .filter_map(Result::ok)
+            .map(|depth| depth.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        Operator::Call { function_index }
+        | Operator::ReturnCall { function_index }
+        | Operator::RefFunc { function_index } => function_index.to_string(),
+        Operator::CallIndirect {
+            type_index,
+            table_index,
+        }
+        | Operator::ReturnCallIndirect {
+            type_index,
+            table_index,
+        } => match table_index {
+            0 => format!("(type {})", type_index),
+            _ => format!("{} (type {})", table_index, type_index),
+        },
+        Operator::CallRef { type_index } | Operator::ReturnCallRef { type_index } => {
+            type_index.to_string()
+        }
+        Operator::LocalGet { local_index }
+        | Operator::LocalSet { local_index }
+        | Operator::LocalTee { local_index } => local_index.to_string(),
+        Operator::GlobalGet { global_index } | Operator::GlobalSet { global_index } => {
+            global_index.to_string()
+        }
+        Operator::Throw { tag_index } | Operator::Catch { tag_index } => tag_index.to_string(),
+        Operator::I32Const { value } => value.to_string(),
+        Operator::I64Const { value } => value.to_string(),
+        Operator::F32Const { value } => float(f32::from_bits(value.bits())),
+        Operator::F64Const { value } => float(f64::from_bits(value.bits())),
+        Operator::V128Const { ref value } => {
+            let lanes = value
+                .bytes()
+                .chunks(4)
+                .map(|lane| {
+                    format!(
+                        "0x{:02x}{:02x}{:02x}{:02x}",
+                        lane[3], lane[2], lane[1], lane[0]
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("i32x4 {}", lanes.join(" "))
+        }
+        Operator::I8x16Shuffle { ref lanes } => lanes
+            .iter()
+            .map(|lane| lane.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        Operator::MemorySize { mem }
+        | Operator::MemoryGrow { mem }
+        | Operator::MemoryFill { mem } => nonzero(mem),
+        Operator::MemoryInit { data_index, mem } => match mem {
+            0 => data_index.to_string(),
+            _ => format!("{} {}", mem, data_index),
+        },
+        Operator::DataDrop { data_index } => data_index.to_string(),
+        Operator::MemoryCopy { dst_mem, src_mem } => match (dst_mem, src_mem) {
+            (0, 0) => String::new(),
+            _ => format!("{} {}", dst_mem, src_mem),
+        },
+        Operator::TableInit { elem_index, table } => match table {
+            0 => elem_index.to_string(),
+            _ => format!("{} {}", table, elem_index),
+        },
+        Operator::ElemDrop { elem_index } => elem_index.to_string(),
+        Operator::TableCopy {
+            dst_table,
+            src_table,
+        } => match (dst_table, src_table) {
+            (0, 0) => String::new(),
+            _ => format!("{} {}", dst_table, src_table),
+        },
+        Operator::TableFill { table }
+        | Operator::TableGet { table }
+        | Operator::TableSet { table }
+        | Operator::TableGrow { table }
+        | Operator::TableSize { table } => table.to_string(),
+        Operator::TypedSelect { ty } => format!("(result {})", val_type(ty)),
+        Operator::TypedSelectMulti { ref tys } => {
+            let tys = tys.iter().map(|&ty| val_type(ty)).collect::<Vec<_>>();
+            format!("(result {})", tys.join(" "))
+        }
+        Operator::RefNull { hty } => heap_type(hty),
+        _ => String::new(),
+    }
+}
+
+/// The lane that is accessed by a SIMD operator.
+fn lane(op: &Operator) -> Option<u8> {
+    match *op {
+        Operator::V128Load8Lane { lane, .. }
+        | Operator::V128Load16Lane { lane, .. }
+        | Operator::V128Load32Lane { lane, .. }
+        | Operator::V128Load64Lane { lane, .. }
+        | Operator::V128Store8Lane { lane, .. }
+        | Operator::V128Store16Lane { lane, .. }
+        | Operator::V128Store32Lane { lane, .. }
+        | Operator::V128Store64Lane { lane, .. }
+        | Operator::I8x16ExtractLaneS { lane }
+        | Operator::I8x16ExtractLaneU { lane }
+        | Operator::I8x16ReplaceLane { lane }
+        | Operator::I16x8ExtractLaneS { lane }
+        | Operator::I16x8ExtractLaneU { lane }
+        | Operator::I16x8ReplaceLane { lane }
+        | Operator::I32x4ExtractLane { lane }
+        | Operator::I32x4ReplaceLane { lane }
+        | Operator::I64x2ExtractLane { lane }
+        | Operator::I64x2ReplaceLane { lane }
+        | Operator::F32x4ExtractLane { lane }
+        | Operator::F32x4ReplaceLane { lane }
+        | Operator::F64x2ExtractLane { lane }
+        | Operator::F64x2ReplaceLane { lane } => Some(lane),
+        _ => None,
+    }
+}
+
+/// A floating point constant, as it is written in the text format.
+fn float<F: Copy + Into<f64> + fmt::Display>(value: F) -> String {
+    let sign = if value.into().is_sign_negative() {
+        "-"
+    } else {
+        ""
+    };
+    if value.into().is_nan() {
+        format!("{}nan", sign)
+    } else if value.into().is_infinite() {
+        format!("{}inf", sign)
+    } else {
+        value.to_string()
+    }
+}
+
+/// The type of a block, which is empty when the block has no results.
+fn block_type(ty: BlockType) -> String {
+    match ty {
+        BlockType::Empty => String::new(),
+        BlockType::Type(ty) => format!("(result {})", val_type(ty)),
+        BlockType::FuncType(index) => format!("(type {})", index),
+    }
+}
+
+fn val_type(ty: ValType) -> String {
+    match ty {
+        ValType::Ref(ty) => ref_type(ty),
+        _ => ty.to_string(),
+    }
+}
+
+fn ref_type(ty: RefType) -> String {
+    let null = if ty.is_nullable() { "null " } else { "" };
+    match ty.heap_type() {
+        HeapType::Concrete(index) => format!("(ref {}{})", null, type_index(index)),
+        HeapType::Exact(index) => format!("(ref {}(exact {}))", null, type_index(index)),
+        // The abstract types are already shown as in the text format.
+        HeapType::Abstract { .. } => ty.to_string(),
+    }
+}
+
+fn heap_type(ty: HeapType) -> String {
+    match ty {
+        HeapType::Concrete(index) => type_index(index),
+        HeapType::Exact(index) => format!("(exact {})", type_index(index)),
+        HeapType::Abstract { ty, .. } => match ty {
+            AbstractHeapType::Func => "func",
+            AbstractHeapType::Extern => "extern",
+            AbstractHeapType::Any => "any",
+            AbstractHeapType::None => "none",
+            AbstractHeapType::NoExtern => "noextern",
+            AbstractHeapType::NoFunc => "nofunc",
+            AbstractHeapType::Eq => "eq",
+            AbstractHeapType::Struct => "struct",
+            AbstractHeapType::Array => "array",
+            AbstractHeapType::I31 => "i31",
+            AbstractHeapType::Exn => "exn",
+            AbstractHeapType::NoExn => "noexn",
+            AbstractHeapType::Cont => "cont",
+            AbstractHeapType::NoCont => "nocont",
+        }
+        .to_owned(),
+    }
+}
+
+fn type_index(index: UnpackedIndex) -> String {
+    match index.as_module_index() {
+        Some(index) => index.to_string(),
+        None => index.to_string(),
     }
 }
 
 impl Function<WasmInstruction> {
     /// Create a function from WebAssembly bytecode.
-    pub fn from_wasm(symbol: Symbol, instructions: &Instructions) -> Function<WasmInstruction> {
-        let is = instructions
-            .elements()
+    ///
+    /// The code is the expression of a function body, without its local
+    /// declarations, so it ends with an `end`. Instructions are
+    /// addressed by their position within the code.
+    ///
+    /// This returns `None` if the code can not be decoded.
+    pub fn from_wasm(symbol: Symbol, code: &[u8]) -> Option<Function<WasmInstruction>> {
+        Function::from_wasm_in_module(
+            symbol,
+            OperatorsReader::new(BinaryReader::new(code, 0)),
+            &IndirectCalls::default(),
+            None,
+            None,
+        )
+    }
+
+    /// Create a function from WebAssembly bytecode, given the functions
+    /// that may be called indirectly and the declaration of the function,
+    /// which also names its local variables.
+    ///
+    /// Instructions are addressed by their byte offset less `base`, or
+    /// by their position if there is no `base`.
+    fn from_wasm_in_module(
+        symbol: Symbol,
+        mut operators: OperatorsReader,
+        indirect_calls: &IndirectCalls,
+        declaration: Option<&WasmDeclaration>,
+        base: Option<u64>,
+    ) -> Option<Function<WasmInstruction>> {
+        let mut is = vec![];
+        while !operators.eof() {
+            let offset = operators.original_position();
+            let op = operators.read().ok()?;
+            let address = match base {
+                Some(base) => offset as u64 - base,
+                None => is.len() as u64,
+            };
+            let encoded_len = operators.original_position() - offset;
+            let mut inst = WasmInstruction::new(Address::new(address), encoded_len, &op);
+            match op {
+                Operator::CallIndirect {
+                    type_index,
+                    table_index,
+                }
+                | Operator::ReturnCallIndirect {
+                    type_index,
+                    table_index,
+                } => {
+                    inst.call_targets = indirect_calls.table_candidates(table_index, type_index);
+                }
+                Operator::CallRef { type_index } | Operator::ReturnCallRef { type_index } => {
+                    inst.call_targets = indirect_calls.reference_candidates(type_index);
+                }
+                Operator::LocalGet { local_index }
+                | Operator::LocalSet { local_index }
+                | Operator::LocalTee { local_index } => {
+                    inst.local_name = declaration
+                        .and_then(|d| d.local_names.get(&local_index))
+                        .cloned();
+                }
+                _ => {}
+            }
+            is.push(inst);
+        }
+        Some(Function::new(symbol, resolve_control_flow(is)))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Try,
+    TryTable,
+}
+
+/// A block, loop, if or try, or the body of the function itself.
+struct Frame {
+    kind: FrameKind,
+    start: usize,
+    /// The frame that encloses this one.
+    parent: usize,
+    else_idx: Option<usize>,
+    end: Option<usize>,
+    /// The `catch` clauses of a legacy `try`, and whether each one
+    /// catches every exception.
+    catches: Vec<(usize, bool)>,
+    /// The frame that a legacy `try` delegates its exceptions to.
+    delegate: Option<Option<usize>>,
+    /// The frames that the `catch` clauses of a `try_table` branch to,
+    /// and whether each one catches every exception.
+    handlers: Vec<(Option<usize>, bool)>,
+}
+
+/// The frame that a branch refers to, given the stack of enclosing frames.
+fn label(stack: &[usize], depth: u32) -> Option<usize> {
+    stack
+        .len()
+        .checked_sub(depth as usize + 1)
+        .map(|level| stack[level])
+}
+
+/// Find the targets of structured control flow instructions.
+///
+/// Branches refer to an enclosing block by its depth, so this keeps
+/// a stack of the enclosing frames while walking the function body.
+/// A branch to a `loop` goes to the start of the loop. A branch to
+/// anything else goes to its `end`. A branch to the function body
+/// itself goes to the final `end`, which returns.
+///
+/// Calls and throws within a `try` body also find the handlers that
+/// may catch their exceptions.
+fn resolve_control_flow(mut is: Vec<WasmInstruction>) -> Vec<WasmInstruction> {
+    let mut frames = vec![Frame {
+        kind: FrameKind::Function,
+        start: 0,
+        parent: 0,
+        else_idx: None,
+        end: None,
+        catches: vec![],
+        delegate: None,
+        handlers: vec![],
+    }];
+    let mut stack = vec![0];
+    // The frame that each instruction opens or belongs to, and the
+    // frames that each branch refers to. A label that is out of range
+    // is kept as `None` so that the cases of a `br_table` stay in order.
+    let mut frame_of = vec![0; is.len()];
+    let mut labels = vec![vec![]; is.len()];
+    for (idx, inst) in is.iter().enumerate() {
+        let kind = match inst.control {
+            Control::Block => Some(FrameKind::Block),
+            Control::Loop => Some(FrameKind::Loop),
+            Control::If => Some(FrameKind::If),
+            Control::Try => Some(FrameKind::Try),
+            Control::TryTable(_) => Some(FrameKind::TryTable),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            // The labels of a `try_table` are outside of it.
+            let handlers = match inst.control {
+                Control::TryTable(ref catches) => catches
+                    .iter()
+                    .map(|&(depth, all)| (label(&stack, depth), all))
+                    .collect(),
+                _ => vec![],
+            };
+            frames.push(Frame {
+                kind,
+                start: idx,
+                parent: *stack.last().unwrap_or(&0),
+                else_idx: None,
+                end: None,
+                catches: vec![],
+                delegate: None,
+                handlers,
+            });
+            stack.push(frames.len() - 1);
+        }
+        let top = *stack.last().unwrap_or(&0);
+        frame_of[idx] = top;
+        match inst.control {
+            Control::Else => frames[top].else_idx = Some(idx),
+            Control::Catch(all) => frames[top].catches.push((idx, all)),
+            Control::End => {
+                frames[top].end = Some(idx);
+                stack.pop();
+            }
+            Control::Delegate(depth) => {
+                frames[top].end = Some(idx);
+                stack.pop();
+                frames[top].delegate = Some(label(&stack, depth));
+            }
+            Control::Br(depth) | Control::BrIf(depth) => {
+                labels[idx] = vec![label(&stack, depth)];
+            }
+            Control::BrTable(ref table, default) => {
+                labels[idx] = table
+                    .iter()
+                    .chain(Some(&default))
+                    .map(|&depth| label(&stack, depth))
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    let address = |idx: usize| is.get(idx).map(instruction::Instruction::address);
+    let label_target = |frame: &Option<usize>| {
+        let frame = &frames[(*frame)?];
+        match frame.kind {
+            FrameKind::Loop => address(frame.start),
+            _ => frame.end.and_then(address),
+        }
+    };
+    // Walk out through the frames that enclose an instruction to find
+    // the handlers for its exceptions, until one catches everything.
+    // The `catch` clauses of a `try` only cover its body.
+    let exception_handlers = |idx: usize| {
+        let mut handlers = vec![];
+        let mut frame = frame_of[idx];
+        let mut delegated = false;
+        'walk: loop {
+            let f = &frames[frame];
+            let in_body = delegated || f.catches.first().is_none_or(|&(catch, _)| idx < catch);
+            delegated = false;
+            let clauses = match f.kind {
+                FrameKind::Function => break,
+                FrameKind::Try if in_body => match f.delegate {
+                    Some(Some(delegate)) => {
+                        frame = delegate;
+                        delegated = true;
+                        continue;
+                    }
+                    Some(None) => break,
+                    None => f
+                        .catches
+                        .iter()
+                        .map(|&(catch, all)| (address(catch + 1), all))
+                        .collect(),
+                },
+                FrameKind::TryTable => f
+                    .handlers
+                    .iter()
+                    .map(|&(ref label, all)| (label_target(label), all))
+                    .collect(),
+                _ => vec![],
+            };
+            for (handler, all) in clauses {
+                if let Some(handler) = handler {
+                    if !handlers.contains(&handler) {
+                        handlers.push(handler);
+                    }
+                }
+                if all {
+                    break 'walk;
+                }
+            }
+            frame = f.parent;
+        }
+        handlers
+    };
+    let mut resolved = Vec::with_capacity(is.len());
+    for (idx, inst) in is.iter().enumerate() {
+        let frame = &frames[frame_of[idx]];
+        let target = match inst.control {
+            // Without an `else`, a false condition goes to the `end`.
+            Control::If => match frame.else_idx {
+                Some(else_idx) => address(else_idx + 1),
+                None => frame.end.and_then(address),
+            },
+            Control::Else | Control::Catch(_) => frame.end.and_then(address),
+            Control::Br(_) | Control::BrIf(_) => labels[idx].first().and_then(label_target),
+            _ => None,
+        };
+        let branch_targets = match inst.control {
+            Control::BrTable(ref table, _) => labels[idx]
+                .iter()
+                .enumerate()
+                .filter_map(|(case, frame)| {
+                    label_target(frame).map(|address| BranchTarget {
+                        address,
+                        case: if case < table.len() {
+                            Some(case as i64)
+                        } else {
+                            None
+                        },
+                    })
+                })
+                .collect(),
+            _ => vec![],
+        };
+        let handlers = if inst.can_throw() {
+            exception_handlers(idx)
+        } else {
+            vec![]
+        };
+        resolved.push((target, branch_targets, handlers, frames[0].end == Some(idx)));
+    }
+    for (inst, (target, branch_targets, handlers, is_function_end)) in is.iter_mut().zip(resolved) {
+        inst.target = target;
+        inst.branch_targets = branch_targets;
+        inst.exception_handlers = handlers;
+        inst.is_function_end = is_function_end;
+    }
+    is
+}
+
+/// What is known about the functions that may be called indirectly
+/// within a module.
+#[derive(Default)]
+struct IndirectCalls {
+    /// Each type, or `None` if it is not a function type.
+    types: Vec<Option<FuncType>>,
+    /// The type index of each function in the function index space.
+    function_types: Vec<u32>,
+    /// The functions placed in each table by active element segments.
+    tables: HashMap<u32, Vec<u32>>,
+    /// Whether each global holds a reference.
+    reference_globals: Vec<bool>,
+    /// The tables that may hold functions other than those placed by
+    /// active element segments, as they are imported, exported or
+    /// changed by the code.
+    open_tables: HashSet<u32>,
+    /// The functions in any element segment. Every function that is
+    /// used by a `ref.func` within a function body must be declared by
+    /// an element segment.
+    referenced: Vec<u32>,
+    /// Whether a function reference may come from the host, through an
+    /// import or export that involves a reference type.
+    host_references: bool,
+}
+
+impl IndirectCalls {
+    /// The possible targets of a `call_indirect` through a table, or
+    /// nothing if the table is open.
+    fn table_candidates(&self, table_index: u32, type_index: u32) -> Vec<Address> {
+        if self.open_tables.contains(&table_index) {
+            return vec![];
+        }
+        let table = self.tables.get(&table_index);
+        self.candidates(table.map_or(&[], |t| t.as_slice()), type_index)
+    }
+
+    /// The possible targets of a `call_ref`, or nothing if a function
+    /// reference may come from the host.
+    fn reference_candidates(&self, type_index: u32) -> Vec<Address> {
+        if self.host_references {
+            return vec![];
+        }
+        self.candidates(&self.referenced, type_index)
+    }
+
+    /// Does a function type take or return a reference?
+    fn has_references(&self, type_index: u32) -> bool {
+        match self.types.get(type_index as usize) {
+            Some(Some(ty)) => ty
+                .params()
+                .iter()
+                .chain(ty.results())
+                .any(ValType::is_reference_type),
+            _ => true,
+        }
+    }
+
+    /// Note the tables that the code of a function changes.
+    fn scan_table_writes(&mut self, mut operators: OperatorsReader) -> Option<()> {
+        while !operators.eof() {
+            match operators.read().ok()? {
+                Operator::TableSet { table }
+                | Operator::TableInit { table, .. }
+                | Operator::TableCopy {
+                    dst_table: table, ..
+                }
+                | Operator::TableGrow { table }
+                | Operator::TableFill { table } => {
+                    self.open_tables.insert(table);
+                }
+                _ => {}
+            }
+        }
+        Some(())
+    }
+
+    /// The functions of a type from among the given functions.
+    ///
+    /// Types are compared structurally, as a module may have several
+    /// identical types.
+    fn candidates(&self, functions: &[u32], type_index: u32) -> Vec<Address> {
+        let ty = match self.types.get(type_index as usize) {
+            Some(Some(ty)) => ty,
+            _ => return vec![],
+        };
+        functions
             .iter()
-            .enumerate()
-            .map(|(idx, insn)| WasmInstruction::new(idx as u64, insn.clone()))
-            .collect::<Vec<_>>();
-        Function::new(symbol, is)
+            .filter(|&&function| {
+                self.function_types
+                    .get(function as usize)
+                    .and_then(|&t| self.types.get(t as usize))
+                    .is_some_and(|f| f.as_ref() == Some(ty))
+            })
+            .map(|&function| Address::new(u64::from(function)))
+            .collect()
+    }
+}
+
+/// The functions in an element segment.
+///
+/// Items that are not a function, such as a `ref.null`, are skipped.
+fn element_functions(items: ElementItems) -> Vec<u32> {
+    match items {
+        ElementItems::Functions(functions) => {
+            functions.into_iter().filter_map(Result::ok).collect()
+        }
+        ElementItems::Expressions(_, expressions) => expressions
+            .into_iter()
+            .filter_map(
+                |expression| match expression.ok()?.get_operators_reader().read().ok()? {
+                    Operator::RefFunc { function_index } => Some(function_index),
+                    _ => None,
+                },
+            )
+            .collect(),
+    }
+}
+
+/// The names of functions and local variables from the name section.
+#[derive(Default)]
+struct Names {
+    functions: HashMap<u32, String>,
+    locals: HashMap<u32, HashMap<u32, String>>,
+}
+
+impl Names {
+    /// Parse the function and local names from the custom "name" section.
+    ///
+    /// The name section is only for debugging, so a name that can not be
+    /// parsed is skipped rather than rejecting the module.
+    fn new(reader: NameSectionReader) -> Self {
+        let mut names = Names::default();
+        for subsection in reader.into_iter().filter_map(Result::ok) {
+            match subsection {
+                Name::Function(map) => {
+                    for naming in map.into_iter().filter_map(Result::ok) {
+                        names.functions.insert(naming.index, naming.name.to_owned());
+                    }
+                }
+                Name::Local(map) => {
+                    for function in map.into_iter().filter_map(Result::ok) {
+                        let locals = names.locals.entry(function.index).or_default();
+                        for naming in function.names.into_iter().filter_map(Result::ok) {
+                            locals.insert(naming.index, naming.name.to_owned());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        names
     }
 }
 
 impl Module<WasmInstruction> {
     /// Load a module from a binary WebAssembly file.
     pub fn from_wasm_file<P: AsRef<Path>>(path: P) -> Option<Self> {
-        if let Ok(m) = deserialize_file(path) {
-            let mut symbol_table = HashMap::<Address, &str>::new();
-            if let Some(exports) = m.export_section() {
-                for export in exports.entries() {
-                    if let Internal::Function(index) = *export.internal() {
-                        symbol_table.insert(Address::new(u64::from(index)), export.field());
+        Module::from_wasm_file_with_addressing(path, WasmAddressing::Ordinal)
+    }
+
+    /// Load a module from a binary WebAssembly file, choosing how the
+    /// addresses of instructions are assigned.
+    pub fn from_wasm_file_with_addressing<P: AsRef<Path>>(
+        path: P,
+        addressing: WasmAddressing,
+    ) -> Option<Self> {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| Module::from_wasm_bytes_with_addressing(&bytes, addressing))
+    }
+
+    /// Load a module from the contents of a binary WebAssembly file.
+    pub fn from_wasm_bytes(bytes: &[u8]) -> Option<Self> {
+        Module::from_wasm_bytes_with_addressing(bytes, WasmAddressing::Ordinal)
+    }
+
+    /// Load a module from the contents of a binary WebAssembly file,
+    /// choosing how the addresses of instructions are assigned.
+    ///
+    /// The module uses the WebAssembly function index space, where
+    /// imported functions come first, followed by the functions defined
+    /// in the code section. The address of each function is its index.
+    ///
+    /// Functions are named from the name section when it is present,
+    /// falling back to their export or import names.
+    pub fn from_wasm_bytes_with_addressing(
+        bytes: &[u8],
+        addressing: WasmAddressing,
+    ) -> Option<Self> {
+        let mut indirect_calls = IndirectCalls::default();
+        let mut imported_tables = 0;
+        let mut names = Names::default();
+        let mut import_names = vec![];
+        let mut export_names = HashMap::<u32, &str>::new();
+        let mut code = 0;
+        let mut bodies = vec![];
+        for payload in Parser::new(0).parse_all(bytes) {
+            match payload.ok()? {
+                Payload::TypeSection(types) => {
+                    for group in types {
+                        for ty in group.ok()?.into_types() {
+                            indirect_calls.types.push(match ty.composite_type.inner {
+                                CompositeInnerType::Func(f) => Some(f),
+                                _ => None,
+                            });
+                        }
                     }
                 }
-            }
-            if let Some(imports) = m.import_section() {
-                for import in imports.entries() {
-                    if let External::Function(index) = *import.external() {
-                        symbol_table.insert(Address::new(u64::from(index)), import.field());
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        let import = import.ok()?;
+                        match import.ty {
+                            TypeRef::Func(type_index) | TypeRef::FuncExact(type_index) => {
+                                indirect_calls.host_references |=
+                                    indirect_calls.has_references(type_index);
+                                indirect_calls.function_types.push(type_index);
+                                import_names.push(import.name);
+                            }
+                            TypeRef::Table(_) => {
+                                indirect_calls.open_tables.insert(imported_tables);
+                                indirect_calls.host_references = true;
+                                imported_tables += 1;
+                            }
+                            TypeRef::Global(global) => {
+                                let is_reference = global.content_type.is_reference_type();
+                                indirect_calls.reference_globals.push(is_reference);
+                                indirect_calls.host_references |= is_reference;
+                            }
+                            TypeRef::Tag(tag) => {
+                                indirect_calls.host_references |=
+                                    indirect_calls.has_references(tag.func_type_idx);
+                            }
+                            TypeRef::Memory(_) => {}
+                        }
                     }
                 }
+                Payload::FunctionSection(functions) => {
+                    for type_index in functions {
+                        indirect_calls.function_types.push(type_index.ok()?);
+                    }
+                }
+                Payload::GlobalSection(globals) => {
+                    for global in globals {
+                        let content_type = global.ok()?.ty.content_type;
+                        indirect_calls
+                            .reference_globals
+                            .push(content_type.is_reference_type());
+                    }
+                }
+                Payload::ElementSection(elements) => {
+                    for element in elements {
+                        let element = element.ok()?;
+                        let functions = element_functions(element.items);
+                        if let ElementKind::Active { table_index, .. } = element.kind {
+                            indirect_calls
+                                .tables
+                                .entry(table_index.unwrap_or(0))
+                                .or_default()
+                                .extend(&functions);
+                        }
+                        indirect_calls.referenced.extend(functions);
+                    }
+                }
+                Payload::ExportSection(exports) => {
+                    for export in exports {
+                        let export = export.ok()?;
+                        match export.kind {
+                            ExternalKind::Func | ExternalKind::FuncExact => {
+                                export_names.insert(export.index, export.name);
+                                let type_index =
+                                    indirect_calls.function_types.get(export.index as usize);
+                                indirect_calls.host_references |=
+                                    type_index.is_none_or(|&t| indirect_calls.has_references(t));
+                            }
+                            ExternalKind::Table => {
+                                indirect_calls.open_tables.insert(export.index);
+                                indirect_calls.host_references = true;
+                            }
+                            ExternalKind::Global => {
+                                indirect_calls.host_references |= indirect_calls
+                                    .reference_globals
+                                    .get(export.index as usize)
+                                    .is_none_or(|&is_reference| is_reference);
+                            }
+                            ExternalKind::Tag => indirect_calls.host_references = true,
+                            ExternalKind::Memory => {}
+                        }
+                    }
+                }
+                Payload::CodeSectionStart { range, .. } => code = range.start,
+                Payload::CodeSectionEntry(body) => {
+                    indirect_calls.scan_table_writes(body.get_operators_reader().ok()?)?;
+                    bodies.push(body);
+                }
+                Payload::CustomSection(section) => {
+                    if let KnownCustom::Name(reader) = section.as_known() {
+                        names = Names::new(reader);
+                    }
+                }
+                _ => {}
             }
-            if let Some(code) = m.code_section() {
-                let functions = code
-                    .bodies()
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, body)| {
-                        let addr = Address::new(idx as u64);
-                        let name = symbol_table.get(&addr).copied();
-                        Function::from_wasm(Symbol::new(addr, name), body.code())
-                    })
-                    .collect();
-                Some(Module { functions })
-            } else {
-                None
-            }
-        } else {
-            None
         }
+        for functions in indirect_calls.tables.values_mut() {
+            functions.sort_unstable();
+            functions.dedup();
+        }
+        indirect_calls.referenced.sort_unstable();
+        indirect_calls.referenced.dedup();
+
+        let imported_functions = import_names
+            .iter()
+            .enumerate()
+            .map(|(index, &field)| {
+                let name = names.functions.get(&(index as u32)).map_or(field, |n| n);
+                Symbol::new(Address::new(index as u64), Some(name))
+            })
+            .collect::<Vec<_>>();
+        let import_count = imported_functions.len() as u32;
+        let base = match addressing {
+            WasmAddressing::Ordinal => None,
+            WasmAddressing::ModuleOffset => Some(0),
+            WasmAddressing::CodeSectionOffset => Some(code as u64),
+        };
+        let mut functions = vec![];
+        let mut declarations = HashMap::new();
+        for (idx, body) in bodies.iter().enumerate() {
+            let index = import_count + idx as u32;
+            let name = names
+                .functions
+                .get(&index)
+                .map(|n| n.as_str())
+                .or_else(|| export_names.get(&index).copied());
+            let symbol = Symbol::new(Address::new(u64::from(index)), name);
+            let type_index = *indirect_calls.function_types.get(index as usize)?;
+            let (params, results) = match indirect_calls.types.get(type_index as usize) {
+                Some(Some(ty)) => (ty.params().to_vec(), ty.results().to_vec()),
+                _ => (vec![], vec![]),
+            };
+            let declaration = WasmDeclaration {
+                type_index,
+                params,
+                results,
+                locals: body
+                    .get_locals_reader()
+                    .ok()?
+                    .into_iter()
+                    .collect::<Result<_, _>>()
+                    .ok()?,
+                local_names: names.locals.remove(&index).unwrap_or_default(),
+            };
+            functions.push(Function::from_wasm_in_module(
+                symbol,
+                body.get_operators_reader().ok()?,
+                &indirect_calls,
+                Some(&declaration),
+                base,
+            )?);
+            declarations.insert(Address::new(u64::from(index)), declaration);
+        }
+        Some(Module {
+            functions,
+            imported_functions,
+            declarations,
+        })
     }
 }
 
 impl fmt::Display for WasmInstruction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.insn.fmt(f)
+        if self.immediates.is_empty() {
+            f.write_str(self.mnemonic)
+        } else {
+            write!(f, "{} {}", self.mnemonic, self.immediates)
+        }
+    }
+}
+
+/// Render a [`Function`] or [`Module`] of WebAssembly instructions in
+/// the [text format], laid out like the output of `wasm2wat`.
+///
+/// Instructions are indented by their nesting within `block`, `loop`,
+/// `if` and `try` blocks, and each block is annotated with its label
+/// so that branches can be matched up with their targets. Functions
+/// and local variables are referred to by name when they are named.
+///
+/// The functions of a [`Module`] are printed with their type,
+/// parameters, results and local variables, which are not known for a
+/// function by itself. Only the functions of a module are printed. Its
+/// other sections, such as imports and exports, are left out.
+///
+/// The output is produced by the `fmt::Display` implementation, so
+/// `Wat::new(&function).to_string()` gives the whole function.
+///
+/// [text format]: https://webassembly.github.io/spec/core/text/index.html
+pub struct Wat<'a> {
+    functions: &'a [Function<WasmInstruction>],
+    imported_functions: &'a [Symbol],
+    declarations: Option<&'a HashMap<Address, WasmDeclaration>>,
+    is_module: bool,
+}
+
+impl<'a> Wat<'a> {
+    /// Construct a `Wat` renderer for a `Function`.
+    pub fn new(function: &'a Function<WasmInstruction>) -> Self {
+        Wat {
+            functions: slice::from_ref(function),
+            imported_functions: &[],
+            declarations: None,
+            is_module: false,
+        }
+    }
+
+    /// Construct a `Wat` renderer for all of the functions of a `Module`.
+    pub fn module(module: &'a Module<WasmInstruction>) -> Self {
+        Wat {
+            functions: &module.functions,
+            imported_functions: &module.imported_functions,
+            declarations: Some(&module.declarations),
+            is_module: true,
+        }
+    }
+}
+
+/// A name in the text format.
+///
+/// Characters that may not appear in an identifier are replaced by `_`,
+/// as `wasm2wat` does.
+fn identifier(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '0'..='9' | 'a'..='z' | 'A'..='Z' => c,
+            '!' | '#' | '$' | '%' | '&' | '\'' | '*' | '+' | '-' | '.' | '/' | ':' | '<' | '='
+            | '>' | '?' | '@' | '\\' | '^' | '_' | '`' | '|' | '~' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    format!("${}", name)
+}
+
+/// The `param` or `local` declarations of a sequence of local variables.
+///
+/// A named local is declared by itself, while consecutive unnamed locals
+/// share a declaration.
+fn locals(
+    keyword: &str,
+    first: u32,
+    types: impl Iterator<Item = ValType>,
+    names: &HashMap<u32, String>,
+) -> Vec<String> {
+    let mut declarations = vec![];
+    let mut unnamed = vec![];
+    for (index, ty) in (first..).zip(types) {
+        match names.get(&index) {
+            Some(name) => {
+                if !unnamed.is_empty() {
+                    declarations.push(format!("({} {})", keyword, unnamed.join(" ")));
+                    unnamed.clear();
+                }
+                declarations.push(format!(
+                    "({} {} {})",
+                    keyword,
+                    identifier(name),
+                    val_type(ty)
+                ));
+            }
+            None => unnamed.push(val_type(ty)),
+        }
+    }
+    if !unnamed.is_empty() {
+        declarations.push(format!("({} {})", keyword, unnamed.join(" ")));
+    }
+    declarations
+}
+
+/// The lines of text for a function, indented by `indent` levels.
+///
+/// An unnamed function is identified by its `index` in a module.
+fn function_lines(
+    function: &Function<WasmInstruction>,
+    declaration: Option<&WasmDeclaration>,
+    index: Option<usize>,
+    function_names: &HashMap<Address, &str>,
+    indent: usize,
+    lines: &mut Vec<String>,
+) {
+    let pad = |level: usize| "  ".repeat(indent + level);
+    let mut header = vec!["(func".to_owned()];
+    match (&function.symbol.name, index) {
+        (Some(name), _) => header.push(identifier(name)),
+        (None, Some(index)) => header.push(format!("(;{};)", index)),
+        (None, None) => {}
+    }
+    if let Some(d) = declaration {
+        header.push(format!("(type {})", d.type_index));
+        header.extend(locals("param", 0, d.params.iter().copied(), &d.local_names));
+        if !d.results.is_empty() {
+            let results = d.results.iter().map(|&ty| val_type(ty));
+            header.push(format!(
+                "(result {})",
+                results.collect::<Vec<_>>().join(" ")
+            ));
+        }
+    }
+    lines.push(format!("{}{}", pad(0), header.join(" ")));
+    if let Some(d) = declaration {
+        let types = d
+            .locals
+            .iter()
+            .flat_map(|&(count, ty)| iter::repeat_n(ty, count as usize));
+        let declarations = locals("local", d.params.len() as u32, types, &d.local_names);
+        if !declarations.is_empty() {
+            lines.push(format!("{}{}", pad(1), declarations.join(" ")));
+        }
+    }
+
+    // The number of blocks that are open, which is also the label of
+    // the innermost block. The function body itself is label 0.
+    let mut level = 0usize;
+    let label = |level: usize, depth: u32| match level.checked_sub(depth as usize) {
+        Some(label) => format!("{} (;@{};)", depth, label),
+        None => depth.to_string(),
+    };
+    for inst in &function.instructions {
+        if inst.is_function_end {
+            continue;
+        }
+        let mut text = inst.to_string();
+        let mut line_level = level;
+        match inst.control {
+            Control::Block | Control::Loop | Control::If | Control::Try | Control::TryTable(_) => {
+                level += 1;
+                text = format!("{}  ;; label = @{}", text, level);
+            }
+            Control::Else | Control::Catch(_) => line_level = level.saturating_sub(1),
+            Control::End => {
+                level = level.saturating_sub(1);
+                line_level = level;
+            }
+            Control::Delegate(depth) => {
+                level = level.saturating_sub(1);
+                line_level = level;
+                text = format!("{} {}", inst.mnemonic, label(level, depth));
+            }
+            Control::Br(depth) | Control::BrIf(depth) => {
+                text = format!("{} {}", inst.mnemonic, label(level, depth));
+            }
+            Control::BrTable(ref targets, default) => {
+                let labels = targets.iter().chain(Some(&default));
+                let labels = labels.map(|&depth| label(level, depth));
+                text = format!("{} {}", inst.mnemonic, labels.collect::<Vec<_>>().join(" "));
+            }
+            Control::Call(Some(index)) | Control::TailCall(Some(index)) => {
+                if let Some(name) = function_names.get(&Address::new(u64::from(index))) {
+                    text = format!("{} {}", inst.mnemonic, identifier(name));
+                }
+            }
+            _ => {
+                if let Some(name) = inst.local_name() {
+                    text = format!("{} {}", inst.mnemonic, identifier(name));
+                }
+            }
+        }
+        lines.push(format!("{}{}", pad(line_level + 1), text));
+    }
+    if let Some(last) = lines.last_mut() {
+        last.push(')');
+    }
+}
+
+impl<'a> fmt::Display for Wat<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let function_names = self
+            .imported_functions
+            .iter()
+            .chain(self.functions.iter().map(|function| &function.symbol))
+            .filter_map(|symbol| Some((symbol.address, symbol.name.as_deref()?)))
+            .collect::<HashMap<_, _>>();
+        let mut lines = vec![];
+        if self.is_module {
+            lines.push("(module".to_owned());
+        }
+        for (idx, function) in self.functions.iter().enumerate() {
+            let index = Some(self.imported_functions.len() + idx).filter(|_| self.is_module);
+            let indent = usize::from(self.is_module);
+            let declaration = self
+                .declarations
+                .and_then(|declarations| declarations.get(&function.symbol.address));
+            function_lines(
+                function,
+                declaration,
+                index,
+                &function_names,
+                indent,
+                &mut lines,
+            );
+        }
+        if self.is_module {
+            lines.last_mut().expect("module line").push(')');
+        }
+        for line in lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate wat;
+
+    use super::{WasmAddressing, WasmInstruction, Wat};
+    use crate::address::Address;
+    use crate::callgraphanalysis::{CallGraphAnalysis, CallSite, CallSiteTarget};
+    use crate::cfg::ExitType;
+    use crate::function::Function;
+    use crate::instruction::{BranchTarget, Instruction as _};
+    use crate::module::Module;
+    use crate::operand::{MemoryOperand, Operand, Register};
+    use crate::symbol::Symbol;
+
+    /// Load a module from the WebAssembly text format.
+    fn module(text: &str) -> Module<WasmInstruction> {
+        Module::from_wasm_bytes(&wat::parse_str(text).unwrap()).unwrap()
+    }
+
+    /// Load a function from the WebAssembly text format, given its
+    /// parameters, results and instructions.
+    fn function(text: &str) -> Function<WasmInstruction> {
+        module(&format!("(module (memory 1) (func {}))", text))
+            .functions
+            .remove(0)
+    }
+
+    fn successors(f: &Function<WasmInstruction>, address: u64) -> Vec<Address> {
+        let cfg = &f.control_flow_graph;
+        let node = cfg.block_finder[&Address::new(address)];
+        let mut successors = cfg
+            .graph
+            .neighbors(node)
+            .map(|n| cfg.graph[n].address)
+            .collect::<Vec<_>>();
+        successors.sort();
+        successors
+    }
+
+    fn addresses(addresses: &[u64]) -> Vec<Address> {
+        addresses.iter().cloned().map(Address::new).collect()
+    }
+
+    #[test]
+    fn registers() {
+        // local.get 1, local.tee 2, global.set 0, end
+        let code = [0x20, 0x01, 0x22, 0x02, 0x24, 0x00, 0x0b];
+        let f = Function::from_wasm(Symbol::new(Address::new(0), None), &code).unwrap();
+
+        let get = &f.instructions[0];
+        assert_eq!(get.registers_read(), vec![Register::Local(1)]);
+        assert!(get.registers_written().is_empty());
+        assert_eq!(get.operands(), vec![Operand::Register(Register::Local(1))]);
+
+        let tee = &f.instructions[1];
+        assert!(tee.registers_read().is_empty());
+        assert_eq!(tee.registers_written(), vec![Register::Local(2)]);
+
+        let set = &f.instructions[2];
+        assert_eq!(set.registers_written(), vec![Register::Global(0)]);
+        assert_eq!(set.to_string(), "global.set 0");
+
+        assert!(Function::from_wasm(Symbol::new(Address::new(0), None), &code[..3]).is_none());
+    }
+
+    #[test]
+    fn operands() {
+        let f = function(
+            "(param i32)
+             block
+               block
+                 i32.const 0
+                 i64.load16_u offset=24 align=1
+                 drop
+                 i32.const -7
+                 i32.const 0
+                 i32.store
+                 local.get 0
+                 br_table 0 2 1
+               end
+             end",
+        );
+
+        let load = &f.instructions[3];
+        assert_eq!(
+            load.operands(),
+            vec![Operand::Memory(MemoryOperand {
+                base: None,
+                index: None,
+                scale: 1,
+                displacement: 24,
+                size: Some(2),
+            })]
+        );
+        assert_eq!(load.to_string(), "i64.load16_u offset=24 align=1");
+
+        let constant = &f.instructions[5];
+        assert_eq!(constant.operands(), vec![Operand::Immediate(-7)]);
+
+        assert!(load.reads_memory() && !load.writes_memory());
+        let store = &f.instructions[7];
+        assert!(!store.reads_memory() && store.writes_memory());
+
+        let table = &f.instructions[9];
+        assert_eq!(
+            table.operands(),
+            vec![
+                Operand::Immediate(0),
+                Operand::Immediate(2),
+                Operand::Immediate(1)
+            ]
+        );
+        assert_eq!(table.to_string(), "br_table 0 2 1");
+    }
+
+    #[test]
+    fn block_and_loop() {
+        let f = function(
+            "(param i32)
+             block
+               loop
+                 local.get 0
+                 br_if 1
+                 br 0
+               end
+             end",
+        );
+
+        assert_eq!(f.instructions[3].target_address(), Some(Address::new(6)));
+        assert_eq!(f.instructions[4].target_address(), Some(Address::new(1)));
+        assert_eq!(successors(&f, 0), addresses(&[1]));
+        assert_eq!(successors(&f, 1), addresses(&[4, 6]));
+        assert_eq!(successors(&f, 4), addresses(&[1]));
+        assert_eq!(
+            f.control_flow_graph.return_blocks(),
+            vec![f.control_flow_graph.block_finder[&Address::new(6)]]
+        );
+    }
+
+    #[test]
+    fn if_else() {
+        let f = function(
+            "(param i32)
+             local.get 0
+             if
+               nop
+             else
+               nop
+             end
+             return",
+        );
+
+        assert_eq!(successors(&f, 0), addresses(&[2, 4]));
+        assert_eq!(successors(&f, 2), addresses(&[5]));
+        assert_eq!(successors(&f, 4), addresses(&[5]));
+        assert_eq!(f.control_flow_graph.return_blocks().len(), 2);
+
+        // Without an else, the false condition goes to the end.
+        let f = function(
+            "(param i32)
+             local.get 0
+             if
+               nop
+             end",
+        );
+        assert_eq!(successors(&f, 0), addresses(&[2, 3]));
+    }
+
+    #[test]
+    fn br_table_and_unreachable() {
+        let f = function(
+            "(param i32)
+             block
+               block
+                 local.get 0
+                 br_table 0 1 2
+               end
+               unreachable
+             end",
+        );
+
+        let a = Address::new;
+        assert!(!f.instructions[3].is_local_conditional_jump());
+        assert_eq!(
+            f.instructions[3].branch_targets(),
+            vec![
+                BranchTarget {
+                    address: a(4),
+                    case: Some(0),
+                },
+                BranchTarget {
+                    address: a(6),
+                    case: Some(1),
+                },
+                BranchTarget {
+                    address: a(7),
+                    case: None,
+                },
+            ]
+        );
+        assert_eq!(successors(&f, 0), addresses(&[4, 6, 7]));
+        assert_eq!(successors(&f, 4), addresses(&[]));
+
+        let cfg = &f.control_flow_graph;
+        assert_eq!(cfg.exit_blocks[&cfg.block_finder[&a(4)]], ExitType::Trap);
+        assert!(cfg.unresolved_blocks().is_empty());
+    }
+
+    #[test]
+    fn call_indirect_targets() {
+        let m = module(
+            "(module
+               (type $unary (func (param i32) (result i32)))
+               (type $nullary (func))
+               ;; A second type that is the same as the first.
+               (type $same (func (param i32) (result i32)))
+               (table 3 funcref)
+               (elem (i32.const 0) 0 1 2)
+               (func (type $unary) local.get 0)
+               (func (type $unary) i32.const 0)
+               (func (type $nullary))
+               (func (type $nullary)
+                 i32.const 1
+                 i32.const 0
+                 call_indirect (type $unary)
+                 drop
+                 i32.const 0
+                 call_indirect (type $nullary)
+                 i32.const 1
+                 i32.const 0
+                 call_indirect (type $same)
+                 drop))",
+        );
+        let calls = m.functions[3].identify_call_sites();
+        let unary_targets = CallSiteTarget::Candidates(vec![Address::new(0), Address::new(1)]);
+        assert_eq!(
+            calls,
+            vec![
+                CallSite {
+                    call_site_address: Address::new(2),
+                    target: unary_targets.clone(),
+                },
+                CallSite {
+                    call_site_address: Address::new(5),
+                    target: CallSiteTarget::Candidates(vec![Address::new(2)]),
+                },
+                CallSite {
+                    call_site_address: Address::new(8),
+                    target: unary_targets,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn open_tables() {
+        let calls = |wat| {
+            module(wat).functions[0]
+                .identify_call_sites()
+                .into_iter()
+                .map(|call| call.target)
+                .collect::<Vec<_>>()
+        };
+        let closed = "(module
+             (table 1 funcref)
+             (elem (i32.const 0) 0)
+             (func i32.const 0 call_indirect))";
+        assert_eq!(
+            calls(closed),
+            vec![CallSiteTarget::Candidates(vec![Address::new(0)])]
+        );
+        // The host may place any function in an exported table.
+        let exported = r#"(module
+             (table (export "table") 1 funcref)
+             (elem (i32.const 0) 0)
+             (func i32.const 0 call_indirect))"#;
+        assert_eq!(calls(exported), vec![CallSiteTarget::Indirect]);
+        let imported = r#"(module
+             (import "env" "table" (table 1 funcref))
+             (elem (i32.const 0) 0)
+             (func i32.const 0 call_indirect))"#;
+        assert_eq!(calls(imported), vec![CallSiteTarget::Indirect]);
+        let mutated = "(module
+             (table 1 funcref)
+             (elem (i32.const 0) 0)
+             (func i32.const 0 call_indirect)
+             (func (param funcref) i32.const 0 local.get 0 table.set))";
+        assert_eq!(calls(mutated), vec![CallSiteTarget::Indirect]);
+    }
+
+    #[test]
+    fn call_ref_targets() {
+        let calls = |wat| {
+            module(wat).functions[0]
+                .identify_call_sites()
+                .into_iter()
+                .map(|call| call.target)
+                .collect::<Vec<_>>()
+        };
+        let closed = "(module
+             (type $t (func))
+             (elem declare func 0)
+             (func (type $t) ref.func 0 call_ref $t))";
+        assert_eq!(
+            calls(closed),
+            vec![CallSiteTarget::Candidates(vec![Address::new(0)])]
+        );
+        // The host may pass in a function of its own.
+        let host = r#"(module
+             (type $t (func))
+             (elem declare func 0)
+             (func (type $t) ref.func 0 call_ref $t)
+             (func (export "run") (param (ref null $t)) local.get 0 call_ref $t))"#;
+        assert_eq!(calls(host), vec![CallSiteTarget::Indirect]);
+    }
+
+    #[test]
+    fn function_index_space() {
+        let m = module(
+            r#"(module
+                 (import "env" "log" (func))
+                 (func)
+                 (func (export "run")
+                   call 0
+                   call 1))"#,
+        );
+        assert_eq!(m.imported_functions.len(), 1);
+        assert_eq!(m.imported_functions[0].address, Address::new(0));
+        assert_eq!(m.imported_functions[0].name.as_deref(), Some("log"));
+        assert_eq!(m.functions[0].symbol.address, Address::new(1));
+        assert_eq!(m.functions[0].symbol.name, None);
+        assert_eq!(m.functions[1].symbol.address, Address::new(2));
+        assert_eq!(m.functions[1].symbol.name.as_deref(), Some("run"));
+        assert_eq!(
+            m.functions[1]
+                .identify_call_sites()
+                .into_iter()
+                .map(|call| call.target)
+                .collect::<Vec<_>>(),
+            vec![
+                CallSiteTarget::Direct(m.imported_functions[0].address),
+                CallSiteTarget::Direct(m.functions[0].symbol.address),
+            ]
+        );
+    }
+
+    #[test]
+    fn byte_offsets() {
+        #[rustfmt::skip]
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+            // A type section with one type, and a function section.
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00,
+            // A code section with one body and no locals.
+            0x0a, 0x0f, 0x01, 0x0d, 0x00,
+            // block
+            0x02, 0x40,
+            // i32.const 1000, padded to five bytes
+            0x41, 0xe8, 0x87, 0x80, 0x80, 0x00,
+            // br_if 0
+            0x0d, 0x00,
+            // end, end
+            0x0b, 0x0b,
+        ];
+        let offsets = |addressing| {
+            let m = Module::from_wasm_bytes_with_addressing(&bytes, addressing).unwrap();
+            let f = &m.functions[0];
+            assert_eq!(
+                f.instructions[2].target_address(),
+                Some(f.instructions[3].address())
+            );
+            f.instructions
+                .iter()
+                .map(|i| (i.address(), i.encoded_len()))
+                .collect::<Vec<_>>()
+        };
+        let a = Address::new;
+
+        assert_eq!(
+            offsets(WasmAddressing::CodeSectionOffset),
+            vec![(a(3), 2), (a(5), 6), (a(11), 2), (a(13), 1), (a(14), 1)]
+        );
+        assert_eq!(
+            offsets(WasmAddressing::ModuleOffset),
+            vec![(a(23), 2), (a(25), 6), (a(31), 2), (a(33), 1), (a(34), 1)]
+        );
+        assert_eq!(
+            offsets(WasmAddressing::Ordinal),
+            vec![(a(0), 2), (a(1), 6), (a(2), 2), (a(3), 1), (a(4), 1)]
+        );
+    }
+
+    #[test]
+    fn name_section() {
+        let m = module(
+            r#"(module $mod
+                 (func $main (export "run") (param $count i32) (local i32)
+                   local.get $count
+                   local.set 1))"#,
+        );
+        let f = &m.functions[0];
+        assert_eq!(f.symbol.name.as_deref(), Some("main"));
+        assert_eq!(f.instructions[0].local_name(), Some("count"));
+        assert_eq!(f.instructions[0].comment().as_deref(), Some("count"));
+        assert_eq!(f.instructions[1].comment(), None);
+        assert_eq!(f.instructions[2].comment(), None);
+    }
+
+    #[test]
+    fn simd_bulk_memory_and_reference_types() {
+        let f = function(
+            "(param i32) (result i32)
+             i32.const 0
+             v128.load offset=16
+             v128.const i32x4 1 2 3 4
+             i32x4.add
+             i32x4.extract_lane 3
+             i32.const 0
+             i32.const 8
+             memory.copy
+             ref.null func
+             ref.is_null
+             local.get 0
+             select (result i32)",
+        );
+        let text = f
+            .instructions
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            text,
+            vec![
+                "i32.const 0",
+                "v128.load offset=16",
+                "v128.const i32x4 0x00000001 0x00000002 0x00000003 0x00000004",
+                "i32x4.add",
+                "i32x4.extract_lane 3",
+                "i32.const 0",
+                "i32.const 8",
+                "memory.copy",
+                "ref.null func",
+                "ref.is_null",
+                "local.get 0",
+                "select (result i32)",
+                "end",
+            ]
+        );
+        assert_eq!(
+            f.instructions[1].operands(),
+            vec![Operand::Memory(MemoryOperand {
+                base: None,
+                index: None,
+                scale: 1,
+                displacement: 16,
+                size: Some(16),
+            })]
+        );
+        let copy = &f.instructions[7];
+        assert!(copy.reads_memory() && copy.writes_memory());
+    }
+
+    #[test]
+    fn atomic_memory_accesses() {
+        let f = function(
+            "i32.const 0
+             i32.atomic.load
+             i32.const 1
+             i32.atomic.rmw.add
+             i32.const 2
+             i32.atomic.store
+             i32.const 0
+             i32.const 1
+             memory.atomic.notify
+             drop",
+        );
+        let access = |i: usize| {
+            let inst = &f.instructions[i];
+            (inst.reads_memory(), inst.writes_memory())
+        };
+        assert_eq!(access(1), (true, false));
+        assert_eq!(access(3), (true, true));
+        assert_eq!(access(5), (false, true));
+        assert_eq!(access(8), (true, false));
+    }
+
+    #[test]
+    fn tail_calls() {
+        let m = module(
+            "(module
+               (type $t (func (param i32) (result i32)))
+               (table 1 funcref)
+               (elem (i32.const 0) 0)
+               (func (type $t)
+                 local.get 0
+                 if (result i32)
+                   local.get 0
+                   return_call 1
+                 else
+                   local.get 0
+                   i32.const 0
+                   return_call_indirect (type $t)
+                 end)
+               (func (type $t) local.get 0))",
+        );
+        let f = &m.functions[0];
+        assert!(f.instructions[3].is_tail_call() && !f.instructions[3].is_call());
+        assert_eq!(
+            f.identify_call_sites(),
+            vec![
+                CallSite {
+                    call_site_address: Address::new(3),
+                    target: CallSiteTarget::Direct(Address::new(1)),
+                },
+                CallSite {
+                    call_site_address: Address::new(7),
+                    target: CallSiteTarget::Candidates(vec![Address::new(0)]),
+                },
+            ]
+        );
+        let cfg = &f.control_flow_graph;
+        for address in [2, 5] {
+            let block = cfg.block_finder[&Address::new(address)];
+            assert_eq!(cfg.exit_blocks[&block], ExitType::TailCall);
+            assert_eq!(cfg.graph.neighbors(block).count(), 0);
+        }
+        // The callee's index does not split the block at that address.
+        assert!(!cfg.block_finder.contains_key(&Address::new(1)));
+    }
+
+    #[test]
+    fn call_targets_are_not_leaders() {
+        let m = module(
+            "(module
+               (func $f (param i32) (result i32)
+                 local.get 0
+                 i32.const 1
+                 i32.add
+                 call $b
+                 drop
+                 local.get 0)
+               (func $b (param i32) (result i32) local.get 0))",
+        );
+        let cfg = &m.functions[0].control_flow_graph;
+        assert_eq!(cfg.graph.node_count(), 2);
+        let entry = cfg.entry_block.unwrap();
+        assert_eq!(cfg.graph[entry].instruction_indices, vec![0, 1, 2, 3]);
+        assert!(cfg.block_finder.contains_key(&Address::new(4)));
+    }
+
+    #[test]
+    fn try_catch() {
+        let m = module(
+            "(module
+               (tag $e)
+               (func $f)
+               (func
+                 try
+                   call $f
+                   throw $e
+                 catch $e
+                   rethrow 0
+                 catch_all
+                   nop
+                 end))",
+        );
+        let f = &m.functions[1];
+        let a = Address::new;
+        let handlers = vec![a(4), a(6)];
+        assert_eq!(f.instructions[1].exception_handlers(), handlers);
+        assert!(f.instructions[2].is_throw());
+        assert_eq!(f.instructions[2].exception_handlers(), handlers);
+        // The handlers of a try do not cover each other.
+        assert!(f.instructions[4].exception_handlers().is_empty());
+        assert_eq!(f.instructions[3].target_address(), Some(a(7)));
+
+        assert_eq!(successors(f, 0), addresses(&[2, 4, 6]));
+        assert_eq!(successors(f, 2), addresses(&[4, 6]));
+        assert_eq!(successors(f, 4), addresses(&[]));
+        assert_eq!(successors(f, 6), addresses(&[7]));
+        let cfg = &f.control_flow_graph;
+        assert_eq!(cfg.exit_blocks[&cfg.block_finder[&a(4)]], ExitType::Throw);
+        assert!(!cfg.exit_blocks.contains_key(&cfg.block_finder[&a(2)]));
+    }
+
+    #[test]
+    fn try_delegate() {
+        let m = module(
+            "(module
+               (tag $e)
+               (func
+                 try
+                   try
+                     throw $e
+                   delegate 0
+                 catch $e
+                   nop
+                 end))",
+        );
+        let f = &m.functions[0];
+        assert_eq!(
+            f.instructions[2].exception_handlers(),
+            vec![Address::new(5)]
+        );
+    }
+
+    #[test]
+    fn try_table() {
+        let m = module(
+            "(module
+               (tag $e (param i32))
+               (func $f)
+               (func (result i32)
+                 block $caught (result i32)
+                   try_table (catch $e $caught)
+                     call $f
+                   end
+                   i32.const 0
+                 end))",
+        );
+        let f = &m.functions[1];
+        assert_eq!(f.instructions[1].to_string(), "try_table (catch 0 0)");
+        assert_eq!(
+            f.instructions[2].exception_handlers(),
+            vec![Address::new(5)]
+        );
+        assert_eq!(successors(f, 0), addresses(&[3, 5]));
+    }
+
+    #[test]
+    fn text_format() {
+        let m = module(
+            r#"(module
+                (import "env" "log" (func $log (param i32)))
+                (func $count (param $n i32) (result i32) (local $i i32) (local i64 i64)
+                    block $done
+                        loop $again
+                            local.get $i
+                            local.get $n
+                            i32.ge_u
+                            br_if $done
+                            local.get $i
+                            call $log
+                            local.get $i
+                            i32.const 1
+                            i32.add
+                            local.set $i
+                            br $again
+                        end
+                    end
+                    local.get $i
+                    if (result i32)
+                        i32.const 1
+                    else
+                        i32.const 2
+                    end)
+                (func (result i32)
+                    i32.const 0
+                    call $count)
+                (func))"#,
+        );
+        assert_eq!(
+            Wat::module(&m).to_string(),
+            "(module
+  (func $count (type 1) (param $n i32) (result i32)
+    (local $i i32) (local i64 i64)
+    block  ;; label = @1
+      loop  ;; label = @2
+        local.get $i
+        local.get $n
+        i32.ge_u
+        br_if 1 (;@1;)
+        local.get $i
+        call $log
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br 0 (;@2;)
+      end
+    end
+    local.get $i
+    if (result i32)  ;; label = @1
+      i32.const 1
+    else
+      i32.const 2
+    end)
+  (func (;2;) (type 2) (result i32)
+    i32.const 0
+    call $count)
+  (func (;3;) (type 3)))
+"
+        );
+
+        // Without the module, neither the declaration nor the other
+        // functions are known.
+        let text = Wat::new(&m.functions[1]).to_string();
+        assert_eq!(text, "(func\n  i32.const 0\n  call 1)\n");
+
+        // A function that was not loaded from a module has no declaration.
+        let code = [0x02, 0x40, 0x0c, 0x01, 0x0b, 0x0b];
+        let f = Function::from_wasm(Symbol::new(Address::new(0), Some("f")), &code).unwrap();
+        assert_eq!(
+            Wat::new(&f).to_string(),
+            "(func $f\n  block  ;; label = @1\n    br 1 (;@0;)\n  end)\n"
+        );
     }
 }
@@ -17,25 +17,35 @@
     unused_qualifications
 )]
 
+extern crate clap;
 extern crate disassemble;
 
-use disassemble::{Address, Function, Symbol};
+use disassemble::{Instruction, Module};
 
 fn main() {
-    let prog = &[
-        0xb7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // mov r0, 0
-        0x79, 0x12, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // load ptr from r1[0x40] to r2
-        0x07, 0x02, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, // add r2, 5
-        0x79, 0x11, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, // load ptr from r1[0x50] to r1
-        0x2d, 0x12, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, // if r2 > r1 skip 3 instructions
-        0x71, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // load r2 (= *(mem + 5)) into r0
-        0x67, 0x00, 0x00, 0x00, 0x38, 0x00, 0x00, 0x00, // r0 >>= 56
-        0xc7, 0x00, 0x00, 0x00, 0x38, 0x00, 0x00, 0x00, // r0 <<= 56 (arsh)
-        0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
-    ];
+    let matches = clap::App::new("disassemble-bpf")
+        .version("0.0.1")
+        .author("Bruce Mitchener, Jr.")
+        .about("Disassemble eBPF ELF object files")
+        .arg(
+            clap::Arg::with_name("FILE")
+                .help("The eBPF ELF object file to use.")
+                .required(true)
+                .index(1),
+        )
+        .get_matches();
 
-    let f = Function::from_bpf(Symbol::new(Address::new(100000), Some("test")), prog);
-    for i in f.instructions {
-        println!("{}", i);
+    let file = matches.value_of("FILE").unwrap();
+
+    if let Some(m) = Module::from_bpf_file(file) {
+        for f in m.functions {
+            println!("{}:", f.symbol.name.unwrap_or_default());
+            for i in f.instructions {
+                match i.comment() {
+                    Some(comment) => println!("    {} ; {}", i, comment),
+                    None => println!("    {}", i),
+                }
+            }
+        }
     }
 }
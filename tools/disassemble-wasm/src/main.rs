@@ -20,7 +20,7 @@
 extern crate clap;
 extern crate disassemble;
 
-use disassemble::Module;
+use disassemble::{Module, Wat};
 
 fn main() {
     let matches = clap::App::new("disassemble-wasm")
@@ -38,10 +38,6 @@ fn main() {
     let file = matches.value_of("FILE").unwrap();
 
     if let Some(m) = Module::from_wasm_file(file) {
-        for f in m.functions {
-            for i in f.instructions {
-                println!("{}", i);
-            }
-        }
+        print!("{}", Wat::module(&m));
     }
 }